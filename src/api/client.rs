@@ -1,4 +1,6 @@
 use anyhow::Context;
+use crate::budget::tokens::TokenUsage;
+use crate::errors::EctusError;
 use reqwest::Client;
 use serde_json::Value;
 
@@ -28,9 +30,16 @@ impl ApiClient {
         let status = res.status();
         let text = res.text().await.unwrap_or_default();
         if !status.is_success() {
-            anyhow::bail!("status {}: {}", status, text);
+            return Err(EctusError::Http(format!("status {}: {}", status, text)).into());
         }
         let val: Value = serde_json::from_str(&text).unwrap_or(Value::String(text));
         Ok(val)
     }
+
+    /// `post_json` plus the token usage the response reports, if any.
+    pub async fn post_metered(&self, path: &str, body: &Value) -> anyhow::Result<(Value, Option<TokenUsage>)> {
+        let val = self.post_json(path, body).await?;
+        let usage = TokenUsage::from_response(&val);
+        Ok((val, usage))
+    }
 }
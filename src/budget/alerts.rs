@@ -0,0 +1,261 @@
+//! Spend alerts: crossing a percentage of a limit, or a month-end forecast above it, is announced once
+//! per month to each webhook (generic JSON or Slack) and to the MCP client as `notifications/message`.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::budget::hierarchy::GLOBAL;
+use crate::budget::ledger::Spend;
+use crate::budget::money::{self, Decimal};
+use crate::state::AppState;
+
+const TICK: Duration = Duration::from_secs(60);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format { Json, Slack }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub format: Format,
+    pub url: String,
+}
+
+/// `https://hooks.slack.com/...,json:https://ops.example/hook`: an optional `json:`/`slack:` prefix picks
+/// the payload; unprefixed Slack hook URLs get Slack payloads, everything else generic JSON.
+pub fn parse_webhooks(s: &str) -> anyhow::Result<Vec<Webhook>> {
+    s.split(',').map(str::trim).filter(|e| !e.is_empty()).map(|e| {
+        let (format, url) = match e.split_once(':') {
+            Some(("json", url)) => (Format::Json, url),
+            Some(("slack", url)) => (Format::Slack, url),
+            _ if e.contains("hooks.slack.com/") => (Format::Slack, e),
+            _ => (Format::Json, e),
+        };
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            anyhow::bail!("BUDGET_ALERT_WEBHOOKS: `{}` is not an http(s) URL", url);
+        }
+        Ok(Webhook { format, url: url.to_string() })
+    }).collect()
+}
+
+/// `50,80,100,forecast`: percentages of the limit (sorted, deduplicated) and whether to alert on the forecast.
+pub fn parse_thresholds(s: &str) -> anyhow::Result<(Vec<u32>, bool)> {
+    let mut forecast = false;
+    let mut out = Vec::new();
+    for t in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        if t.eq_ignore_ascii_case("forecast") { forecast = true; continue; }
+        out.push(t.trim_end_matches('%').parse::<u32>().ok().filter(|t| *t > 0).ok_or_else(|| anyhow::anyhow!("BUDGET_ALERT_THRESHOLDS: bad percentage `{}`", t))?);
+    }
+    out.sort_unstable();
+    out.dedup();
+    Ok((out, forecast))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub budget: String,
+    /// `threshold` or `forecast`
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_pct: Option<u32>,
+    pub month: String,
+    pub month_to_date_usd: Decimal,
+    pub projected_eom_usd: Decimal,
+    pub monthly_usd_limit: Decimal,
+    pub currency: String,
+}
+
+impl Alert {
+    fn key(&self) -> String {
+        format!("{}|{}|{}", self.month, self.budget, self.threshold_pct.map(|t| t.to_string()).unwrap_or_else(|| self.kind.into()))
+    }
+
+    pub fn text(&self) -> String {
+        let (spent, limit, projected) = (money::format(self.month_to_date_usd, 2), money::format(self.monthly_usd_limit, 2), money::format(self.projected_eom_usd, 2));
+        match self.threshold_pct {
+            Some(pct) => format!("Budget {} reached {}% of its {} limit for {}: {} spent, {} projected by month end", self.budget, pct, limit, self.month, spent, projected),
+            None => format!("Budget {} is forecast to exceed its {} limit for {}: {} projected by month end ({} spent so far)", self.budget, limit, self.month, projected, spent),
+        }
+    }
+
+    pub fn payload(&self, format: Format) -> Value {
+        match format {
+            Format::Json => json!({"source": "ectusr2", "text": self.text(), "alert": self}),
+            Format::Slack => json!({"text": format!(":warning: {}", self.text())}),
+        }
+    }
+}
+
+/// Alerts for `budget` not yet in `sent`: the highest threshold crossed by month-to-date spend
+/// (lower ones are implied), and a forecast above the limit.
+pub fn due(budget: &str, spend: &Spend, limit: Decimal, thresholds: &[u32], sent: &BTreeSet<String>) -> Vec<Alert> {
+    if limit <= Decimal::ZERO { return Vec::new(); }
+    let alert = |kind, threshold_pct| Alert {
+        budget: budget.into(), kind, threshold_pct, month: spend.month.clone(), month_to_date_usd: spend.month_to_date_usd,
+        projected_eom_usd: spend.projected_eom_usd, monthly_usd_limit: limit, currency: spend.currency.clone(),
+    };
+    let pct = spend.month_to_date_usd * Decimal::from(100) / limit;
+    let crossed = thresholds.iter().rev().find(|t| pct >= Decimal::from(**t)).copied();
+    let mut out = Vec::new();
+    // an alert for a higher threshold stands in for the lower ones
+    if let Some(t) = crossed {
+        let higher_sent = thresholds.iter().filter(|h| **h >= t).any(|h| sent.iter().any(|k| k.ends_with(&format!("|{}", alert("threshold", Some(*h)).key()))));
+        if !higher_sent { out.push(alert("threshold", Some(t))); }
+    }
+    if spend.projected_eom_usd > limit {
+        let a = alert("forecast", None);
+        if !sent.iter().any(|k| k.ends_with(&format!("|{}", a.key()))) { out.push(a); }
+    }
+    out
+}
+
+pub struct Alerter {
+    thresholds: Vec<u32>,
+    forecast: bool,
+    webhooks: Vec<Webhook>,
+    /// `<destination>|<month>|<budget>|<threshold or kind>` already delivered
+    sent: Mutex<BTreeSet<String>>,
+    path: Option<PathBuf>,
+    http: reqwest::Client,
+}
+
+impl Alerter {
+    /// `path` keeps the delivered set across restarts.
+    pub fn new(thresholds: Vec<u32>, forecast: bool, webhooks: Vec<Webhook>, path: Option<PathBuf>) -> Self {
+        let sent = path.as_deref().and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|t| serde_json::from_str(&t).ok()).unwrap_or_default();
+        let http = reqwest::Client::builder().user_agent("ectusr2/0.1").timeout(TIMEOUT).build().expect("reqwest client");
+        Self { thresholds, forecast, webhooks, sent: Mutex::new(sent), path, http }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.thresholds.is_empty() || self.forecast
+    }
+
+    fn was_sent(&self, dest: &str, alert: &Alert) -> bool {
+        self.sent.lock().unwrap().contains(&format!("{}|{}", dest, alert.key()))
+    }
+
+    fn mark(&self, dest: &str, alert: &Alert) {
+        let mut sent = self.sent.lock().unwrap();
+        sent.insert(format!("{}|{}", dest, alert.key()));
+        // older months can no longer repeat
+        sent.retain(|k| k.split('|').nth(1) == Some(alert.month.as_str()));
+        if let Some(path) = &self.path {
+            if let Err(e) = std::fs::write(path, serde_json::to_vec(&*sent).unwrap_or_default()) {
+                warn!(path = %path.display(), error = %e, "could not persist sent budget alerts");
+            }
+        }
+    }
+
+    /// Alerts due now for every destination, across the global limit and each named budget.
+    fn pending(&self, state: &AppState) -> anyhow::Result<Vec<Alert>> {
+        let settings = state.budget.current();
+        let mut budgets: Vec<(String, Decimal, Spend)> = Vec::new();
+        if let Some(limit) = settings.monthly_usd_limit {
+            budgets.push((GLOBAL.into(), limit, state.ledger.spend()?));
+        }
+        for (name, b) in &settings.budgets.budgets {
+            if let Some(limit) = b.monthly_usd_limit {
+                budgets.push((name.clone(), limit, state.ledger.spend_in(name, None)?));
+            }
+        }
+        let sent = self.sent.lock().unwrap().clone();
+        // an alert is pending while any destination still lacks it
+        let dests: Vec<String> = self.webhooks.iter().map(|w| w.url.clone()).chain(["mcp".to_string()]).collect();
+        let mut out = Vec::new();
+        for (name, limit, spend) in budgets {
+            for dest in &dests {
+                let seen: BTreeSet<String> = sent.iter().filter(|k| k.starts_with(&format!("{}|", dest))).cloned().collect();
+                for a in due(&name, &spend, limit, &self.thresholds, &seen) {
+                    if (a.kind == "threshold" || self.forecast) && !out.contains(&a) { out.push(a); }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    async fn deliver(&self, alert: &Alert) {
+        let mut delivered = 0;
+        for hook in &self.webhooks {
+            if self.was_sent(&hook.url, alert) { continue; }
+            let res = self.http.post(&hook.url).json(&alert.payload(hook.format)).send().await.and_then(|r| r.error_for_status());
+            match res {
+                Ok(_) => { self.mark(&hook.url, alert); delivered += 1; }
+                // retried on the next tick
+                Err(e) => warn!(budget = %alert.budget, error = %e, "budget alert webhook failed"),
+            }
+        }
+        // held back until a client has initialized
+        if !self.was_sent("mcp", alert) && crate::mcp::server::notify("notifications/message", json!({"level": "warning", "logger": "ectusr2.budget", "data": {"text": alert.text(), "alert": alert}})) {
+            self.mark("mcp", alert);
+            delivered += 1;
+        }
+        if delivered > 0 {
+            info!(budget = %alert.budget, kind = alert.kind, threshold = ?alert.threshold_pct, delivered, "budget alert raised");
+        }
+    }
+}
+
+/// Check spend against the alert thresholds every minute.
+pub async fn run(state: Arc<AppState>) {
+    if !state.alerts.enabled() { return; }
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tick.tick().await;
+        match state.alerts.pending(&state) {
+            Ok(alerts) => for a in &alerts { state.alerts.deliver(a).await },
+            Err(e) => warn!(error = %e, "budget alerts: spend unavailable"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spend(mtd: i64, eom: i64) -> Spend {
+        Spend {
+            month: "2025-03".into(), currency: "USD".into(), month_to_date_usd: mtd.into(), compute_usd: mtd.into(), charges_usd: Decimal::ZERO,
+            current_hourly_usd: Decimal::ZERO, projected_eom_usd: eom.into(), forecast: Default::default(), running_pools: Vec::new(), models: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_due_thresholds_and_dedup() {
+        let limit = Decimal::from(1000);
+        let t = [50, 80, 100];
+        assert!(due("acme", &spend(400, 900), limit, &t, &BTreeSet::new()).is_empty());
+
+        // jumping past 50% and 80% at once raises only 80%, plus the forecast
+        let a = due("acme", &spend(850, 1200), limit, &t, &BTreeSet::new());
+        assert_eq!(a.iter().map(|a| (a.kind, a.threshold_pct)).collect::<Vec<_>>(), [("threshold", Some(80)), ("forecast", None)]);
+        let sent: BTreeSet<String> = a.iter().map(|a| format!("mcp|{}", a.key())).collect();
+        assert!(due("acme", &spend(900, 1200), limit, &t, &sent).is_empty());
+        assert_eq!(due("acme", &spend(1000, 1200), limit, &t, &sent)[0].threshold_pct, Some(100));
+        // a new month starts over
+        let april = Spend { month: "2025-04".into(), ..spend(600, 900) };
+        assert_eq!(due("acme", &april, limit, &t, &sent)[0].threshold_pct, Some(50));
+    }
+
+    #[test]
+    fn test_webhooks_and_payloads() {
+        let hooks = parse_webhooks("https://hooks.slack.com/services/T/B/x, json:https://ops.example/hook,slack:http://chat.local/in").unwrap();
+        assert_eq!(hooks.iter().map(|h| h.format).collect::<Vec<_>>(), [Format::Slack, Format::Json, Format::Slack]);
+        assert_eq!(hooks[1].url, "https://ops.example/hook");
+        assert!(parse_webhooks("ftp://x").is_err());
+        assert_eq!(parse_thresholds("100, 50%,80,50").unwrap(), (vec![50, 80, 100], false));
+        assert_eq!(parse_thresholds("forecast").unwrap(), (vec![], true));
+        assert!(parse_thresholds("0").is_err());
+
+        let a = &due(GLOBAL, &spend(800, 950), Decimal::from(1000), &[80], &BTreeSet::new())[0];
+        assert_eq!(a.payload(Format::Slack)["text"], ":warning: Budget (global) reached 80% of its $1000.00 limit for 2025-03: $800.00 spent, $950.00 projected by month end");
+        assert_eq!(a.payload(Format::Json)["alert"]["threshold_pct"], 80);
+        assert_eq!(a.payload(Format::Json)["alert"]["monthly_usd_limit"], 1000.0);
+    }
+}
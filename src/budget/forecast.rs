@@ -0,0 +1,171 @@
+//! Month-end forecast: pools running now cost their rate for the rest of the month while their schedule
+//! allows and until their expected end; one-off charges follow their daily history (linear trend and
+//! weekday pattern). The range reflects how much daily spend has varied around that model.
+
+use serde::Serialize;
+
+use crate::budget::money::{dec, hours_from_ms, Decimal};
+
+/// Complete days of history looked at.
+pub const HISTORY_DAYS: u64 = 28;
+pub const DAY_MS: u64 = 86_400_000;
+/// Fewer days than this keep charges at the month's average pace.
+const MIN_HISTORY: usize = 3;
+const TREND_HISTORY: usize = 7;
+const WEEKDAY_HISTORY: usize = 14;
+/// Two-sided 80% normal interval.
+const Z80: f64 = 1.2816;
+
+/// Spend on one complete UTC day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Day {
+    pub start_ms: u64,
+    pub compute: Decimal,
+    pub charges: Decimal,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Forecast {
+    pub projected_eom_usd: Decimal,
+    /// 80% range of the month-end spend; never below month-to-date
+    pub low_usd: Decimal,
+    pub high_usd: Decimal,
+    /// Running pools for the rest of the month, as scheduled
+    pub committed_usd: Decimal,
+    /// Charges expected for the rest of the month
+    pub variable_usd: Decimal,
+    /// Change in daily charges per day, from the trend
+    pub trend_usd_per_day: Decimal,
+    pub history_days: u32,
+    /// `month_pace`, `mean`, `trend` or `trend_weekday`
+    pub method: &'static str,
+}
+
+/// The month so far and the pools' scheduled cost for the rest of it.
+pub struct Inputs<'a> {
+    pub now: u64,
+    pub month_start: u64,
+    pub month_end: u64,
+    pub month_to_date: Decimal,
+    pub month_charges: Decimal,
+    pub committed: Decimal,
+    /// Consecutive complete days ending before today, oldest first
+    pub history: &'a [Day],
+}
+
+/// Monday = 0, in UTC.
+fn weekday(ms: u64) -> usize {
+    ((ms / DAY_MS + 3) % 7) as usize // 1970-01-01 was a Thursday
+}
+
+fn std_dev(xs: impl Iterator<Item = f64> + Clone) -> f64 {
+    let n = xs.clone().count();
+    if n < 2 { return 0.0; }
+    let mean = xs.clone().sum::<f64>() / n as f64;
+    (xs.map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+}
+
+pub fn forecast(i: &Inputs) -> Forecast {
+    let remaining_h = hours_from_ms(i.month_end.saturating_sub(i.now));
+    let n = i.history.len();
+    let base = |variable: Decimal, trend: f64, band: f64, method| {
+        let projected = i.month_to_date + i.committed + variable;
+        let band = dec(band).round_dp(2);
+        Forecast {
+            projected_eom_usd: projected, low_usd: (projected - band).max(i.month_to_date), high_usd: projected + band,
+            committed_usd: i.committed, variable_usd: variable, trend_usd_per_day: dec(trend).round_dp(4), history_days: n as u32, method,
+        }
+    };
+    if n < MIN_HISTORY {
+        let elapsed_h = hours_from_ms(i.now.saturating_sub(i.month_start)).max(Decimal::ONE);
+        return base(i.month_charges / elapsed_h * remaining_h, 0.0, 0.0, "month_pace");
+    }
+
+    let y: Vec<f64> = i.history.iter().map(|d| f64::try_from(d.charges).unwrap_or_default()).collect();
+    let mean = y.iter().sum::<f64>() / n as f64;
+    // least squares over the day index; too short a history only gives the mean
+    let (a, b) = if n >= TREND_HISTORY {
+        let t_mean = (n - 1) as f64 / 2.0;
+        let sxx: f64 = (0..n).map(|t| (t as f64 - t_mean).powi(2)).sum();
+        let sxy: f64 = (0..n).map(|t| (t as f64 - t_mean) * (y[t] - mean)).sum();
+        let b = sxy / sxx;
+        (mean - b * t_mean, b)
+    } else {
+        (mean, 0.0)
+    };
+    let mut factor = [1.0; 7];
+    if n >= WEEKDAY_HISTORY && mean > 0.0 {
+        for (wd, f) in factor.iter_mut().enumerate() {
+            let on: Vec<f64> = i.history.iter().zip(&y).filter(|(d, _)| weekday(d.start_ms) == wd).map(|(_, y)| *y).collect();
+            if !on.is_empty() { *f = on.iter().sum::<f64>() / on.len() as f64 / mean; }
+        }
+    }
+    let predict = |t: usize, day_ms: u64| (a + b * t as f64).max(0.0) * factor[weekday(day_ms)];
+    let residual = ((0..n).map(|t| (y[t] - predict(t, i.history[t].start_ms)).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt();
+
+    // the rest of today, then every day to the end of the month
+    let today = i.now - i.now % DAY_MS;
+    let (mut variable, mut days) = (0.0, 0.0);
+    let mut day = today;
+    while day < i.month_end {
+        let frac = (day + DAY_MS).min(i.month_end).saturating_sub(day.max(i.now)) as f64 / DAY_MS as f64;
+        variable += predict(n + ((day - today) / DAY_MS) as usize, day) * frac;
+        days += frac;
+        day += DAY_MS;
+    }
+    let compute_sd = std_dev(i.history.iter().map(|d| f64::try_from(d.compute).unwrap_or_default()));
+    let band = Z80 * days.sqrt() * (residual.powi(2) + compute_sd.powi(2)).sqrt();
+    let method = if factor != [1.0; 7] { "trend_weekday" } else if n >= TREND_HISTORY { "trend" } else { "mean" };
+    base(dec(variable).round_dp(4), b, band, method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2025-03-01T00:00:00Z, a Saturday
+    const MARCH: u64 = 1_740_787_200_000;
+
+    fn history(days: u64, charges: impl Fn(u64) -> i64) -> Vec<Day> {
+        (0..days).map(|d| Day { start_ms: MARCH - (days - d) * DAY_MS, compute: Decimal::from(24), charges: charges(d).into() }).collect()
+    }
+
+    fn inputs(history: &[Day]) -> Inputs<'_> {
+        Inputs {
+            now: MARCH, month_start: MARCH, month_end: MARCH + 31 * DAY_MS, month_to_date: Decimal::ZERO,
+            month_charges: Decimal::ZERO, committed: Decimal::from(744), history,
+        }
+    }
+
+    #[test]
+    fn test_forecast_trend_and_range() {
+        // flat charges and compute: no spread, so the range collapses onto the projection
+        let flat = history(7, |_| 10);
+        let f = forecast(&inputs(&flat));
+        assert_eq!((f.method, f.variable_usd, f.trend_usd_per_day), ("trend", Decimal::from(310), Decimal::ZERO));
+        assert_eq!((f.projected_eom_usd, f.low_usd, f.high_usd), (Decimal::from(1054), Decimal::from(1054), Decimal::from(1054)));
+
+        // +1 a day over the last week: 17, 18, ... for the 31 days of March
+        let rising = history(7, |d| 10 + d as i64);
+        let f = forecast(&inputs(&rising));
+        assert_eq!(f.trend_usd_per_day, Decimal::ONE);
+        assert_eq!(f.variable_usd, Decimal::from((17..17 + 31).sum::<i64>()));
+
+        let noisy = history(7, |d| if d % 2 == 0 { 5 } else { 15 });
+        let f = forecast(&inputs(&noisy));
+        assert!(f.low_usd < f.projected_eom_usd && f.projected_eom_usd < f.high_usd);
+    }
+
+    #[test]
+    fn test_forecast_weekday_pattern_and_fallback() {
+        // charges only on weekdays: the weekend of 1-2 March gets none
+        let weekdays = history(14, |d| if weekday(MARCH - (14 - d) * DAY_MS) < 5 { 10 } else { 0 });
+        let f = forecast(&Inputs { month_end: MARCH + 2 * DAY_MS, ..inputs(&weekdays) });
+        assert_eq!((f.method, f.variable_usd), ("trend_weekday", Decimal::ZERO));
+
+        let short = history(2, |_| 10);
+        let f = forecast(&Inputs { now: MARCH + 10 * 3_600_000, month_charges: Decimal::from(5), ..inputs(&short) });
+        assert_eq!(f.method, "month_pace");
+        assert_eq!(f.variable_usd, Decimal::new(5, 1) * Decimal::from(31 * 24 - 10));
+    }
+}
@@ -0,0 +1,164 @@
+//! Named budgets in an org → team → project tree (`acme/ml/chat`); spend rolls up to every ancestor.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::budget::money::{self, Decimal};
+use crate::budget::{BudgetPolicy, PolicyKind};
+
+/// Pool label naming the budget a pool is billed to; label values cannot hold `/`, so `.` separates levels.
+pub const POOL_LABEL: &str = "ectusr2.io/budget";
+/// The server-wide limit where budgets are listed by name.
+pub const GLOBAL: &str = "(global)";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    #[serde(default, alias = "monthly_limit", with = "money::exact::option")]
+    pub monthly_usd_limit: Option<Decimal>,
+    /// `hard` or `soft` (default)
+    #[serde(default)]
+    pub policy: Option<String>,
+    /// Currency of the limit; default: the reporting currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+impl Budget {
+    pub fn policy(&self) -> BudgetPolicy {
+        let kind = if self.policy.as_deref().is_some_and(|p| p.eq_ignore_ascii_case("hard")) { PolicyKind::Hard } else { PolicyKind::Soft };
+        BudgetPolicy { monthly_usd_limit: self.monthly_usd_limit, policy: Some(kind) }
+    }
+
+    /// For outputs and the audit trail, with the limit as a number like other amounts.
+    pub fn describe(&self) -> Value {
+        json!({"monthly_usd_limit": self.monthly_usd_limit, "policy": self.policy})
+    }
+}
+
+/// `BUDGETS`/`BUDGETS_FILE`: budgets by path, MCP client name to budget, and the budget for everything else.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budgets {
+    #[serde(default)]
+    pub budgets: BTreeMap<String, Budget>,
+    #[serde(default)]
+    pub clients: BTreeMap<String, String>,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl Budgets {
+    /// Parse and validate; limits are converted to the reporting currency.
+    pub fn parse(text: &str, toml_format: bool) -> anyhow::Result<Self> {
+        let raw: Budgets = if toml_format { toml::from_str(text)? } else { serde_json::from_str(text)? };
+        let fx = money::fx();
+        let mut budgets = BTreeMap::new();
+        for (path, mut b) in raw.budgets {
+            let key = normalize(&path).map_err(|e| anyhow::anyhow!("budgets: {}", e))?;
+            if let Some(p) = b.policy.as_deref().filter(|p| !p.eq_ignore_ascii_case("hard") && !p.eq_ignore_ascii_case("soft")) {
+                anyhow::bail!("budgets: `{}` has policy `{}` (hard or soft)", key, p);
+            }
+            if let Some(limit) = b.monthly_usd_limit {
+                if limit.is_sign_negative() { anyhow::bail!("budgets: `{}` has a negative limit", key); }
+                b.monthly_usd_limit = Some(fx.to_base(limit, b.currency.as_deref().unwrap_or(&fx.base)).map_err(|e| anyhow::anyhow!("budgets: `{}`: {}", key, e))?);
+                b.currency = None;
+            }
+            if budgets.insert(key.clone(), b).is_some() { anyhow::bail!("budgets: `{}` is listed twice", key); }
+        }
+        let mut out = Budgets { budgets, ..Default::default() };
+        for (client, path) in raw.clients {
+            out.clients.insert(client.clone(), out.known(path.as_str()).map_err(|e| anyhow::anyhow!("budgets: client `{}`: {}", client, e))?);
+        }
+        out.default = raw.default.map(|d| out.known(&d)).transpose().map_err(|e| anyhow::anyhow!("budgets: default: {}", e))?;
+        Ok(out)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("BUDGETS_FILE {}: {}", path.display(), e))?;
+        Self::parse(&text, path.extension().is_some_and(|e| e == "toml"))
+    }
+
+    /// `path` normalized, if it names a configured budget.
+    pub fn known(&self, path: &str) -> Result<String, String> {
+        let key = normalize(path)?;
+        if self.budgets.contains_key(&key) { Ok(key) } else { Err(format!("unknown budget `{}`", key)) }
+    }
+
+    /// The budget to bill: explicit argument, then pool label (or the pool's earlier booking), then client mapping, then default.
+    pub fn resolve(&self, arg: Option<&str>, pool: Option<&str>, client: Option<&str>) -> Result<Option<String>, String> {
+        if let Some(path) = arg.or(pool) {
+            return self.known(path).map(Some);
+        }
+        Ok(client.and_then(|c| self.clients.get(c)).or(self.default.as_ref()).cloned())
+    }
+
+    /// For outputs: the budgets as `Budget::describe` shows them, client mappings and the default.
+    pub fn describe(&self) -> Value {
+        let budgets: BTreeMap<&str, Value> = self.budgets.iter().map(|(k, b)| (k.as_str(), b.describe())).collect();
+        json!({"budgets": budgets, "clients": self.clients, "default": self.default})
+    }
+
+    /// Configured budgets from the root down to `path`.
+    pub fn chain<'a>(&'a self, path: &str) -> Vec<(&'a str, &'a Budget)> {
+        let mut out = Vec::new();
+        let mut end = 0;
+        while end < path.len() {
+            end = path[end..].find('/').map(|i| end + i).unwrap_or(path.len());
+            if let Some((k, b)) = self.budgets.get_key_value(&path[..end]) { out.push((k.as_str(), b)); }
+            end += 1;
+        }
+        out
+    }
+}
+
+/// `acme/ml/chat`; `acme.ml.chat` (label form) is accepted too.
+pub fn normalize(path: &str) -> Result<String, String> {
+    let parts: Vec<&str> = path.trim().split(['/', '.']).map(str::trim).collect();
+    if parts.iter().any(|p| p.is_empty()) { return Err(format!("bad budget path `{}`", path)); }
+    Ok(parts.join("/"))
+}
+
+/// `path` is `prefix` or below it.
+pub fn within(path: &str, prefix: &str) -> bool {
+    path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "budgets": {"acme": {"monthly_usd_limit": 1000, "policy": "hard"}, "acme/ml": {}, "acme/ml/chat": {"monthly_limit": 100}},
+        "clients": {"cursor": "acme.ml"},
+        "default": "acme"
+    }"#;
+
+    #[test]
+    fn test_budgets_resolve_and_chain() {
+        let b = Budgets::parse(JSON, false).unwrap();
+        assert_eq!(b.clients["cursor"], "acme/ml");
+        assert_eq!(b.resolve(Some("acme/ml/chat"), Some("acme"), None).unwrap().as_deref(), Some("acme/ml/chat"));
+        assert_eq!(b.resolve(None, Some("acme.ml.chat"), Some("cursor")).unwrap().as_deref(), Some("acme/ml/chat"));
+        assert_eq!(b.resolve(None, None, Some("cursor")).unwrap().as_deref(), Some("acme/ml"));
+        assert_eq!(b.resolve(None, None, Some("other")).unwrap().as_deref(), Some("acme"));
+        assert!(b.resolve(Some("acme/web"), None, None).is_err());
+
+        let chain: Vec<&str> = b.chain("acme/ml/chat").into_iter().map(|(k, _)| k).collect();
+        assert_eq!(chain, ["acme", "acme/ml", "acme/ml/chat"]);
+        assert_eq!(b.chain("acme/ml/chat")[0].1.policy().policy, Some(PolicyKind::Hard));
+        assert_eq!(b.chain("acme/ml/chat")[2].1.policy().policy, Some(PolicyKind::Soft));
+        assert!(within("acme/ml/chat", "acme/ml") && !within("acme/mlops", "acme/ml"));
+    }
+
+    #[test]
+    fn test_budgets_reject_bad_config() {
+        assert!(Budgets::parse(r#"{"budgets": {"a": {}}, "clients": {"c": "b"}}"#, false).unwrap_err().to_string().contains("unknown budget `b`"));
+        assert!(Budgets::parse(r#"{"budgets": {"a//b": {}}}"#, false).is_err());
+        assert!(Budgets::parse(r#"{"budgets": {"a": {"policy": "strict"}}}"#, false).is_err());
+        assert!(Budgets::parse(r#"{"budgets": {"a": {"monthly_usd_limit": 5, "currency": "JPY"}}}"#, false).is_err());
+        assert!(Budgets::parse("[budgets.a]\nmonthly_usd_limit = 5\n[budgets.\"a/b\"]\n", true).is_ok());
+    }
+}
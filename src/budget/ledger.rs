@@ -0,0 +1,635 @@
+//! Append-only spend ledger: pool rate changes and one-off charges, one JSON object per line.
+//! Several servers may share the file: each reads what the others appended before computing
+//! spend, and writes under an exclusive file lock.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{Date, OffsetDateTime};
+use tracing::warn;
+
+use crate::budget::forecast::{self, Day, Forecast, DAY_MS, HISTORY_DAYS};
+use crate::budget::hierarchy;
+use crate::budget::money::{self, dec, hours_from_ms, Decimal};
+use crate::budget::schedule::Schedule;
+use crate::budget::tokens::TokenUsage;
+use crate::util::now_ms;
+
+/// Amounts are in `currency` (the reporting currency when written; lines from before currencies
+/// were recorded are USD) and converted to the current reporting currency when spend is computed.
+/// `budget` is the budget path the cost rolls up to, if any; `schedule` and `until_ms` are the rate's `Plan`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Entry {
+    /// From `ts_ms` on, `pool` runs `replicas` at `hourly_usd` (0 = stopped).
+    Rate {
+        ts_ms: u64, pool: String, replicas: u32, #[serde(with = "money::exact")] hourly_usd: Decimal, #[serde(default = "usd")] currency: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")] budget: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] schedule: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] until_ms: Option<u64>,
+    },
+    /// A one-off cost, e.g. an upstream API call and the tokens it used.
+    Charge {
+        ts_ms: u64, source: String, #[serde(with = "money::exact")] usd: Decimal, #[serde(default = "usd")] currency: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")] budget: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] tokens: Option<TokenUsage>,
+    },
+    /// `usd` held for a change to `pool` between its budget check and its booking (see `reservations`),
+    /// until released or `expires_ms`, so a server that dies mid-change cannot hold it for ever.
+    Hold {
+        ts_ms: u64, id: String, pool: String, #[serde(with = "money::exact")] usd: Decimal, #[serde(default = "usd")] currency: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")] budget: Option<String>,
+        expires_ms: u64,
+    },
+    /// Ends the hold `id`.
+    Release { ts_ms: u64, id: String },
+}
+
+/// When a booked rate is expected to run: while `schedule` (as given to the tool) allows, and until
+/// `until_ms` (e.g. a job's expected end). Only the forecast uses it; spend so far follows the rates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub schedule: Option<Value>,
+    pub until_ms: Option<u64>,
+}
+
+fn usd() -> String {
+    "USD".into()
+}
+
+impl Entry {
+    fn ts_ms(&self) -> u64 {
+        match self { Entry::Rate { ts_ms, .. } | Entry::Charge { ts_ms, .. } | Entry::Hold { ts_ms, .. } | Entry::Release { ts_ms, .. } => *ts_ms }
+    }
+
+    fn budget(&self) -> Option<&str> {
+        match self {
+            Entry::Rate { budget, .. } | Entry::Charge { budget, .. } | Entry::Hold { budget, .. } => budget.as_deref(),
+            Entry::Release { .. } => None,
+        }
+    }
+}
+
+/// Spend for the calendar month (UTC) containing `now`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Spend {
+    pub month: String,
+    pub currency: String,
+    pub month_to_date_usd: Decimal,
+    pub compute_usd: Decimal,
+    pub charges_usd: Decimal,
+    pub current_hourly_usd: Decimal,
+    pub projected_eom_usd: Decimal,
+    pub forecast: Forecast,
+    pub running_pools: Vec<RunningPool>,
+    /// Upstream API calls this month by model
+    pub models: Vec<ModelSpend>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelSpend {
+    /// `unknown` when neither the response nor the request named one
+    pub model: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_input_tokens: u64,
+    /// Calls priced from the request size because the response reported no usage
+    pub estimated_calls: u64,
+    pub usd: Decimal,
+}
+
+/// A hold still in effect, in the reporting currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hold {
+    pub id: String,
+    pub pool: String,
+    pub budget: Option<String>,
+    pub usd: Decimal,
+    pub expires_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunningPool {
+    pub pool: String,
+    pub replicas: u32,
+    pub hourly_usd: Decimal,
+}
+
+pub struct Ledger {
+    path: Option<PathBuf>,
+    tail: Mutex<Tail>,
+}
+
+/// What has been read of the file: its entries in time order, and how far into it.
+#[derive(Default)]
+struct Tail {
+    entries: Vec<Entry>,
+    offset: u64,
+    lines: usize,
+    /// Bytes after the last complete line: a torn write, as writers hold the lock until done
+    torn: bool,
+}
+
+/// The ledger with the file locked (shared to read, exclusive to write) and read up to its end.
+pub struct Locked<'a> {
+    ledger: &'a Ledger,
+    tail: MutexGuard<'a, Tail>,
+    file: Option<File>,
+}
+
+impl Ledger {
+    /// In-memory only; spend is lost on restart.
+    pub fn memory() -> Self {
+        Self { path: None, tail: Mutex::new(Tail::default()) }
+    }
+
+    /// Load `path` (created on first write). Unreadable lines are skipped so one torn write
+    /// cannot hide the rest of the month.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+        let ledger = Self { path: Some(path.to_path_buf()), tail: Mutex::new(Tail::default()) };
+        let file = OpenOptions::new().read(true).append(true).create(true).open(path).map_err(|e| anyhow::anyhow!("ledger {}: {}", path.display(), e))?;
+        file.lock_shared().map_err(|e| anyhow::anyhow!("ledger {}: {}", path.display(), e))?;
+        ledger.read_new(&mut ledger.tail.lock().unwrap(), &file).map_err(|e| anyhow::anyhow!("ledger {}: {}", path.display(), e))?;
+        Ok(ledger)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Lock the file and catch up with what other processes appended. When the file cannot be
+    /// opened or read, the entries in memory are used.
+    fn locked(&self, exclusive: bool) -> Locked<'_> {
+        let mut tail = self.tail.lock().unwrap();
+        let file = self.path.as_ref().and_then(|path| {
+            let res = OpenOptions::new().read(true).append(true).create(true).open(path).and_then(|f| {
+                if exclusive { f.lock()? } else { f.lock_shared()? }
+                self.read_new(&mut tail, &f)?;
+                Ok(f)
+            });
+            res.map_err(|e| warn!(path = %path.display(), error = %e, "ledger read failed; using entries in memory")).ok()
+        });
+        Locked { ledger: self, tail, file }
+    }
+
+    /// Read complete lines past `tail.offset`; a file that shrank was replaced and is read again.
+    fn read_new(&self, tail: &mut Tail, mut file: &File) -> std::io::Result<()> {
+        let len = file.metadata()?.len();
+        if len < tail.offset {
+            *tail = Tail::default();
+        }
+        file.seek(SeekFrom::Start(tail.offset))?;
+        let mut buf = Vec::new();
+        file.take(len - tail.offset).read_to_end(&mut buf)?;
+        let complete = buf.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let last = tail.entries.last().map(Entry::ts_ms).unwrap_or_default();
+        let before = tail.entries.len();
+        for line in String::from_utf8_lossy(&buf[..complete]).lines() {
+            tail.lines += 1;
+            if line.trim().is_empty() { continue; }
+            match serde_json::from_str::<Entry>(line) {
+                Ok(e) => tail.entries.push(e),
+                Err(e) => warn!(path = ?self.path, line = tail.lines, error = %e, "skipping ledger line"),
+            }
+        }
+        if tail.entries[before..].iter().any(|e| e.ts_ms() < last) || before == 0 {
+            tail.entries.sort_by_key(Entry::ts_ms);
+        }
+        tail.offset += complete as u64;
+        tail.torn = complete < buf.len();
+        Ok(())
+    }
+
+    fn append(&self, entry: Entry) {
+        self.locked(true).append(entry);
+    }
+
+    /// Lock the file exclusively, e.g. to check a change against the budgets and hold its cost
+    /// with no other process doing the same in between. It must not be held across an await.
+    pub fn lock(&self) -> Locked<'_> {
+        self.locked(true)
+    }
+
+    /// Holds in effect, from every process sharing the file.
+    pub fn holds(&self) -> Vec<Hold> {
+        self.locked(false).holds()
+    }
+
+    /// End the hold `id`.
+    pub fn release(&self, id: &str) {
+        self.append(Entry::Release { ts_ms: now_ms() as u64, id: id.to_string() });
+    }
+
+
+    /// Record that `pool` now runs `replicas` at `hourly_usd` (reporting currency); repeats of the current rate are not written.
+    /// Without a `budget` the pool stays billed to the budget of its previous rate, and keeps its plan.
+    pub fn rate(&self, pool: &str, replicas: u32, hourly_usd: Decimal, budget: Option<&str>) {
+        self.rate_planned(pool, replicas, hourly_usd, budget, None)
+    }
+
+    /// As `rate`, replacing the pool's plan when one is given.
+    pub fn rate_planned(&self, pool: &str, replicas: u32, hourly_usd: Decimal, budget: Option<&str>, plan: Option<Plan>) {
+        let currency = money::currency();
+        // the check for a repeat and the write under one lock, so another process cannot book in between
+        let mut locked = self.locked(true);
+        let last = last_rate(&locked.tail.entries, pool);
+        let (last_budget, last_plan) = match last.clone() {
+            Some(Entry::Rate { budget, schedule, until_ms, .. }) => (budget, Plan { schedule, until_ms }),
+            _ => (None, Plan::default()),
+        };
+        let budget = budget.map(str::to_string).or(last_budget);
+        let plan = plan.unwrap_or(last_plan);
+        let entry = |ts_ms, replicas, hourly_usd| Entry::Rate {
+            ts_ms, pool: pool.to_string(), replicas, hourly_usd, currency: currency.clone(), budget: budget.clone(), schedule: plan.schedule.clone(), until_ms: plan.until_ms,
+        };
+        let mut current = last.unwrap_or_else(|| entry(0, 0, Decimal::ZERO));
+        if let Entry::Rate { ts_ms, .. } = &mut current { *ts_ms = 0; }
+        if current == entry(0, replicas, hourly_usd) { return; }
+        locked.append(entry(now_ms() as u64, replicas, hourly_usd));
+    }
+
+    /// A one-off cost; calls with `tokens` are written even when unpriced, so usage stays visible.
+    pub fn charge(&self, source: &str, usd: Decimal, budget: Option<&str>, tokens: Option<TokenUsage>) {
+        if usd > Decimal::ZERO || tokens.is_some() {
+            self.append(Entry::Charge { ts_ms: now_ms() as u64, source: source.to_string(), usd, currency: money::currency(), budget: budget.map(str::to_string), tokens });
+        }
+    }
+
+    /// The budget `pool` was last billed to.
+    pub fn budget_of(&self, pool: &str) -> Option<String> {
+        match last_rate(&self.locked(false).tail.entries, pool) { Some(Entry::Rate { budget, .. }) => budget, _ => None }
+    }
+
+    /// Fails when an entry's currency has no FX rate to the reporting currency.
+    pub fn spend(&self) -> anyhow::Result<Spend> {
+        self.locked(false).spend()
+    }
+
+    /// Spend billed to `budget` or any budget below it. With `stopping`, that pool's
+    /// current rate is left out of the projection, so a change to it can be added back.
+    pub fn spend_in(&self, budget: &str, stopping: Option<&str>) -> anyhow::Result<Spend> {
+        self.locked(false).spend_in(budget, stopping)
+    }
+}
+
+impl Locked<'_> {
+    /// As `Ledger::spend`, from what this lock has read.
+    pub fn spend(&self) -> anyhow::Result<Spend> {
+        spend_at(&self.tail.entries, now_ms() as u64, &money::fx())
+    }
+
+    /// As `Ledger::spend_in`.
+    pub fn spend_in(&self, budget: &str, stopping: Option<&str>) -> anyhow::Result<Spend> {
+        self.spend_where(Some(budget), stopping)
+    }
+
+    /// Spend across all budgets with `stopping`'s current rate left out of the projection.
+    pub fn spend_without(&self, stopping: &str) -> anyhow::Result<Spend> {
+        self.spend_where(None, Some(stopping))
+    }
+
+    fn spend_where(&self, budget: Option<&str>, stopping: Option<&str>) -> anyhow::Result<Spend> {
+        let now = now_ms() as u64;
+        let mut entries: Vec<Entry> = self.tail.entries.iter()
+            .filter(|e| budget.is_none_or(|budget| e.budget().is_some_and(|b| hierarchy::within(b, budget))))
+            .cloned().collect();
+        if let Some(pool) = stopping {
+            entries.push(Entry::Rate { ts_ms: now, pool: pool.to_string(), replicas: 0, hourly_usd: Decimal::ZERO, currency: money::currency(), budget: None, schedule: None, until_ms: None });
+        }
+        spend_at(&entries, now, &money::fx())
+    }
+
+    /// Holds neither released nor expired. One in a currency without an FX rate counts as written.
+    pub fn holds(&self) -> Vec<Hold> {
+        let now = now_ms() as u64;
+        let fx = money::fx();
+        let released: std::collections::HashSet<&str> = self.tail.entries.iter()
+            .filter_map(|e| match e { Entry::Release { id, .. } => Some(id.as_str()), _ => None }).collect();
+        self.tail.entries.iter().filter_map(|e| match e {
+            Entry::Hold { id, pool, usd, currency, budget, expires_ms, .. } if *expires_ms > now && !released.contains(id.as_str()) => Some(Hold {
+                id: id.clone(), pool: pool.clone(), budget: budget.clone(), usd: fx.to_base(*usd, currency).unwrap_or(*usd), expires_ms: *expires_ms,
+            }),
+            _ => None,
+        }).collect()
+    }
+
+    /// Hold `usd` (reporting currency) for `pool` for at most `ttl_ms`; returns the hold's id.
+    pub fn hold(&mut self, pool: &str, budget: Option<&str>, usd: Decimal, ttl_ms: u64) -> String {
+        let (ts_ms, id) = (now_ms() as u64, uuid::Uuid::new_v4().to_string());
+        self.append(Entry::Hold { ts_ms, id: id.clone(), pool: pool.to_string(), usd, currency: money::currency(), budget: budget.map(str::to_string), expires_ms: ts_ms.saturating_add(ttl_ms) });
+        id
+    }
+
+    /// Write `entry` and keep it; a failed write keeps it in memory only.
+    fn append(&mut self, entry: Entry) {
+        if let (Some(path), Some(file)) = (&self.ledger.path, &mut self.file) {
+            // end a torn line first, so it stays one skipped line rather than spoiling this one
+            let line = format!("{}{}\n", if self.tail.torn { "\n" } else { "" }, serde_json::to_string(&entry).unwrap_or_default());
+            match file.write_all(line.as_bytes()).and_then(|_| file.metadata()) {
+                Ok(meta) => {
+                    self.tail.lines += self.tail.torn as usize + 1;
+                    self.tail.offset = meta.len();
+                    self.tail.torn = false;
+                }
+                Err(e) => warn!(path = %path.display(), error = %e, "ledger write failed; kept in memory only"),
+            }
+        } else if let Some(path) = &self.ledger.path {
+            warn!(path = %path.display(), "ledger not writable; kept in memory only");
+        }
+        self.tail.entries.push(entry);
+    }
+}
+
+/// The latest `Entry::Rate` for `pool`.
+fn last_rate(entries: &[Entry], pool: &str) -> Option<Entry> {
+    entries.iter().rev().find(|e| matches!(e, Entry::Rate { pool: p, .. } if p == pool)).cloned()
+}
+
+/// First millisecond of the UTC month containing `now_ms`, and of the month after.
+pub fn month_bounds(now_ms: u64) -> (u64, u64) {
+    let now = OffsetDateTime::from_unix_timestamp((now_ms / 1000) as i64).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    let start = Date::from_calendar_date(now.year(), now.month(), 1).unwrap_or(now.date());
+    let end = match now.month() {
+        time::Month::December => Date::from_calendar_date(now.year() + 1, time::Month::January, 1),
+        m => Date::from_calendar_date(now.year(), m.next(), 1),
+    }.unwrap_or(start);
+    let ms = |d: Date| (d.midnight().assume_utc().unix_timestamp() * 1000) as u64;
+    (ms(start), ms(end))
+}
+
+/// A pool's rate from `since` on.
+struct Open<'a> {
+    since: u64,
+    replicas: u32,
+    rate: Decimal,
+    schedule: Option<&'a Value>,
+    until_ms: Option<u64>,
+}
+
+/// Compute and charges between `from` and `to` (inclusive), and each pool's rate at `to`.
+fn integrate<'a>(entries: &'a [Entry], from: u64, to: u64, fx: &money::Fx) -> anyhow::Result<(Decimal, Decimal, HashMap<&'a str, Open<'a>>)> {
+    let overlap = |a: u64, b: u64| hours_from_ms(b.min(to).saturating_sub(a.max(from)));
+    let mut open: HashMap<&str, Open> = HashMap::new();
+    let (mut compute, mut charges) = (Decimal::ZERO, Decimal::ZERO);
+    for e in entries.iter().filter(|e| e.ts_ms() <= to) {
+        match e {
+            Entry::Rate { ts_ms, pool, replicas, hourly_usd, currency, schedule, until_ms, .. } => {
+                let next = Open { since: *ts_ms, replicas: *replicas, rate: fx.to_base(*hourly_usd, currency)?, schedule: schedule.as_ref(), until_ms: *until_ms };
+                if let Some(prev) = open.insert(pool, next) {
+                    compute += prev.rate * overlap(prev.since, *ts_ms);
+                }
+            }
+            Entry::Charge { ts_ms, usd, currency, .. } if *ts_ms >= from => charges += fx.to_base(*usd, currency)?,
+            Entry::Charge { .. } | Entry::Hold { .. } | Entry::Release { .. } => {}
+        }
+    }
+    compute += open.values().map(|o| o.rate * overlap(o.since, to)).sum::<Decimal>();
+    Ok((compute, charges, open))
+}
+
+/// Spend on each complete UTC day of the last `HISTORY_DAYS` before `now`'s, from the first entry on.
+fn history(entries: &[Entry], now: u64, fx: &money::Fx) -> anyhow::Result<Vec<Day>> {
+    let today = now - now % DAY_MS;
+    let Some(first) = entries.first().map(|e| e.ts_ms() - e.ts_ms() % DAY_MS) else { return Ok(Vec::new()) };
+    let origin = first.max(today.saturating_sub(HISTORY_DAYS * DAY_MS));
+    let mut days: Vec<Day> = (origin..today).step_by(DAY_MS as usize).map(|start_ms| Day { start_ms, compute: Decimal::ZERO, charges: Decimal::ZERO }).collect();
+    // one pass over the entries: each run of a rate is split across the days it spans
+    let run = |days: &mut Vec<Day>, rate: Decimal, since: u64, until: u64| {
+        let (mut at, end) = (since.max(origin), until.min(today));
+        while at < end {
+            let day = ((at - origin) / DAY_MS) as usize;
+            let next = (days[day].start_ms + DAY_MS).min(end);
+            days[day].compute += rate * hours_from_ms(next - at);
+            at = next;
+        }
+    };
+    let mut open: HashMap<&str, (u64, Decimal)> = HashMap::new();
+    for e in entries.iter().filter(|e| e.ts_ms() <= today) {
+        match e {
+            Entry::Rate { ts_ms, pool, hourly_usd, currency, .. } => {
+                if let Some((since, rate)) = open.insert(pool, (*ts_ms, fx.to_base(*hourly_usd, currency)?)) {
+                    run(&mut days, rate, since, *ts_ms);
+                }
+            }
+            Entry::Charge { ts_ms, usd, currency, .. } if *ts_ms >= origin => {
+                let usd = fx.to_base(*usd, currency)?;
+                // a charge on a midnight closes the day before, as the month's running totals count it
+                if *ts_ms > origin { days[((ts_ms - origin - 1) / DAY_MS) as usize].charges += usd; }
+            }
+            Entry::Charge { .. } | Entry::Hold { .. } | Entry::Release { .. } => {}
+        }
+    }
+    for (since, rate) in open.into_values() {
+        run(&mut days, rate, since, today);
+    }
+    Ok(days)
+}
+
+/// Month-to-date spend and an end-of-month forecast (see `forecast`).
+fn spend_at(entries: &[Entry], now: u64, fx: &money::Fx) -> anyhow::Result<Spend> {
+    let (start, end) = month_bounds(now);
+    let (compute, charges, open) = integrate(entries, start, now, fx)?;
+    let mut running_pools: Vec<RunningPool> = open.iter()
+        .filter(|(_, o)| o.rate > Decimal::ZERO)
+        .map(|(pool, o)| RunningPool { pool: pool.to_string(), replicas: o.replicas, hourly_usd: o.rate })
+        .collect();
+    running_pools.sort_by(|a, b| a.pool.cmp(&b.pool));
+    let current_hourly: Decimal = running_pools.iter().map(|p| p.hourly_usd).sum();
+    // running pools keep their rate while scheduled, up to an expected end still ahead
+    let committed: Decimal = open.values().filter(|o| o.rate > Decimal::ZERO).map(|o| {
+        let stop = o.until_ms.filter(|u| *u > now).unwrap_or(end).min(end);
+        let schedule = o.schedule.and_then(|s| Schedule::from_value(s).ok()).unwrap_or_default();
+        let hours = if schedule.is_always() { hours_from_ms(stop.saturating_sub(now)) } else { dec(schedule.running_hours(now, stop)) };
+        o.rate * hours
+    }).sum();
+    let history = history(entries, now, fx)?;
+    let forecast = forecast::forecast(&forecast::Inputs {
+        now, month_start: start, month_end: end, month_to_date: compute + charges, month_charges: charges, committed, history: &history,
+    });
+    let mut models: BTreeMap<String, ModelSpend> = BTreeMap::new();
+    for e in entries.iter().filter(|e| (start..=now).contains(&e.ts_ms())) {
+        if let Entry::Charge { usd, currency, tokens: Some(t), .. } = e {
+            let name = t.model.clone().unwrap_or_else(|| "unknown".into());
+            let m = models.entry(name.clone()).or_insert(ModelSpend {
+                model: name, calls: 0, input_tokens: 0, output_tokens: 0, cached_input_tokens: 0, estimated_calls: 0, usd: Decimal::ZERO,
+            });
+            m.calls += 1;
+            m.input_tokens += t.input_tokens;
+            m.output_tokens += t.output_tokens;
+            m.cached_input_tokens += t.cached_input_tokens;
+            m.estimated_calls += t.estimated as u64;
+            m.usd += fx.to_base(*usd, currency)?;
+        }
+    }
+    let month = OffsetDateTime::from_unix_timestamp((start / 1000) as i64).map(|d| format!("{}-{:02}", d.year(), d.month() as u8)).unwrap_or_default();
+    Ok(Spend {
+        month,
+        currency: fx.base.clone(),
+        month_to_date_usd: compute + charges,
+        compute_usd: compute,
+        charges_usd: charges,
+        current_hourly_usd: current_hourly,
+        projected_eom_usd: forecast.projected_eom_usd,
+        forecast,
+        running_pools,
+        models: models.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H: u64 = 3_600_000;
+    // 2025-03-01T00:00:00Z
+    const MARCH: u64 = 1_740_787_200_000;
+
+    fn rate(ts_ms: u64, pool: &str, hourly_usd: i64) -> Entry {
+        Entry::Rate { ts_ms, pool: pool.into(), replicas: if hourly_usd > 0 { 1 } else { 0 }, hourly_usd: hourly_usd.into(), currency: "USD".into(), budget: None, schedule: None, until_ms: None }
+    }
+
+    #[test]
+    fn test_month_bounds() {
+        assert_eq!(month_bounds(MARCH + 5 * H), (MARCH, MARCH + 31 * 24 * H));
+        // December rolls into the next year
+        let (start, end) = month_bounds(1_765_000_000_000);
+        assert_eq!((start, end), (1_764_547_200_000, 1_767_225_600_000));
+    }
+
+    #[test]
+    fn test_spend_integrates_rates_within_month() {
+        let entries = vec![
+            rate(MARCH - 10 * H, "a", 2),             // started last month: only March hours count
+            rate(MARCH + 4 * H, "a", 0),
+            rate(MARCH + 2 * H, "b", 1),
+            Entry::Charge { ts_ms: MARCH - H, source: "api".into(), usd: 100.into(), currency: "USD".into(), budget: None, tokens: None },
+            Entry::Charge { ts_ms: MARCH + H, source: "api".into(), usd: Decimal::new(5, 1), currency: "USD".into(), budget: None, tokens: None },
+        ];
+        let mut sorted = entries.clone();
+        sorted.sort_by_key(Entry::ts_ms);
+        let s = spend_at(&sorted, MARCH + 10 * H, &money::Fx::default()).unwrap();
+        assert_eq!(s.month, "2025-03");
+        assert_eq!((s.compute_usd, s.charges_usd), (Decimal::from(16), Decimal::new(5, 1)));
+        assert_eq!(s.running_pools, vec![RunningPool { pool: "b".into(), replicas: 1, hourly_usd: Decimal::ONE }]);
+        let remaining = Decimal::from(31 * 24 - 10);
+        assert_eq!(s.projected_eom_usd, Decimal::new(165, 1) + remaining + Decimal::new(5, 2) * remaining);
+
+        // entries in another currency are converted; old lines without one are USD
+        let eur: Entry = serde_json::from_str(r#"{"kind":"charge","ts_ms":1740790800000,"source":"api","usd":2,"currency":"EUR"}"#).unwrap();
+        let old: Entry = serde_json::from_str(r#"{"kind":"charge","ts_ms":1740790800000,"source":"api","usd":1.5}"#).unwrap();
+        let fx = money::Fx::parse("USD", "EUR=1.10").unwrap();
+        assert_eq!(spend_at(&[eur.clone(), old], MARCH + 10 * H, &fx).unwrap().charges_usd, Decimal::new(37, 1));
+        assert!(spend_at(&[eur], MARCH + 10 * H, &money::Fx::default()).is_err());
+
+        // token-bearing charges are summed by model
+        let calls: Vec<Entry> = [r#"{"kind":"charge","ts_ms":1740790800000,"source":"/api/v1/generate","usd":0.5,"tokens":{"model":"gpt-4o","input_tokens":100,"output_tokens":20}}"#,
+            r#"{"kind":"charge","ts_ms":1740794400000,"source":"/api/v1/qa","usd":0.25,"tokens":{"model":"gpt-4o","input_tokens":40,"output_tokens":10,"estimated":true}}"#]
+            .iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let m = &spend_at(&calls, MARCH + 10 * H, &money::Fx::default()).unwrap().models[0];
+        assert_eq!((m.model.as_str(), m.calls, m.input_tokens, m.output_tokens, m.estimated_calls, m.usd), ("gpt-4o", 2, 140, 30, 1, Decimal::new(75, 2)));
+    }
+
+    #[test]
+    fn test_ledger_persists_and_dedups() {
+        let path = std::env::temp_dir().join(format!("ectusr2-ledger-{}.jsonl", uuid::Uuid::new_v4()));
+        let l = Ledger::open(&path).unwrap();
+        l.rate("p", 2, Decimal::new(15, 1), Some("acme/ml"));
+        l.rate("p", 2, Decimal::new(15, 1), None);
+        l.charge("api", Decimal::ZERO, None, None);
+        l.charge("api", Decimal::new(25, 2), Some("acme/web"), None);
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{torn\n").unwrap();
+        let reopened = Ledger::open(&path).unwrap();
+        assert_eq!(reopened.tail.lock().unwrap().entries.len(), 2);
+        assert_eq!(reopened.spend().unwrap().running_pools.len(), 1);
+        // spend rolls up by budget prefix; a stopping pool leaves the projection
+        assert_eq!(reopened.budget_of("p").as_deref(), Some("acme/ml"));
+        assert_eq!(reopened.spend_in("acme", None).unwrap().charges_usd, Decimal::new(25, 2));
+        assert_eq!(reopened.spend_in("acme/ml", None).unwrap().current_hourly_usd, Decimal::new(15, 1));
+        assert_eq!(reopened.spend_in("acme/ml", Some("p")).unwrap().current_hourly_usd, Decimal::ZERO);
+        assert_eq!(reopened.spend_in("acme/m", None).unwrap().running_pools.len(), 0);
+        // amounts are written as strings and read back exactly
+        let big: Decimal = "123456789012345.67".parse().unwrap();
+        reopened.charge("api", big, None, None);
+        assert!(std::fs::read_to_string(&path).unwrap().contains(r#""usd":"123456789012345.67""#));
+        assert_eq!(Ledger::open(&path).unwrap().spend().unwrap().charges_usd, big + Decimal::new(25, 2));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_ledgers_sharing_a_file_see_each_others_entries() {
+        let path = std::env::temp_dir().join(format!("ectusr2-ledger-{}.jsonl", uuid::Uuid::new_v4()));
+        let (a, b) = (Ledger::open(&path).unwrap(), Ledger::open(&path).unwrap());
+        a.rate("p", 1, Decimal::ONE, Some("acme"));
+        b.charge("api", Decimal::TWO, Some("acme"), None);
+        for l in [&a, &b] {
+            let s = l.spend_in("acme", None).unwrap();
+            assert_eq!((s.current_hourly_usd, s.charges_usd), (Decimal::ONE, Decimal::TWO));
+        }
+        // a repeat of the rate another process booked is not written again
+        b.rate("p", 1, Decimal::ONE, None);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        // a torn line is ended before the next write and skipped
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{torn").unwrap();
+        b.charge("api", Decimal::ONE, None, None);
+        assert_eq!(a.spend().unwrap().charges_usd, Decimal::from(3));
+        assert_eq!(Ledger::open(&path).unwrap().tail.lock().unwrap().entries.len(), 3);
+        // a replaced file is read again from the start
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(a.spend().unwrap().charges_usd, Decimal::ZERO);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_history_matches_running_totals() {
+        // the day split agrees with differences of `integrate` totals, midnights included
+        let now = MARCH + 20 * 24 * H + 5 * H;
+        let charge = |ts_ms| Entry::Charge { ts_ms, source: "api".into(), usd: Decimal::new(15, 1), currency: "USD".into(), budget: None, tokens: None };
+        let mut entries = vec![
+            rate(MARCH - 40 * 24 * H, "old", 1), rate(MARCH + 2 * 24 * H + 7 * H, "a", 3), rate(MARCH + 9 * 24 * H, "a", 0),
+            rate(MARCH + 4 * 24 * H, "b", 2), charge(MARCH + 3 * 24 * H), charge(MARCH + 3 * 24 * H + 1), charge(MARCH + 20 * 24 * H), charge(now),
+        ];
+        entries.sort_by_key(Entry::ts_ms);
+        let fx = money::Fx::default();
+        let today = now - now % DAY_MS;
+        let origin = today - HISTORY_DAYS * DAY_MS;
+        let totals: Vec<(Decimal, Decimal)> = (origin..=today).step_by(DAY_MS as usize).map(|d| {
+            let (compute, charges, _) = integrate(&entries, origin, d, &fx).unwrap();
+            (compute, charges)
+        }).collect();
+        let expected: Vec<(Decimal, Decimal)> = totals.windows(2).map(|w| (w[1].0 - w[0].0, w[1].1 - w[0].1)).collect();
+        let days = history(&entries, now, &fx).unwrap();
+        assert_eq!(days.len(), HISTORY_DAYS as usize);
+        assert_eq!(days.iter().map(|d| (d.compute, d.charges)).collect::<Vec<_>>(), expected);
+        assert_eq!(days.iter().map(|d| d.charges).sum::<Decimal>(), Decimal::new(45, 1));
+    }
+
+    #[test]
+    fn test_spend_forecast_follows_plan_and_history() {
+        let now = MARCH + 10 * 24 * H;
+        let planned = |pool: &str, schedule: Option<Value>, until_ms: Option<u64>| Entry::Rate {
+            ts_ms: now - H, pool: pool.into(), replicas: 1, hourly_usd: Decimal::ONE, currency: "USD".into(), budget: None, schedule, until_ms,
+        };
+        // a job expected to end in 5 h, and a pool that runs business hours (Tue 11 March onwards: 15 weekdays x 8 h)
+        let entries = vec![planned("job", None, Some(now + 5 * H)), planned("biz", Some(serde_json::json!("business_hours")), None)];
+        let s = spend_at(&entries, now, &money::Fx::default()).unwrap();
+        assert_eq!(s.forecast.committed_usd, Decimal::from(5 + 15 * 8));
+        assert_eq!(s.current_hourly_usd, Decimal::TWO);
+
+        // a week of API charges rising by 1 a day: the rest of March follows the trend
+        let mut entries: Vec<Entry> = (0..7).map(|d| Entry::Charge {
+            ts_ms: now - (7 - d) * 24 * H + H, source: "api".into(), usd: (10 + d as i64).into(), currency: "USD".into(), budget: None, tokens: None,
+        }).collect();
+        entries.push(rate(now - 7 * 24 * H, "a", 0));
+        entries.sort_by_key(Entry::ts_ms);
+        let s = spend_at(&entries, now, &money::Fx::default()).unwrap();
+        assert_eq!((s.forecast.history_days, s.forecast.method, s.forecast.trend_usd_per_day), (7, "trend", Decimal::ONE));
+        assert_eq!(s.forecast.variable_usd, Decimal::from((17..17 + 21).sum::<i64>()));
+        assert_eq!(s.projected_eom_usd, s.month_to_date_usd + s.forecast.variable_usd);
+    }
+}
@@ -1,95 +1,263 @@
 use serde::Deserialize;
 
+pub mod alerts;
+pub mod forecast;
+pub mod hierarchy;
+pub mod ledger;
+pub mod money;
+pub mod pricing;
+pub mod reservations;
+pub mod schedule;
+pub mod store;
+pub mod tokens;
+
+use money::{dec, Decimal};
+use schedule::Schedule;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Resources {
     pub cpu: String,      // e.g., "500m" or "1"
     pub memory: String,   // e.g., "512Mi" or "2Gi"
-    pub gpu: Option<String>,
+    #[serde(default, deserialize_with = "gpu_count")]
+    pub gpu: Option<String>, // e.g. "1" or 1, as backends accept both
+    #[serde(default)]
+    pub gpu_type: Option<String>, // e.g. "NVIDIA-A100-SXM4-80GB"; priced when the catalog lists it
+    #[serde(default)]
+    pub region: Option<String>,   // pricing catalog region; default: the backend's `default_region`
+    #[serde(default)]
+    pub instance_class: Option<String>, // pricing catalog class, e.g. "spot"
 }
 
+/// Per-replica quantities above these are rejected: no machine has them, and they keep estimates
+/// well inside `Decimal`'s range for any replica count.
+const MAX_CPU: u32 = 100_000;
+const MAX_MEMORY_GIB: u32 = 100_000_000;
+const MAX_GPUS: u32 = 10_000;
+
+impl Resources {
+    /// `resources` of a pool or pod (1 vCPU and 1Gi when absent), with quantities checked.
+    pub fn from_value(v: Option<&serde_json::Value>) -> Result<Self, String> {
+        let r: Self = match v {
+            Some(v) => serde_json::from_value(v.clone()).map_err(|e| format!("resources: {}", e))?,
+            None => Self { cpu: "1".into(), memory: "1Gi".into(), gpu: None, gpu_type: None, region: None, instance_class: None },
+        };
+        r.validate()?;
+        Ok(r)
+    }
+
+    /// Per-replica total of a pod's `containers` (Kubernetes form): each container's limits, else its
+    /// requests, and 1 vCPU and 1Gi where it gives none, so a pod is never priced below what it may use.
+    pub fn from_containers(containers: &[serde_json::Value]) -> Result<Self, String> {
+        if containers.is_empty() { return Err("resources: the pod has no containers".into()); }
+        let (mut cpu, mut memory, mut gpu) = (Decimal::ZERO, Decimal::ZERO, 0u64);
+        for c in containers {
+            let quantity = |key: &str| {
+                let r = c.get("resources");
+                let v = r.and_then(|r| r.get("limits")).and_then(|l| l.get(key)).or_else(|| r.and_then(|r| r.get("requests")).and_then(|l| l.get(key)))?;
+                v.as_str().map(str::to_string).or_else(|| v.is_number().then(|| v.to_string()))
+            };
+            let one = Self {
+                cpu: quantity("cpu").unwrap_or_else(|| "1".into()), memory: quantity("memory").unwrap_or_else(|| "1Gi".into()), gpu: quantity("nvidia.com/gpu"),
+                gpu_type: None, region: None, instance_class: None,
+            };
+            one.validate()?;
+            cpu += cpu_quantity(&one.cpu);
+            memory += mem_quantity_gib(&one.memory);
+            gpu = gpu.saturating_add(one.gpu.as_deref().and_then(|g| g.trim().parse().ok()).unwrap_or(0));
+        }
+        let total = Self { cpu: cpu.normalize().to_string(), memory: format!("{}Gi", memory.normalize()), gpu: (gpu > 0).then(|| gpu.to_string()), gpu_type: None, region: None, instance_class: None };
+        total.validate()?;
+        Ok(total)
+    }
+
+    /// Quantities must parse, be non-negative and stay under the per-replica maximums, so every
+    /// estimate is a real, bounded cost.
+    pub fn validate(&self) -> Result<(), String> {
+        match try_cpu_quantity(&self.cpu) {
+            Some(cpu) if cpu.is_sign_negative() => return Err(format!("resources: cpu `{}` is negative", self.cpu)),
+            Some(cpu) if cpu > Decimal::from(MAX_CPU) => return Err(format!("resources: cpu `{}` is over {} vCPUs per replica", self.cpu, MAX_CPU)),
+            Some(_) => {}
+            None => return Err(format!("resources: cpu `{}` is not a quantity", self.cpu)),
+        }
+        match try_mem_quantity_gib(&self.memory) {
+            Some(mem) if mem.is_sign_negative() => return Err(format!("resources: memory `{}` is negative", self.memory)),
+            Some(mem) if mem > Decimal::from(MAX_MEMORY_GIB) => return Err(format!("resources: memory `{}` is over {} GiB per replica", self.memory, MAX_MEMORY_GIB)),
+            Some(_) => {}
+            None => return Err(format!("resources: memory `{}` is not a quantity", self.memory)),
+        }
+        if let Some(gpu) = self.gpu.as_deref() {
+            match gpu.trim().parse::<u64>() {
+                Ok(n) if n > MAX_GPUS as u64 => return Err(format!("resources: gpu `{}` is over {} per replica", gpu, MAX_GPUS)),
+                Ok(_) => {}
+                Err(_) => return Err(format!("resources: gpu `{}` is not a whole number", gpu)),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn gpu_count<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    use serde::de::Error;
+    match Option::<serde_json::Value>::deserialize(d)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(s)) => Ok(Some(s)),
+        Some(serde_json::Value::Number(n)) if n.is_u64() => Ok(Some(n.to_string())),
+        Some(other) => Err(D::Error::custom(format!("gpu: expected a whole number, got {}", other))),
+    }
+}
+
+/// Amounts here and in `EstimateResult` are in the reporting currency (`money::currency()`);
+/// the `_usd` names predate currency support and are kept for stable JSON.
 #[derive(Debug, Clone)]
 pub struct BudgetPolicy {
-    pub monthly_usd_limit: Option<f32>,
+    pub monthly_usd_limit: Option<Decimal>,
     pub policy: Option<PolicyKind>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PolicyKind { Hard, Soft }
 
+/// Longest duration an estimate or job may state: ten years.
+pub const MAX_DURATION_HOURS: f64 = 10.0 * 366.0 * 24.0;
+
+/// How long and when the replicas run: `hours` from `start_ms`, only while `schedule` is active.
+#[derive(Debug, Clone)]
+pub struct Usage {
+    pub hours: f32,
+    pub start_ms: u64,
+    pub schedule: Schedule,
+}
+
+impl Usage {
+    /// `hours` from now, around the clock.
+    pub fn hours(hours: f32) -> Self {
+        Self { hours, start_ms: crate::util::now_ms() as u64, schedule: Schedule::always() }
+    }
+}
+
 pub struct EstimateResult {
-    pub hourly_total_usd: f32,
-    pub monthly_projected_usd: f32,
+    pub hourly_total_usd: Decimal,
+    pub duration_hours: f32,
+    pub running_hours: f32,        // hours within the duration the schedule allows
+    pub duration_total_usd: Decimal,
+    pub month: String,
+    pub monthly_running_hours: f32, // scheduled hours in the calendar month containing the start
+    pub monthly_projected_usd: Decimal,
     pub breakdown: String,
+    pub assumptions: Vec<String>,
 }
 
 pub fn estimate_cost(backend: &str, replicas: u32, resources: &Resources, hours: f32) -> EstimateResult {
-    // Simple static rates as fallback (USD per hour per unit)
-    // CPU: per vCPU hour; Memory: per GiB hour; GPU flat add if present
-    let (cpu_rate, mem_rate, gpu_rate) = match backend.to_lowercase().as_str() {
-        "ecs" => (0.040, 0.005, 1.50),
-        "kubernetes" => (0.035, 0.004, 1.40),
-        "cloud_run" | "aca" => (0.045, 0.006, 1.80),
-        "asg" | "mig" | "vmss" => (0.030, 0.004, 1.30),
-        _ => (0.040, 0.005, 1.50),
-    };
-
-    let cpu_cores = parse_cpu(&resources.cpu);
-    let mem_gib = parse_mem_gib(&resources.memory);
+    estimate_usage(backend, replicas, resources, &Usage::hours(hours))
+}
+
+pub fn estimate_usage(backend: &str, replicas: u32, resources: &Resources, usage: &Usage) -> EstimateResult {
+    // Per hour per unit from the pricing catalog: per vCPU, per GiB, per GPU (by type when listed)
+    let rates = pricing::current().rates(backend, resources.region.as_deref(), resources.instance_class.as_deref());
+    let gpu_rate = rates.gpu_rate(resources.gpu_type.as_deref());
+
+    let cpu_cores = cpu_quantity(&resources.cpu);
+    let mem_gib = mem_quantity_gib(&resources.memory);
     let gpu_count = resources.gpu.as_deref().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
 
-    let hourly_per_replica = cpu_cores * cpu_rate + mem_gib * mem_rate + (gpu_count as f32) * gpu_rate;
-    let hourly_total = hourly_per_replica * replicas as f32;
-    let monthly_projected = hourly_total * 24.0 * 30.0; // simple projection
+    let hourly_per_replica = cpu_cores * rates.cpu_hour + mem_gib * rates.memory_gib_hour + Decimal::from(gpu_count) * gpu_rate;
+    let hourly_total = hourly_per_replica * Decimal::from(replicas);
+    let hours = usage.hours.max(0.0);
+    let running = usage.schedule.running_hours(usage.start_ms, usage.start_ms.saturating_add((hours as f64 * 3_600_000.0) as u64));
+    let (month_start, month_end) = ledger::month_bounds(usage.start_ms);
+    let month_running = usage.schedule.running_hours(month_start, month_end);
+    let month = time::OffsetDateTime::from_unix_timestamp((month_start / 1000) as i64)
+        .map(|d| format!("{}-{:02}", d.year(), d.month() as u8)).unwrap_or_default();
+    let catalog = pricing::current();
+    let assumptions = vec![
+        format!("rates: {} from the {} catalog{}{}", rates.backend,
+            catalog.source.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "built-in".into()),
+            rates.region.as_deref().map(|r| format!(", region {}", r)).unwrap_or_default(),
+            rates.instance_class.as_deref().map(|c| format!(", class {}", c)).unwrap_or_default()),
+        format!("{} replica(s) of {} vCPU, {} GiB, {} GPU each, billed per running hour in {}", replicas, cpu_cores.normalize(), mem_gib.normalize(), gpu_count, money::currency()),
+        format!("schedule: {}", usage.schedule.describe()),
+        format!("duration: {} h from the start, {} h of it scheduled", hours, running),
+        format!("month: {} has {} h, {} h of them scheduled", month, (month_end - month_start) / 3_600_000, month_running),
+        "excludes storage, network egress and upstream API usage".into(),
+    ];
 
     EstimateResult {
-        hourly_total_usd: (hourly_total * hours / hours.max(1.0)),
-        monthly_projected_usd: monthly_projected,
+        hourly_total_usd: hourly_total,
+        duration_hours: hours,
+        running_hours: running as f32,
+        duration_total_usd: hourly_total * dec(running),
+        month,
+        monthly_running_hours: month_running as f32,
+        monthly_projected_usd: hourly_total * dec(month_running),
+        assumptions,
         breakdown: format!(
-            "replicas={} cpu={}cores mem={}Gi gpu={} -> perReplica=${:.4}/h",
-            replicas, cpu_cores, mem_gib, gpu_count, hourly_per_replica
+            "replicas={} cpu={}cores mem={}Gi gpu={}{} -> perReplica={}/h{}{}",
+            replicas, cpu_cores.normalize(), mem_gib.normalize(), gpu_count,
+            resources.gpu_type.as_deref().filter(|_| gpu_count > 0).map(|t| format!("({})", t)).unwrap_or_default(),
+            money::format(hourly_per_replica, 4),
+            rates.region.as_deref().map(|r| format!(" region={}", r)).unwrap_or_default(),
+            rates.instance_class.as_deref().map(|c| format!(" class={}", c)).unwrap_or_default()
         ),
     }
 }
 
-pub fn enforce_budget(policy: &BudgetPolicy, projected_monthly: f32, override_ok: bool) -> Result<(), String> {
+pub fn enforce_budget(policy: &BudgetPolicy, projected_monthly: Decimal, override_ok: bool) -> Result<(), String> {
     if let Some(limit) = policy.monthly_usd_limit {
         if projected_monthly > limit {
+            let (limit, projected) = (money::format(limit, 2), money::format(projected_monthly, 2));
             match policy.policy {
-                Some(PolicyKind::Hard) => return Err(format!("budget hard-limit exceeded (limit=${:.2}, projected=${:.2})", limit, projected_monthly)),
-                Some(PolicyKind::Soft) => {
-                    if !override_ok { return Err(format!("budget soft-limit exceeded; override required (limit=${:.2}, projected=${:.2})", limit, projected_monthly)); }
-                }
-                None => {}
+                Some(PolicyKind::Hard) => return Err(format!("budget hard-limit exceeded (limit={}, projected={})", limit, projected)),
+                Some(PolicyKind::Soft) if !override_ok => return Err(format!("budget soft-limit exceeded; override required (limit={}, projected={})", limit, projected)),
+                _ => {}
             }
         }
     }
     Ok(())
 }
 
-fn parse_cpu(s: &str) -> f32 {
-    if let Some(stripped) = s.strip_suffix('m') { // millicores
-        return stripped.parse::<f32>().unwrap_or(0.0) / 1000.0;
+/// Floating-point quantities for backends that pass them to container runtimes.
+#[cfg(any(feature = "local", feature = "aws", feature = "azure"))]
+pub(crate) fn parse_cpu(s: &str) -> f32 {
+    f32::try_from(cpu_quantity(s)).unwrap_or_default()
+}
+
+#[cfg(any(feature = "local", feature = "aws"))]
+pub(crate) fn parse_mem_gib(s: &str) -> f32 {
+    f32::try_from(mem_quantity_gib(s)).unwrap_or_default()
+}
+
+fn number(s: &str) -> Option<Decimal> {
+    s.trim().parse::<Decimal>().ok()
+}
+
+/// vCPUs as an exact decimal: "500m" or "1.5"; zero when unparsable (`validate` rejects those).
+pub(crate) fn cpu_quantity(s: &str) -> Decimal {
+    try_cpu_quantity(s).unwrap_or_default()
+}
+
+fn try_cpu_quantity(s: &str) -> Option<Decimal> {
+    if let Some(stripped) = s.trim().strip_suffix('m') { // millicores
+        return number(stripped).map(|n| n / Decimal::from(1000));
     }
-    s.parse::<f32>().unwrap_or(0.0)
+    number(s)
+}
+
+/// Memory in GiB as an exact decimal: Ti/TB, Gi/GB, Mi/MB suffixes, bare numbers are GiB; zero when unparsable.
+pub(crate) fn mem_quantity_gib(s: &str) -> Decimal {
+    try_mem_quantity_gib(s).unwrap_or_default()
 }
 
-fn parse_mem_gib(s: &str) -> f32 {
+fn try_mem_quantity_gib(s: &str) -> Option<Decimal> {
     let lower = s.trim().to_ascii_lowercase();
-    // Tebibytes/Terabytes → GiB
-    if let Some(v) = lower.strip_suffix("tib") { return v.trim().parse::<f32>().unwrap_or(0.0) * 1024.0; }
-    if let Some(v) = lower.strip_suffix("ti") { return v.trim().parse::<f32>().unwrap_or(0.0) * 1024.0; }
-    if let Some(v) = lower.strip_suffix("tb") { return v.trim().parse::<f32>().unwrap_or(0.0) * 1024.0; }
-    // Gibibytes/Gigabytes → GiB
-    if let Some(v) = lower.strip_suffix("gib") { return v.trim().parse::<f32>().unwrap_or(0.0); }
-    if let Some(v) = lower.strip_suffix("gi") { return v.trim().parse::<f32>().unwrap_or(0.0); }
-    if let Some(v) = lower.strip_suffix("gb") { return v.trim().parse::<f32>().unwrap_or(0.0); }
-    if let Some(v) = lower.strip_suffix("g") { return v.trim().parse::<f32>().unwrap_or(0.0); }
-    // Mebibytes/Megabytes → GiB
-    if let Some(v) = lower.strip_suffix("mib") { return v.trim().parse::<f32>().unwrap_or(0.0) / 1024.0; }
-    if let Some(v) = lower.strip_suffix("mi") { return v.trim().parse::<f32>().unwrap_or(0.0) / 1024.0; }
-    if let Some(v) = lower.strip_suffix("mb") { return v.trim().parse::<f32>().unwrap_or(0.0) / 1024.0; }
-    if let Some(v) = lower.strip_suffix("m") { return v.trim().parse::<f32>().unwrap_or(0.0) / 1024.0; }
-    lower.parse::<f32>().unwrap_or(0.0)
+    let k = Decimal::from(1024);
+    // Tebibytes/Terabytes → GiB, Gibibytes/Gigabytes as is, Mebibytes/Megabytes → GiB
+    for (suffix, scale) in [("tib", Some(k)), ("ti", Some(k)), ("tb", Some(k)), ("gib", None), ("gi", None), ("gb", None), ("g", None)] {
+        if let Some(v) = lower.strip_suffix(suffix) { return number(v).map(|n| n.saturating_mul(scale.unwrap_or(Decimal::ONE))); }
+    }
+    for suffix in ["mib", "mi", "mb", "m"] {
+        if let Some(v) = lower.strip_suffix(suffix) { return number(v).map(|n| n / k); }
+    }
+    number(&lower)
 }
 
 #[cfg(test)]
@@ -98,34 +266,88 @@ mod tests {
 
     #[test]
     fn test_parse_cpu() {
-        assert!((parse_cpu("1000m") - 1.0).abs() < 1e-6);
-        assert!((parse_cpu("500m") - 0.5).abs() < 1e-6);
-        assert!((parse_cpu("2") - 2.0).abs() < 1e-6);
+        assert_eq!(cpu_quantity("1000m"), Decimal::ONE);
+        assert_eq!(cpu_quantity("500m"), Decimal::new(5, 1));
+        assert_eq!(cpu_quantity("2"), Decimal::TWO);
     }
 
     #[test]
     fn test_parse_mem() {
-        assert!((parse_mem_gib("1024Mi") - 1.0).abs() < 1e-6);
-        assert!((parse_mem_gib("1Gi") - 1.0).abs() < 1e-6);
-        assert!((parse_mem_gib("2048MB") - 2.0).abs() < 1e-6);
+        assert_eq!(mem_quantity_gib("1024Mi"), Decimal::ONE);
+        assert_eq!(mem_quantity_gib("1Gi"), Decimal::ONE);
+        assert_eq!(mem_quantity_gib("2048MB"), Decimal::TWO);
+        assert_eq!(mem_quantity_gib("1.5Ti"), Decimal::from(1536));
+    }
+
+    #[test]
+    fn test_resources_bounds() {
+        use serde_json::json;
+        assert!(Resources::from_value(None).is_ok_and(|r| r.cpu == "1" && r.memory == "1Gi"));
+        assert!(Resources::from_value(Some(&json!({"cpu": "64", "memory": "512Gi", "gpu": 8}))).is_ok());
+        let err = Resources::from_value(Some(&json!({"cpu": "9999999999999999999999999999", "memory": "1Gi"}))).unwrap_err();
+        assert!(err.contains("cpu"), "{}", err);
+        assert!(Resources::from_value(Some(&json!({"cpu": "1", "memory": "79228162514264337593543950335Ti"}))).unwrap_err().contains("memory"));
+        assert!(Resources::from_value(Some(&json!({"cpu": "1", "memory": "1Gi", "gpu": "4294967295"}))).unwrap_err().contains("gpu"));
+        assert!(Resources::from_value(Some(&json!({"memory": "1Gi"}))).is_err());
+        // negative or unparsable quantities would price a change below zero
+        assert!(Resources::from_value(Some(&json!({"cpu": "-100000", "memory": "1Gi"}))).unwrap_err().contains("negative"));
+        assert!(Resources::from_value(Some(&json!({"cpu": "-500m", "memory": "1Gi"}))).is_err());
+        assert!(Resources::from_value(Some(&json!({"cpu": "1", "memory": "lots"}))).unwrap_err().contains("not a quantity"));
+        assert!(Resources::from_value(Some(&json!({"cpu": "1", "memory": "-2Gi"}))).is_err());
+        assert!(Resources::from_value(Some(&json!({"cpu": "1", "memory": "1Gi", "gpu": "two"}))).is_err());
+
+        // a pod's containers add up: limits over requests, the default size where none is given
+        let pod = json!([
+            {"name": "a", "resources": {"requests": {"cpu": "500m", "memory": "512Mi"}, "limits": {"cpu": 2, "nvidia.com/gpu": "1"}}},
+            {"name": "b"},
+        ]);
+        let r = Resources::from_containers(pod.as_array().unwrap()).unwrap();
+        assert_eq!((r.cpu.as_str(), r.memory.as_str(), r.gpu.as_deref()), ("3", "1.5Gi", Some("1")));
+        assert!(Resources::from_containers(&[]).is_err());
+        assert!(Resources::from_containers(&[json!({"resources": {"limits": {"cpu": "-4"}}})]).is_err());
     }
 
     #[test]
     fn test_estimate_and_enforce_soft() {
-        let res = Resources { cpu: "1".into(), memory: "1Gi".into(), gpu: None };
+        let res = Resources { cpu: "1".into(), memory: "1Gi".into(), gpu: None, gpu_type: None, region: None, instance_class: None };
         let est = estimate_cost("kubernetes", 5, &res, 24.0);
-        assert!(est.monthly_projected_usd > 0.0);
-        let pol = BudgetPolicy { monthly_usd_limit: Some(est.monthly_projected_usd - 1.0), policy: Some(PolicyKind::Soft) };
+        assert!(est.monthly_projected_usd > Decimal::ZERO);
+        assert_eq!(est.duration_total_usd, est.hourly_total_usd * Decimal::from(24));
+        let pol = BudgetPolicy { monthly_usd_limit: Some(est.monthly_projected_usd - Decimal::ONE), policy: Some(PolicyKind::Soft) };
         // soft should error unless override
         assert!(enforce_budget(&pol, est.monthly_projected_usd, false).is_err());
         assert!(enforce_budget(&pol, est.monthly_projected_usd, true).is_ok());
+
+        // GPUs given as a number are priced like the string form backends also accept
+        let numeric: Resources = serde_json::from_value(serde_json::json!({"cpu": "1", "memory": "1Gi", "gpu": 2})).unwrap();
+        let string: Resources = serde_json::from_value(serde_json::json!({"cpu": "1", "memory": "1Gi", "gpu": "2"})).unwrap();
+        assert_eq!(estimate_cost("kubernetes", 1, &numeric, 1.0).hourly_total_usd, estimate_cost("kubernetes", 1, &string, 1.0).hourly_total_usd);
+        assert!(serde_json::from_value::<Resources>(serde_json::json!({"cpu": "1", "memory": "1Gi", "gpu": 1.5})).is_err());
+    }
+
+    #[test]
+    fn test_estimate_duration_schedule_and_month() {
+        let res = Resources { cpu: "2".into(), memory: "4Gi".into(), gpu: None, gpu_type: None, region: None, instance_class: None };
+        // 2025-02-03T00:00:00Z, a Monday in a 28-day month
+        let usage = Usage { hours: 2.5, start_ms: 1_738_540_800_000, schedule: Schedule::always() };
+        let est = estimate_usage("kubernetes", 3, &res, &usage);
+        // 3 x (2 x 0.035 + 4 x 0.004) = 0.258 per hour, exactly
+        assert_eq!(est.hourly_total_usd, Decimal::new(258, 3));
+        assert_eq!(est.duration_total_usd, Decimal::new(645, 3));
+        assert_eq!((est.month.as_str(), est.monthly_running_hours), ("2025-02", 672.0));
+
+        let biz = Usage { hours: 24.0 * 7.0, schedule: Schedule::from_value(&serde_json::json!("business_hours")).unwrap(), ..usage };
+        let est = estimate_usage("kubernetes", 3, &res, &biz);
+        assert_eq!((est.running_hours, est.monthly_running_hours), (40.0, 160.0));
+        assert_eq!(est.monthly_projected_usd, Decimal::new(258, 3) * Decimal::from(160));
+        assert!(est.assumptions.iter().any(|a| a.contains("mon,tue,wed,thu,fri 09:00-17:00")));
     }
 
     #[test]
     fn test_enforce_hard() {
-        let pol = BudgetPolicy { monthly_usd_limit: Some(10.0), policy: Some(PolicyKind::Hard) };
-        assert!(enforce_budget(&pol, 100.0, false).is_err());
-        assert!(enforce_budget(&pol, 5.0, false).is_ok());
+        let pol = BudgetPolicy { monthly_usd_limit: Some(Decimal::from(10)), policy: Some(PolicyKind::Hard) };
+        assert_eq!(enforce_budget(&pol, Decimal::from(100), false).unwrap_err(), "budget hard-limit exceeded (limit=$10.00, projected=$100.00)");
+        assert!(enforce_budget(&pol, Decimal::from(5), false).is_ok());
     }
 }
 
@@ -0,0 +1,179 @@
+//! Exact decimal money in the reporting currency, with static FX conversion for limits and prices
+//! stated in other currencies. Amounts serialize as JSON numbers.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+pub use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+
+/// Reporting currency and how many of its units one unit of each other currency buys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fx {
+    pub base: String,
+    rates: BTreeMap<String, Decimal>,
+}
+
+impl Default for Fx {
+    fn default() -> Self {
+        Self { base: "USD".into(), rates: BTreeMap::new() }
+    }
+}
+
+impl Fx {
+    /// `base` plus `EUR=1.08,GBP=1.27` (units of `base` per unit of each currency).
+    pub fn parse(base: &str, rates: &str) -> anyhow::Result<Self> {
+        let base = currency_code(base)?;
+        let mut out = BTreeMap::new();
+        for entry in rates.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (code, rate) = entry.split_once('=').ok_or_else(|| anyhow::anyhow!("BUDGET_FX_RATES entry `{}` is not CODE=rate", entry))?;
+            let rate: Decimal = rate.trim().parse().map_err(|_| anyhow::anyhow!("BUDGET_FX_RATES: bad rate for {}", code))?;
+            if rate <= Decimal::ZERO { anyhow::bail!("BUDGET_FX_RATES: rate for {} must be positive", code); }
+            out.insert(currency_code(code)?, rate);
+        }
+        Ok(Self { base, rates: out })
+    }
+
+    /// `amount` in `currency` expressed in the reporting currency.
+    pub fn to_base(&self, amount: Decimal, currency: &str) -> anyhow::Result<Decimal> {
+        let code = currency.trim().to_ascii_uppercase();
+        if code == self.base { return Ok(amount); }
+        let rate = self.rates.get(&code).ok_or_else(|| anyhow::anyhow!(
+            "no FX rate from {} to {} (set BUDGET_FX_RATES, e.g. {}=1.0)", code, self.base, code
+        ))?;
+        Ok(amount * rate)
+    }
+}
+
+/// Three-letter ISO 4217 style code, uppercased.
+pub fn currency_code(s: &str) -> anyhow::Result<String> {
+    let code = s.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        anyhow::bail!("currency `{}` is not a three-letter code", s);
+    }
+    Ok(code)
+}
+
+fn slot() -> &'static RwLock<Arc<Fx>> {
+    static FX: OnceLock<RwLock<Arc<Fx>>> = OnceLock::new();
+    FX.get_or_init(|| RwLock::new(Arc::new(Fx::default())))
+}
+
+pub fn fx() -> Arc<Fx> {
+    slot().read().unwrap().clone()
+}
+
+pub fn install_fx(fx: Fx) {
+    *slot().write().unwrap() = Arc::new(fx);
+}
+
+/// The currency every amount in outputs is reported in.
+pub fn currency() -> String {
+    fx().base.clone()
+}
+
+/// `amount` rounded to `dp` places with the reporting currency: `$12.34` for USD, else `12.34 EUR`.
+pub fn format(amount: Decimal, dp: u32) -> String {
+    let amount = amount.round_dp(dp);
+    match currency().as_str() {
+        "USD" => format!("${:.*}", dp as usize, amount),
+        code => format!("{:.*} {}", dp as usize, amount, code),
+    }
+}
+
+/// Serde for amounts in files (ledger, settings): written as strings so they read back exactly,
+/// while outputs keep JSON numbers. Numbers, as earlier files hold them, are still read.
+pub mod exact {
+    use rust_decimal::prelude::FromPrimitive;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Decimal;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Text(String),
+        Number(f64),
+    }
+
+    impl Repr {
+        fn decimal<E: serde::de::Error>(self) -> Result<Decimal, E> {
+            match self {
+                Repr::Text(s) => s.trim().parse().map_err(|_| E::custom(format!("bad amount `{}`", s))),
+                Repr::Number(n) => Decimal::from_f64(n).ok_or_else(|| E::custom(format!("bad amount {}", n))),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(d: &Decimal, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(d)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Decimal, D::Error> {
+        Repr::deserialize(d)?.decimal()
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(d: &Option<Decimal>, s: S) -> Result<S::Ok, S::Error> {
+            match d {
+                Some(d) => s.collect_str(d),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Decimal>, D::Error> {
+            Option::<Repr>::deserialize(d)?.map(Repr::decimal).transpose()
+        }
+    }
+}
+
+/// A measured quantity (cores, GiB, hours) as a decimal; non-finite values count as zero.
+pub fn dec(v: f64) -> Decimal {
+    Decimal::from_f64(v).unwrap_or_default()
+}
+
+/// Milliseconds as exact decimal hours.
+pub fn hours_from_ms(ms: u64) -> Decimal {
+    Decimal::from(ms) / Decimal::from(3_600_000u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fx_and_exact_sums() {
+        let fx = Fx::parse("usd", "EUR=1.08, gbp=1.27").unwrap();
+        assert_eq!(fx.to_base(Decimal::new(100, 0), "eur").unwrap(), Decimal::new(108, 0));
+        assert_eq!(fx.to_base(Decimal::new(5, 1), "USD").unwrap(), Decimal::new(5, 1));
+        assert!(fx.to_base(Decimal::ONE, "JPY").unwrap_err().to_string().contains("BUDGET_FX_RATES"));
+        assert!(Fx::parse("USD", "EUR=-1").is_err());
+        assert!(Fx::parse("dollars", "").is_err());
+
+        // ten thousand one-cent charges add up to exactly 100
+        let cent = Decimal::new(1, 2);
+        assert_eq!((0..10_000).map(|_| cent).sum::<Decimal>(), Decimal::new(100, 0));
+        assert_eq!(hours_from_ms(5_400_000), Decimal::new(15, 1));
+    }
+
+    #[test]
+    fn test_exact_serde_round_trips() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Amounts {
+            #[serde(with = "exact")]
+            usd: Decimal,
+            #[serde(default, with = "exact::option")]
+            limit: Option<Decimal>,
+        }
+        let big: Decimal = "123456789012345.67".parse().unwrap();
+        let text = serde_json::to_string(&Amounts { usd: big, limit: Some(Decimal::new(1, 2)) }).unwrap();
+        assert_eq!(text, r#"{"usd":"123456789012345.67","limit":"0.01"}"#);
+        let back: Amounts = serde_json::from_str(&text).unwrap();
+        assert_eq!((back.usd, back.limit), (big, Some(Decimal::new(1, 2))));
+        // numbers from earlier files, and a missing or null limit
+        let old: Amounts = serde_json::from_str(r#"{"usd": 1.5, "limit": null}"#).unwrap();
+        assert_eq!((old.usd, old.limit), (Decimal::new(15, 1), None));
+        assert!(serde_json::from_str::<Amounts>(r#"{"usd": "lots"}"#).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Pricing catalog: per-backend, per-region, per-instance-class and per-GPU-type hourly rates, and
+//! per-model token prices for upstream API calls.
+//! A catalog file (TOML or JSON) is laid over the built-in table and can be reloaded at runtime.
+//! Rates are converted to the reporting currency when the catalog is loaded.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::money::{self, Decimal};
+
+/// Rates that replace the ones they are laid over; unset fields keep the underlying rate.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateOverride {
+    pub cpu_hour: Option<Decimal>,
+    pub memory_gib_hour: Option<Decimal>,
+    pub gpu_hour: Option<Decimal>,
+    /// GPU product (e.g. `NVIDIA-A100-SXM4-80GB`) to hourly rate per GPU
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpu_types: BTreeMap<String, Decimal>,
+}
+
+impl RateOverride {
+    fn has_negative(&self) -> bool {
+        [self.cpu_hour, self.memory_gib_hour, self.gpu_hour].into_iter().flatten().chain(self.gpu_types.values().copied()).any(|r| r.is_sign_negative())
+    }
+
+    fn convert(&mut self, fx: &money::Fx, currency: &str) -> anyhow::Result<()> {
+        for rate in [&mut self.cpu_hour, &mut self.memory_gib_hour, &mut self.gpu_hour].into_iter().flatten().chain(self.gpu_types.values_mut()) {
+            *rate = fx.to_base(*rate, currency)?;
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: RateOverride) {
+        self.cpu_hour = other.cpu_hour.or(self.cpu_hour);
+        self.memory_gib_hour = other.memory_gib_hour.or(self.memory_gib_hour);
+        self.gpu_hour = other.gpu_hour.or(self.gpu_hour);
+        self.gpu_types.extend(other.gpu_types);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackendPrices {
+    /// Currency of this backend's rates; default: the catalog's
+    pub currency: Option<String>,
+    pub cpu_hour: Option<Decimal>,
+    pub memory_gib_hour: Option<Decimal>,
+    pub gpu_hour: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpu_types: BTreeMap<String, Decimal>,
+    /// Region priced when a request names none
+    pub default_region: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub regions: BTreeMap<String, RateOverride>,
+    /// e.g. `spot`, `reserved`, `m6i.xlarge`; applied after the region
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instance_classes: BTreeMap<String, RateOverride>,
+}
+
+impl BackendPrices {
+    /// The backend-wide rates, without region or class overrides.
+    fn rates(&self) -> RateOverride {
+        RateOverride { cpu_hour: self.cpu_hour, memory_gib_hour: self.memory_gib_hour, gpu_hour: self.gpu_hour, gpu_types: self.gpu_types.clone() }
+    }
+
+    fn has_negative(&self) -> bool {
+        self.rates().has_negative() || self.regions.values().chain(self.instance_classes.values()).any(RateOverride::has_negative)
+    }
+
+    /// Express every rate in the reporting currency.
+    fn convert(&mut self, fx: &money::Fx, currency: &str) -> anyhow::Result<()> {
+        let mut rates = self.rates();
+        rates.convert(fx, currency)?;
+        (self.cpu_hour, self.memory_gib_hour, self.gpu_hour, self.gpu_types) = (rates.cpu_hour, rates.memory_gib_hour, rates.gpu_hour, rates.gpu_types);
+        for r in self.regions.values_mut().chain(self.instance_classes.values_mut()) { r.convert(fx, currency)?; }
+        self.currency = None;
+        Ok(())
+    }
+
+    fn merge(&mut self, other: BackendPrices) {
+        let mut rates = self.rates();
+        rates.merge(other.rates());
+        (self.cpu_hour, self.memory_gib_hour, self.gpu_hour, self.gpu_types) = (rates.cpu_hour, rates.memory_gib_hour, rates.gpu_hour, rates.gpu_types);
+        self.default_region = other.default_region.or(self.default_region.take());
+        for (k, v) in other.regions { self.regions.entry(k).or_default().merge(v); }
+        for (k, v) in other.instance_classes { self.instance_classes.entry(k).or_default().merge(v); }
+    }
+}
+
+/// Prices per million tokens of one model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrices {
+    /// Currency of this model's prices; default: the catalog's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub input_per_mtok: Option<Decimal>,
+    pub output_per_mtok: Option<Decimal>,
+    /// Prompt tokens read from a cache; default: `input_per_mtok`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input_per_mtok: Option<Decimal>,
+}
+
+impl ModelPrices {
+    fn prices(&mut self) -> impl Iterator<Item = &mut Decimal> {
+        [&mut self.input_per_mtok, &mut self.output_per_mtok, &mut self.cached_input_per_mtok].into_iter().flatten()
+    }
+}
+
+/// Backend name (or `*` for every backend) to prices, and model name to token prices.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    /// Currency the file's rates are stated in (default USD); loaded catalogs hold the reporting currency
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub backends: BTreeMap<String, BackendPrices>,
+    /// `gpt-4o`, a prefix such as `claude-3-5-*`, or `*` for any model without its own entry
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub models: BTreeMap<String, ModelPrices>,
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    #[serde(skip)]
+    pub loaded_at_ms: u128,
+}
+
+/// Rates that apply to one backend/region/class after every layer is resolved.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveRates {
+    pub backend: String,
+    pub region: Option<String>,
+    pub instance_class: Option<String>,
+    pub currency: String,
+    pub cpu_hour: Decimal,
+    pub memory_gib_hour: Decimal,
+    pub gpu_hour: Decimal,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpu_types: BTreeMap<String, Decimal>,
+}
+
+impl EffectiveRates {
+    /// Hourly rate of one GPU of `gpu_type`; a comma-separated list of alternatives is priced at the dearest known one.
+    pub fn gpu_rate(&self, gpu_type: Option<&str>) -> Decimal {
+        gpu_type.into_iter()
+            .flat_map(|t| t.split(','))
+            .filter_map(|t| self.gpu_types.get(t.trim()).copied())
+            .max()
+            .unwrap_or(self.gpu_hour)
+    }
+}
+
+/// Built-in rates in thousandths of a USD per hour: vCPU, GiB, GPU.
+fn rates(cpu: i64, mem: i64, gpu: i64) -> BackendPrices {
+    let milli = |v| Some(Decimal::new(v, 3));
+    BackendPrices { cpu_hour: milli(cpu), memory_gib_hour: milli(mem), gpu_hour: milli(gpu), ..Default::default() }
+}
+
+impl Catalog {
+    /// The fallback table used when no catalog file is configured, in USD per hour per unit.
+    fn builtin_usd() -> Self {
+        let backends = [
+            ("*", rates(40, 5, 1500)),
+            ("ecs", rates(40, 5, 1500)),
+            ("kubernetes", rates(35, 4, 1400)),
+            ("cloud_run", rates(45, 6, 1800)),
+            ("aca", rates(45, 6, 1800)),
+            ("asg", rates(30, 4, 1300)),
+            ("mig", rates(30, 4, 1300)),
+            ("vmss", rates(30, 4, 1300)),
+        ];
+        Self {
+            currency: Some("USD".into()), backends: backends.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            models: BTreeMap::new(), source: None, loaded_at_ms: crate::util::now_ms(),
+        }
+    }
+
+    /// The built-in table in the reporting currency; needs a USD FX rate unless that is USD.
+    pub fn builtin() -> anyhow::Result<Self> {
+        let mut catalog = Self::builtin_usd();
+        catalog.convert(&money::fx())?;
+        Ok(catalog)
+    }
+
+    fn convert(&mut self, fx: &money::Fx) -> anyhow::Result<()> {
+        let currency = self.currency.take().unwrap_or_else(|| "USD".into());
+        for prices in self.backends.values_mut() {
+            let own = prices.currency.clone().unwrap_or_else(|| currency.clone());
+            prices.convert(fx, &own)?;
+        }
+        for prices in self.models.values_mut() {
+            let own = prices.currency.take().unwrap_or_else(|| currency.clone());
+            for p in prices.prices() { *p = fx.to_base(*p, &own)?; }
+        }
+        self.currency = Some(fx.base.clone());
+        Ok(())
+    }
+
+    /// Parse a catalog (`.toml` by extension, JSON otherwise) and lay it over the built-in table.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("pricing catalog {}: {}", path.display(), e))?;
+        let mut file: Catalog = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml")) {
+            toml::from_str(&text).map_err(|e| anyhow::anyhow!("pricing catalog {}: {}", path.display(), e))?
+        } else {
+            serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("pricing catalog {}: {}", path.display(), e))?
+        };
+        let mut catalog = Self::builtin()?;
+        file.convert(&money::fx()).map_err(|e| anyhow::anyhow!("pricing catalog {}: {}", path.display(), e))?;
+        for (name, prices) in file.backends {
+            if prices.has_negative() {
+                anyhow::bail!("pricing catalog {}: negative rate for {}", path.display(), name);
+            }
+            catalog.backends.entry(backend_key(&name)).or_default().merge(prices);
+        }
+        for (name, mut prices) in file.models {
+            if prices.prices().any(|p| p.is_sign_negative()) {
+                anyhow::bail!("pricing catalog {}: negative price for model {}", path.display(), name);
+            }
+            catalog.models.insert(name, prices);
+        }
+        catalog.source = Some(path.to_path_buf());
+        Ok(catalog)
+    }
+
+    /// Resolve `*`, then the backend, then its region (or `default_region`), then the instance class.
+    pub fn rates(&self, backend: &str, region: Option<&str>, instance_class: Option<&str>) -> EffectiveRates {
+        let key = backend_key(backend);
+        let mut r = RateOverride::default();
+        let mut default_region = None;
+        for layer in [self.backends.get("*"), self.backends.get(&key)].into_iter().flatten() {
+            r.merge(layer.rates());
+            default_region = layer.default_region.clone().or(default_region);
+        }
+        let region = region.map(str::to_string).or(default_region);
+        if let Some(prices) = self.backends.get(&key) {
+            if let Some(o) = region.as_deref().and_then(|reg| prices.regions.get(reg)) { r.merge(o.clone()); }
+            if let Some(o) = instance_class.and_then(|c| prices.instance_classes.get(c)) { r.merge(o.clone()); }
+        }
+        EffectiveRates {
+            backend: key,
+            currency: self.currency.clone().unwrap_or_else(money::currency),
+            region,
+            instance_class: instance_class.map(str::to_string),
+            cpu_hour: r.cpu_hour.unwrap_or_default(),
+            memory_gib_hour: r.memory_gib_hour.unwrap_or_default(),
+            gpu_hour: r.gpu_hour.unwrap_or_default(),
+            gpu_types: r.gpu_types,
+        }
+    }
+
+    /// Prices for `model`: its own entry, else the longest matching `prefix*` entry, else `*`.
+    pub fn model(&self, model: Option<&str>) -> Option<(&str, &ModelPrices)> {
+        let name = model.unwrap_or_default();
+        self.models.get_key_value(name)
+            .or_else(|| self.models.iter().filter(|(k, _)| k.strip_suffix('*').is_some_and(|p| name.starts_with(p))).max_by_key(|(k, _)| k.len()))
+            .map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// Canonical backend name for aliases (`k8s`, `gcp`, ...); other keys are only lowercased.
+fn backend_key(name: &str) -> String {
+    if name == "*" { return name.into(); }
+    crate::orchestrator::canonical(name).map(str::to_string).unwrap_or_else(|| name.to_ascii_lowercase())
+}
+
+fn slot() -> &'static RwLock<Arc<Catalog>> {
+    static CATALOG: OnceLock<RwLock<Arc<Catalog>>> = OnceLock::new();
+    CATALOG.get_or_init(|| RwLock::new(Arc::new(Catalog::builtin_usd())))
+}
+
+/// The catalog in effect for every estimate.
+pub fn current() -> Arc<Catalog> {
+    slot().read().unwrap().clone()
+}
+
+pub fn install(catalog: Catalog) {
+    *slot().write().unwrap() = Arc::new(catalog);
+}
+
+/// Re-read the catalog file in effect; on error the current catalog stays.
+pub fn reload() -> anyhow::Result<Arc<Catalog>> {
+    match current().source.clone() {
+        Some(path) => install(Catalog::load(&path)?),
+        None => install(Catalog::builtin()?),
+    }
+    Ok(current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_layers() {
+        let path = std::env::temp_dir().join(format!("ectusr2-pricing-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"
+[backends.k8s]
+cpu_hour = 0.05
+default_region = "eu-west-1"
+gpu_types = { "NVIDIA-L4" = 0.8 }
+
+[backends.kubernetes.regions.eu-west-1]
+memory_gib_hour = 0.006
+
+[backends.kubernetes.instance_classes.spot]
+cpu_hour = 0.015
+gpu_types = { "NVIDIA-A100-SXM4-80GB" = 2.5 }
+"#).unwrap();
+        let c = Catalog::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let d = |v: i64, dp: u32| Decimal::new(v, dp);
+        let r = c.rates("kubernetes", None, None);
+        assert_eq!((r.cpu_hour, r.memory_gib_hour, r.gpu_hour, r.region.as_deref()), (d(5, 2), d(6, 3), d(14, 1), Some("eu-west-1")));
+        assert_eq!(r.gpu_rate(Some("NVIDIA-L4")), d(8, 1));
+        assert_eq!(r.gpu_rate(Some("H100")), d(14, 1));
+        let spot = c.rates("kubernetes", Some("us-east-1"), Some("spot"));
+        assert_eq!((spot.cpu_hour, spot.memory_gib_hour), (d(15, 3), d(4, 3)));
+        assert_eq!(spot.gpu_rate(Some("NVIDIA-L4, NVIDIA-A100-SXM4-80GB")), d(25, 1));
+        // untouched backends keep the built-in rates; unknown ones fall back to `*`
+        assert_eq!(c.rates("ecs", None, None).cpu_hour, d(40, 3));
+        assert_eq!(c.rates("nomad", None, None).gpu_hour, d(15, 1));
+        assert_eq!(r.currency, "USD");
+
+        // file and backend currencies are converted to the reporting currency on load
+        let mut eur: Catalog = toml::from_str("currency = \"EUR\"\n[backends.ecs]\ncpu_hour = 0.1\n[backends.aca]\ncurrency = \"GBP\"\ngpu_types = { L4 = 1 }\n").unwrap();
+        eur.convert(&money::Fx::parse("USD", "EUR=1.10,GBP=1.25").unwrap()).unwrap();
+        assert_eq!((eur.backends["ecs"].cpu_hour, eur.backends["aca"].gpu_types["L4"]), (Some(d(11, 2)), d(125, 2)));
+        assert_eq!(eur.currency.as_deref(), Some("USD"));
+        assert!(Catalog::builtin_usd().convert(&money::Fx::parse("EUR", "").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_catalog_model_prices() {
+        let mut c: Catalog = toml::from_str(r#"
+currency = "EUR"
+[models."*"]
+input_per_mtok = 1
+[models."claude-*"]
+input_per_mtok = 3
+[models."claude-3-5-haiku-*"]
+input_per_mtok = 0.8
+output_per_mtok = 4
+[models.gpt-4o]
+currency = "USD"
+input_per_mtok = 2.5
+"#).unwrap();
+        c.convert(&money::Fx::parse("USD", "EUR=1.10").unwrap()).unwrap();
+        let input = |m: Option<&str>| c.model(m).map(|(k, p)| (k.to_string(), p.input_per_mtok.unwrap()));
+        assert_eq!(input(Some("claude-3-5-haiku-20241022")), Some(("claude-3-5-haiku-*".into(), Decimal::new(88, 2))));
+        assert_eq!(input(Some("claude-sonnet-4")), Some(("claude-*".into(), Decimal::new(33, 1))));
+        assert_eq!(input(Some("gpt-4o")), Some(("gpt-4o".into(), Decimal::new(25, 1))));
+        assert_eq!(input(None), Some(("*".into(), Decimal::new(11, 1))));
+        assert!(Catalog::builtin_usd().model(Some("gpt-4o")).is_none());
+    }
+
+    #[test]
+    fn test_catalog_rejects_bad_files() {
+        let path = std::env::temp_dir().join(format!("ectusr2-pricing-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"backends": {"ecs": {"cpu_hours": 0.1}}}"#).unwrap();
+        assert!(Catalog::load(&path).unwrap_err().to_string().contains("cpu_hours"));
+        std::fs::write(&path, r#"{"backends": {"ecs": {"cpu_hour": -1}}}"#).unwrap();
+        assert!(Catalog::load(&path).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,101 @@
+//! Spend held by changes between their budget check and their booking in the ledger, so concurrent
+//! callers check against the headroom that is left rather than the same headroom twice. Holds are
+//! ledger entries written under the ledger's file lock, so this covers every server sharing the ledger.
+
+use serde_json::{json, Value};
+
+use crate::budget::hierarchy;
+use crate::budget::ledger::{Ledger, Locked};
+use crate::budget::money::Decimal;
+
+/// How long a hold lasts unless released first; longer than any orchestrator or upstream call.
+pub const HOLD_TTL_MS: u64 = 15 * 60 * 1000;
+
+/// Check and reserve under the ledger's lock; it must not be held across an await.
+pub fn lock(ledger: &Ledger) -> Held<'_> {
+    Held { ledger, locked: ledger.lock() }
+}
+
+/// Changes in flight, for `budget_status`.
+pub fn describe(ledger: &Ledger) -> Vec<Value> {
+    ledger.holds().into_iter().map(|h| json!({"pool": h.pool, "budget": h.budget, "usd": h.usd, "expires_ms": h.expires_ms})).collect()
+}
+
+pub struct Held<'a> {
+    ledger: &'a Ledger,
+    locked: Locked<'a>,
+}
+
+impl<'a> Held<'a> {
+    /// The locked ledger, for spend: the ledger cannot be read through its other methods until this is dropped.
+    pub fn ledger(&self) -> &Locked<'a> {
+        &self.locked
+    }
+
+    /// Held against `budget` (any change billed to it or below), or against the global limit for None.
+    pub fn usd(&self, budget: Option<&str>) -> Decimal {
+        self.locked.holds().iter()
+            .filter(|h| budget.is_none_or(|b| h.budget.as_deref().is_some_and(|hb| hierarchy::within(hb, b))))
+            .map(|h| h.usd).sum()
+    }
+
+    /// Hold `usd` for `pool` until the returned reservation is committed or dropped.
+    pub fn hold(mut self, pool: &str, budget: Option<&str>, usd: Decimal) -> Reservation<'a> {
+        let id = self.locked.hold(pool, budget, usd, HOLD_TTL_MS);
+        Reservation { ledger: self.ledger, id }
+    }
+}
+
+/// Released when dropped, e.g. when the orchestrator call fails.
+pub struct Reservation<'a> {
+    ledger: &'a Ledger,
+    id: String,
+}
+
+impl Reservation<'_> {
+    /// The change is booked in the ledger, which now accounts for it.
+    pub fn commit(self) {}
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.ledger.release(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reservations_reduce_headroom_until_released() {
+        let l = Ledger::memory();
+        let a = lock(&l).hold("p1", Some("acme/ml"), Decimal::from(40));
+        let b = lock(&l).hold("p2", None, Decimal::from(5));
+        let held = lock(&l);
+        assert_eq!((held.usd(None), held.usd(Some("acme")), held.usd(Some("acme/ml")), held.usd(Some("acme/web"))),
+            (Decimal::from(45), Decimal::from(40), Decimal::from(40), Decimal::ZERO));
+        drop(held);
+        a.commit();
+        assert_eq!(describe(&l).len(), 1);
+        assert_eq!(describe(&l)[0]["usd"], json!(5.0));
+        drop(b);
+        assert!(describe(&l).is_empty());
+        // holds are not spend
+        assert_eq!(l.spend().unwrap().month_to_date_usd, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_holds_are_shared_through_the_ledger_file() {
+        let path = std::env::temp_dir().join(format!("ectusr2-holds-{}.jsonl", uuid::Uuid::new_v4()));
+        let (a, b) = (Ledger::open(&path).unwrap(), Ledger::open(&path).unwrap());
+        let r = lock(&a).hold("p1", Some("acme"), Decimal::from(40));
+        assert_eq!(lock(&b).usd(Some("acme")), Decimal::from(40));
+        drop(r);
+        assert_eq!(lock(&b).usd(None), Decimal::ZERO);
+        // a hold left by a server that died lapses
+        a.lock().hold("p2", None, Decimal::ONE, 0);
+        assert!(describe(&b).is_empty());
+        std::fs::remove_file(&path).ok();
+    }
+}
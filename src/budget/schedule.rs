@@ -0,0 +1,187 @@
+//! When a pool runs: weekly schedules (e.g. business hours) and the running hours they allow in a window.
+
+use serde_json::Value;
+
+const MIN_MS: i64 = 60_000;
+const DAY_MS: i64 = 24 * 60 * MIN_MS;
+const WEEK_MS: i64 = 7 * DAY_MS;
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Active on `days` from `start_min` for `len_min` minutes (may run past midnight), in local time at `utc_offset_min`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    days: [bool; 7],
+    start_min: i64,
+    len_min: i64,
+    utc_offset_min: i64,
+}
+
+impl Default for Schedule {
+    fn default() -> Self { Self::always() }
+}
+
+impl Schedule {
+    pub fn always() -> Self {
+        Self { days: [true; 7], start_min: 0, len_min: 24 * 60, utc_offset_min: 0 }
+    }
+
+    pub fn is_always(&self) -> bool {
+        self.days == [true; 7] && self.len_min == 24 * 60
+    }
+
+    /// `"always"`, `"weekdays"`, `"business_hours"` (Mon-Fri 09:00-17:00), or
+    /// `{ days: "mon-fri" | ["sat","sun"], start: "08:00", end: "20:00", utc_offset: "+02:00" }`.
+    pub fn from_value(v: &Value) -> Result<Self, String> {
+        let weekdays = [true, true, true, true, true, false, false];
+        match v {
+            Value::Null => Ok(Self::always()),
+            Value::String(s) => match s.to_ascii_lowercase().as_str() {
+                "always" | "24x7" => Ok(Self::always()),
+                "weekdays" => Ok(Self { days: weekdays, ..Self::always() }),
+                "business_hours" => Ok(Self { days: weekdays, start_min: 9 * 60, len_min: 8 * 60, utc_offset_min: 0 }),
+                other => Err(format!("unknown schedule `{}` (always, weekdays, business_hours or an object)", other)),
+            },
+            Value::Object(m) => {
+                if let Some(k) = m.keys().find(|k| !matches!(k.as_str(), "days" | "start" | "end" | "utc_offset")) {
+                    return Err(format!("schedule: unknown field `{}`", k));
+                }
+                let days = match m.get("days") {
+                    None => [true; 7],
+                    Some(Value::String(s)) => parse_days(s.split(','))?,
+                    Some(Value::Array(a)) => parse_days(a.iter().map(|d| d.as_str().unwrap_or("")))?,
+                    Some(_) => return Err("schedule: `days` must be a string or list".into()),
+                };
+                let clock = |key: &str, default: i64| m.get(key).and_then(|v| v.as_str()).map(parse_clock).unwrap_or(Ok(default));
+                let (start, end) = (clock("start", 0)?, clock("end", 24 * 60)?);
+                let len = (end - start).rem_euclid(24 * 60);
+                let utc_offset_min = match m.get("utc_offset").and_then(|v| v.as_str()) {
+                    Some(o) => parse_offset(o)?,
+                    None => 0,
+                };
+                Ok(Self { days, start_min: start % (24 * 60), len_min: if len == 0 { 24 * 60 } else { len }, utc_offset_min })
+            }
+            _ => Err("schedule must be a string or an object".into()),
+        }
+    }
+
+    /// Hours the schedule is active between two UTC instants (milliseconds).
+    pub fn running_hours(&self, start_ms: u64, end_ms: u64) -> f64 {
+        if end_ms <= start_ms { return 0.0; }
+        let off = self.utc_offset_min * MIN_MS;
+        let (from, to) = (start_ms as i64 + off, end_ms.min(i64::MAX as u64 / 2) as i64 + off);
+        // every whole week runs the same hours, so only the remainder is walked day by day
+        let weeks = (to - from) / WEEK_MS;
+        let weekly = if weeks > 0 { self.running_ms(0, WEEK_MS) } else { 0 };
+        (weeks as f64 * weekly as f64 + self.running_ms(from + weeks * WEEK_MS, to) as f64) / 3_600_000.0
+    }
+
+    /// Active milliseconds between two local instants.
+    fn running_ms(&self, from: i64, to: i64) -> i64 {
+        let mut ms = 0;
+        // a window that starts the previous day may run past midnight into this one
+        for day in from.div_euclid(DAY_MS) - 1..=to.div_euclid(DAY_MS) {
+            if !self.days[(day + 3).rem_euclid(7) as usize] { continue; } // 1970-01-01 was a Thursday
+            let on = day * DAY_MS + self.start_min * MIN_MS;
+            let off = on + self.len_min * MIN_MS;
+            ms += (off.min(to) - on.max(from)).max(0);
+        }
+        ms
+    }
+
+    pub fn describe(&self) -> String {
+        if self.is_always() { return "24x7".into(); }
+        let days: Vec<&str> = DAYS.iter().zip(self.days).filter(|(_, on)| *on).map(|(d, _)| *d).collect();
+        let end = (self.start_min + self.len_min) % (24 * 60);
+        let sign = if self.utc_offset_min < 0 { '-' } else { '+' };
+        format!("{} {:02}:{:02}-{:02}:{:02} UTC{}{:02}:{:02}", days.join(","), self.start_min / 60, self.start_min % 60, end / 60, end % 60,
+            sign, self.utc_offset_min.abs() / 60, self.utc_offset_min.abs() % 60)
+    }
+}
+
+fn day_index(d: &str) -> Result<usize, String> {
+    let d = d.trim().to_ascii_lowercase();
+    DAYS.iter().position(|x| d.starts_with(x)).ok_or_else(|| format!("schedule: unknown day `{}`", d))
+}
+
+/// `mon-fri`, `sat`, ... ranges may wrap (`fri-mon`).
+fn parse_days<'a>(parts: impl Iterator<Item = &'a str>) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for part in parts.filter(|p| !p.trim().is_empty()) {
+        let (a, b) = match part.split_once('-') { Some((a, b)) => (day_index(a)?, day_index(b)?), None => (day_index(part)?, day_index(part)?) };
+        let mut i = a;
+        loop {
+            days[i] = true;
+            if i == b { break; }
+            i = (i + 1) % 7;
+        }
+    }
+    if days == [false; 7] { return Err("schedule: no days selected".into()); }
+    Ok(days)
+}
+
+fn parse_clock(s: &str) -> Result<i64, String> {
+    let (h, m) = s.split_once(':').unwrap_or((s, "0"));
+    match (h.trim().parse::<i64>(), m.trim().parse::<i64>()) {
+        (Ok(h), Ok(m)) if (0..=24).contains(&h) && (0..60).contains(&m) && h * 60 + m <= 24 * 60 => Ok(h * 60 + m),
+        _ => Err(format!("schedule: bad time `{}` (HH:MM)", s)),
+    }
+}
+
+fn parse_offset(s: &str) -> Result<i64, String> {
+    let (sign, rest) = match s.trim().strip_prefix('-') { Some(r) => (-1, r), None => (1, s.trim().trim_start_matches('+')) };
+    let min = parse_clock(rest).map_err(|_| format!("schedule: bad utc_offset `{}` (+HH:MM)", s))?;
+    Ok(sign * min)
+}
+
+/// First millisecond of `YYYY-MM` (UTC).
+pub fn parse_month(s: &str) -> Result<u64, String> {
+    let bad = || format!("bad month `{}` (YYYY-MM)", s);
+    let (y, m) = s.split_once('-').ok_or_else(bad)?;
+    let month = time::Month::try_from(m.parse::<u8>().map_err(|_| bad())?).map_err(|_| bad())?;
+    let date = time::Date::from_calendar_date(y.parse().map_err(|_| bad())?, month, 1).map_err(|_| bad())?;
+    u64::try_from(date.midnight().assume_utc().unix_timestamp() * 1000).map_err(|_| bad())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 2025-03-03T00:00:00Z, a Monday
+    const MON: u64 = 1_740_960_000_000;
+    const H: u64 = 3_600_000;
+
+    #[test]
+    fn test_schedule_running_hours() {
+        let week = (MON, MON + 7 * 24 * H);
+        assert_eq!(Schedule::always().running_hours(week.0, week.1), 168.0);
+        assert_eq!(Schedule::from_value(&json!("weekdays")).unwrap().running_hours(week.0, week.1), 120.0);
+        let biz = Schedule::from_value(&json!("business_hours")).unwrap();
+        assert_eq!(biz.running_hours(week.0, week.1), 40.0);
+        assert_eq!(biz.running_hours(MON + 10 * H, MON + 12 * H), 2.0);
+        assert_eq!(biz.running_hours(MON + 5 * 24 * H, MON + 7 * 24 * H), 0.0);
+
+        // 22:00-06:00 local at UTC+02:00, Fri only: Fri 20:00Z to Sat 04:00Z
+        let night = Schedule::from_value(&json!({"days": "fri", "start": "22:00", "end": "06:00", "utc_offset": "+02:00"})).unwrap();
+        assert_eq!(night.running_hours(week.0, week.1), 8.0);
+        assert_eq!(night.running_hours(MON + (4 * 24 + 22) * H, MON + (5 * 24 + 2) * H), 4.0);
+        assert_eq!(night.describe(), "fri 22:00-06:00 UTC+02:00");
+
+        // whole weeks are counted without walking every day
+        let decade = 52 * 10 * 7 * 24 * H;
+        assert_eq!(biz.running_hours(MON + 10 * H, MON + 10 * H + decade), 40.0 * 520.0);
+        assert_eq!(night.running_hours(MON, MON + decade + 5 * 24 * H), 8.0 * 520.0 + 4.0);
+        assert!(Schedule::always().running_hours(0, u64::MAX) > 0.0);
+    }
+
+    #[test]
+    fn test_schedule_parse_errors_and_month() {
+        assert!(Schedule::from_value(&json!("nights")).is_err());
+        assert!(Schedule::from_value(&json!({"days": "mon-fry"})).is_err());
+        assert!(Schedule::from_value(&json!({"start": "25:00"})).is_err());
+        assert!(Schedule::from_value(&json!({"hours": 3})).is_err());
+        assert_eq!(Schedule::from_value(&json!({"days": ["sat", "sun"]})).unwrap().running_hours(MON, MON + 7 * 24 * H), 48.0);
+        assert_eq!(parse_month("2025-03").unwrap(), 1_740_787_200_000);
+        assert!(parse_month("2025-13").is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Budget limits and policies in effect: seeded from CLI/env, changed at runtime through
+//! `budget_config` by admins, persisted to a JSON file and audited in an append-only log.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::budget::hierarchy::{self, Budget, Budgets};
+use crate::budget::money::{self, Decimal};
+use crate::budget::{BudgetPolicy, PolicyKind};
+use crate::util::now_ms;
+
+/// Amounts are in `currency`, the reporting currency when the settings were written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default, with = "money::exact::option")]
+    pub monthly_usd_limit: Option<Decimal>,
+    pub policy: Option<String>,
+    #[serde(default)]
+    pub budgets: Budgets,
+    #[serde(default = "usd")]
+    pub currency: String,
+}
+
+fn usd() -> String {
+    "USD".into()
+}
+
+impl Settings {
+    /// The global limit and policy; no policy means the limit is only reported.
+    pub fn global(&self) -> BudgetPolicy {
+        let policy = self.policy.as_deref().map(|p| if p.eq_ignore_ascii_case("hard") { PolicyKind::Hard } else { PolicyKind::Soft });
+        BudgetPolicy { monthly_usd_limit: self.monthly_usd_limit, policy }
+    }
+
+    /// Read a settings file written by an earlier run, converting its amounts to the reporting currency.
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut s: Settings = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let fx = money::fx();
+        s.monthly_usd_limit = s.monthly_usd_limit.map(|l| fx.to_base(l, &s.currency)).transpose()?;
+        for b in s.budgets.budgets.values_mut() {
+            b.currency.get_or_insert_with(|| s.currency.clone());
+        }
+        s.budgets = Budgets::parse(&serde_json::to_string(&s.budgets)?, false)?;
+        s.currency = fx.base.clone();
+        Ok(s)
+    }
+}
+
+/// One field changed through `budget_config`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub ts_ms: u64,
+    pub actor: String,
+    #[serde(default)]
+    pub client: Option<String>,
+    /// `monthly_usd_limit`, `policy` or `budgets/<path>`
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// Requested changes; `Some(None)` clears a value, a `None` budget removes it.
+#[derive(Debug, Clone, Default)]
+pub struct Change {
+    monthly_usd_limit: Option<Option<Decimal>>,
+    policy: Option<Option<String>>,
+    budgets: BTreeMap<String, Option<Value>>,
+    currency: Option<String>,
+}
+
+impl Change {
+    /// `{ monthly_usd_limit, policy, budgets: { "<path>": { monthly_usd_limit, policy } | null }, currency }`
+    pub fn from_args(args: &Value) -> Result<Self, String> {
+        let mut c = Change::default();
+        if let Some(v) = args.get("monthly_usd_limit") {
+            c.monthly_usd_limit = Some(if v.is_null() { None } else { Some(serde_json::from_value(v.clone()).map_err(|_| "monthly_usd_limit must be a number or null".to_string())?) });
+        }
+        if let Some(v) = args.get("policy") {
+            c.policy = Some(v.as_str().map(|p| check_policy(p).map(|_| p.to_ascii_lowercase())).transpose()?);
+        }
+        if let Some(v) = args.get("budgets") {
+            let m = v.as_object().ok_or("budgets must be an object of path to budget or null")?;
+            for (path, b) in m {
+                c.budgets.insert(hierarchy::normalize(path)?, (!b.is_null()).then(|| b.clone()));
+            }
+        }
+        c.currency = args.get("currency").and_then(|v| v.as_str()).map(money::currency_code).transpose().map_err(|e| e.to_string())?;
+        Ok(c)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.monthly_usd_limit.is_none() && self.policy.is_none() && self.budgets.is_empty()
+    }
+}
+
+fn check_policy(p: &str) -> Result<(), String> {
+    if p.eq_ignore_ascii_case("hard") || p.eq_ignore_ascii_case("soft") { Ok(()) } else { Err(format!("policy `{}` (hard or soft)", p)) }
+}
+
+pub struct BudgetStore {
+    settings: RwLock<Arc<Settings>>,
+    path: Option<PathBuf>,
+    /// Modification time and size of `path` when last read or written; a change means another process wrote it
+    seen: Mutex<Option<(SystemTime, u64)>>,
+    audit_path: Option<PathBuf>,
+    audit: Mutex<Vec<AuditEntry>>,
+    /// token to admin name
+    admins: BTreeMap<String, String>,
+}
+
+impl BudgetStore {
+    /// `path` holds settings changed at runtime and, when present, wins over `initial` (CLI/env);
+    /// the audit log lives next to it. An unreadable file is logged and `initial` used.
+    pub fn open(initial: Settings, path: Option<PathBuf>, admins: BTreeMap<String, String>) -> Self {
+        let seen = path.as_deref().and_then(stamp);
+        let settings = match path.as_deref().filter(|p| p.exists()).map(Settings::load) {
+            Some(Ok(s)) => s,
+            Some(Err(e)) => {
+                warn!(error = %e, "budget settings unreadable; using CLI/env settings");
+                initial
+            }
+            None => initial,
+        };
+        let audit_path = path.as_ref().map(|p| p.with_file_name("budget-audit.jsonl"));
+        let audit = audit_path.as_deref().and_then(|p| std::fs::read_to_string(p).ok()).unwrap_or_default()
+            .lines().filter_map(|l| serde_json::from_str(l).ok()).collect();
+        Self { settings: RwLock::new(Arc::new(settings)), path, seen: Mutex::new(seen), audit_path, audit: Mutex::new(audit), admins }
+    }
+
+    /// The settings in effect, re-read first when another process has changed the file.
+    pub fn current(&self) -> Arc<Settings> {
+        self.reload(false);
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Re-read `path` if it changed since it was last read or written (or always, with `force`). An unreadable
+    /// file is logged once and the settings in effect kept; a deleted one keeps them too, until the next restart.
+    fn reload(&self, force: bool) {
+        let Some(path) = &self.path else { return };
+        let Some(now) = stamp(path) else { return };
+        let mut seen = self.seen.lock().unwrap();
+        if *seen == Some(now) && !force { return; }
+        match Settings::load(path) {
+            Ok(s) => {
+                if *seen != Some(now) { info!(path = %path.display(), "budget settings changed on disk; reloaded"); }
+                *self.settings.write().unwrap() = Arc::new(s);
+            }
+            Err(e) => warn!(path = %path.display(), error = %e, "budget settings unreadable; keeping the settings in effect"),
+        }
+        *seen = Some(now);
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn writable(&self) -> bool {
+        !self.admins.is_empty()
+    }
+
+    /// The admin holding `token`.
+    pub fn authorize(&self, token: Option<&str>) -> Result<String, String> {
+        if self.admins.is_empty() { return Err("budget_config is read-only: no BUDGET_ADMIN_TOKENS configured".into()); }
+        let token = token.ok_or("budget_config changes require `admin_token`")?;
+        // compare every configured token so the match position is not observable
+        let mut actor = None;
+        for (t, name) in &self.admins {
+            if constant_time_eq(t.as_bytes(), token.as_bytes()) { actor = Some(name.clone()); }
+        }
+        actor.ok_or_else(|| "admin_token not recognised".into())
+    }
+
+    /// The last `n` audit entries, oldest first.
+    pub fn audit(&self, n: usize) -> Vec<AuditEntry> {
+        let audit = self.audit.lock().unwrap();
+        audit[audit.len().saturating_sub(n)..].to_vec()
+    }
+
+    /// Validate `change` against the current settings, persist, audit and put it in effect.
+    pub fn apply(&self, change: &Change, actor: &str, client: Option<&str>) -> anyhow::Result<Vec<AuditEntry>> {
+        // one writer at a time across processes, starting from what the last one wrote; the settings
+        // file itself is replaced on write, so a lock file beside it is what is locked
+        let _lock = self.path.as_deref().map(lock_exclusive).transpose()?;
+        self.reload(true);
+        let mut settings = self.settings.write().unwrap();
+        let mut next = (**settings).clone();
+        let fx = money::fx();
+        let currency = change.currency.clone().unwrap_or_else(|| fx.base.clone());
+        let mut entries = Vec::new();
+        let mut record = |field: String, from: Value, to: Value| {
+            if from != to {
+                entries.push(AuditEntry { ts_ms: now_ms() as u64, actor: actor.into(), client: client.map(str::to_string), field, from, to });
+            }
+        };
+
+        if let Some(limit) = change.monthly_usd_limit {
+            let limit = limit.map(|l| fx.to_base(l, &currency)).transpose()?;
+            if limit.is_some_and(|l| l.is_sign_negative()) { anyhow::bail!("monthly_usd_limit must not be negative"); }
+            record("monthly_usd_limit".into(), json!(next.monthly_usd_limit), json!(limit));
+            next.monthly_usd_limit = limit;
+        }
+        if let Some(policy) = &change.policy {
+            record("policy".into(), json!(next.policy), json!(policy));
+            next.policy = policy.clone();
+        }
+        for (path, update) in &change.budgets {
+            let before = next.budgets.budgets.get(path).cloned();
+            match update {
+                None => {
+                    if before.is_none() { anyhow::bail!("unknown budget `{}`", path); }
+                    next.budgets.budgets.remove(path);
+                }
+                Some(v) => {
+                    let mut b = before.clone().unwrap_or_default();
+                    let m = v.as_object().ok_or_else(|| anyhow::anyhow!("budgets `{}` must be an object or null", path))?;
+                    if let Some(k) = m.keys().find(|k| !matches!(k.as_str(), "monthly_usd_limit" | "monthly_limit" | "policy")) {
+                        anyhow::bail!("budgets `{}`: unknown field `{}`", path, k);
+                    }
+                    if let Some(l) = m.get("monthly_usd_limit").or_else(|| m.get("monthly_limit")) {
+                        let l: Option<Decimal> = serde_json::from_value(l.clone()).map_err(|_| anyhow::anyhow!("budgets `{}`: limit must be a number or null", path))?;
+                        if l.is_some_and(|l| l.is_sign_negative()) { anyhow::bail!("budgets `{}`: negative limit", path); }
+                        b.monthly_usd_limit = l.map(|l| fx.to_base(l, &currency)).transpose()?;
+                    }
+                    if let Some(p) = m.get("policy") {
+                        b.policy = p.as_str().map(|p| check_policy(p).map(|_| p.to_ascii_lowercase())).transpose().map_err(|e| anyhow::anyhow!("budgets `{}`: {}", path, e))?;
+                    }
+                    next.budgets.budgets.insert(path.clone(), Budget { currency: None, ..b });
+                }
+            }
+            record(format!("budgets/{}", path), json!(before.map(|b| b.describe())), json!(next.budgets.budgets.get(path).map(Budget::describe)));
+        }
+        for target in next.budgets.clients.values().chain(next.budgets.default.as_ref()) {
+            next.budgets.known(target).map_err(|e| anyhow::anyhow!("{} is still used by a client or as the default", e))?;
+        }
+        next.currency = fx.base.clone();
+
+        if entries.is_empty() { return Ok(entries); }
+        if let Some(path) = &self.path {
+            persist(path, &next)?;
+        }
+        if let Some(path) = &self.audit_path {
+            let lines: String = entries.iter().map(|e| serde_json::to_string(e).unwrap_or_default() + "\n").collect();
+            let res = OpenOptions::new().create(true).append(true).open(path).and_then(|mut f| f.write_all(lines.as_bytes()));
+            if let Err(e) = res { warn!(path = %path.display(), error = %e, "budget audit write failed; kept in memory only"); }
+        }
+        for e in &entries {
+            info!(actor = %e.actor, field = %e.field, from = %e.from, to = %e.to, "budget setting changed");
+        }
+        self.audit.lock().unwrap().extend(entries.iter().cloned());
+        *settings = Arc::new(next);
+        drop(settings);
+        if let Some(path) = &self.path {
+            *self.seen.lock().unwrap() = stamp(path);
+        }
+        Ok(entries)
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// `budget.json.lock` beside `path`, locked until the returned file is dropped.
+fn lock_exclusive(path: &Path) -> anyhow::Result<std::fs::File> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+    let lock = path.with_extension("json.lock");
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock).map_err(|e| anyhow::anyhow!("budget settings lock {}: {}", lock.display(), e))?;
+    file.lock().map_err(|e| anyhow::anyhow!("budget settings lock {}: {}", lock.display(), e))?;
+    Ok(file)
+}
+
+/// Write-then-rename so a crash never leaves a torn settings file; each write has its own temporary file.
+fn persist(path: &Path, settings: &Settings) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+    let tmp = path.with_extension(format!("json.{}.tmp", uuid::Uuid::new_v4()));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(settings)?)?;
+    std::fs::rename(&tmp, path).map_err(|e| anyhow::anyhow!("budget settings {}: {}", path.display(), e))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `alice=token1,bob=token2` (name per token, for the audit trail).
+pub fn parse_admins(s: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut out = BTreeMap::new();
+    for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, token) = entry.split_once('=').ok_or_else(|| anyhow::anyhow!("BUDGET_ADMIN_TOKENS entry is not name=token"))?;
+        if token.trim().len() < 16 { anyhow::bail!("BUDGET_ADMIN_TOKENS: token for `{}` is shorter than 16 characters", name.trim()); }
+        out.insert(token.trim().to_string(), name.trim().to_string());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    #[test]
+    fn test_apply_persists_and_audits() {
+        let path = std::env::temp_dir().join(format!("ectusr2-budget-{}/budget.json", uuid::Uuid::new_v4()));
+        let initial = Settings { monthly_usd_limit: Some(100.into()), policy: Some("soft".into()), ..Default::default() };
+        let store = BudgetStore::open(initial, Some(path.clone()), parse_admins(&format!("alice={}", TOKEN)).unwrap());
+        assert!(store.authorize(Some("wrong-token-0000")).is_err());
+        assert!(store.authorize(None).is_err());
+        let actor = store.authorize(Some(TOKEN)).unwrap();
+
+        let change = Change::from_args(&json!({"monthly_usd_limit": 250, "policy": "hard", "budgets": {"acme.ml": {"monthly_usd_limit": 50}}})).unwrap();
+        let entries = store.apply(&change, &actor, Some("cursor")).unwrap();
+        assert_eq!(entries.iter().map(|e| e.field.as_str()).collect::<Vec<_>>(), ["monthly_usd_limit", "policy", "budgets/acme/ml"]);
+        assert_eq!(entries[0].actor, "alice");
+        assert_eq!(store.current().global().policy, Some(PolicyKind::Hard));
+
+        // a new store over the same file sees the change and the audit trail, not the CLI/env values
+        let reopened = BudgetStore::open(Settings::default(), Some(path.clone()), BTreeMap::new());
+        assert_eq!(reopened.current().monthly_usd_limit, Some(250.into()));
+        assert_eq!(reopened.current().budgets.budgets["acme/ml"].monthly_usd_limit, Some(50.into()));
+        assert_eq!(reopened.audit(10).len(), 3);
+        assert_eq!(reopened.audit(10)[2].to, json!({"monthly_usd_limit": 50.0, "policy": null}));
+        // amounts are persisted as strings, so they read back exactly
+        let big: Decimal = "123456789012345.67".parse().unwrap();
+        store.apply(&Change { monthly_usd_limit: Some(Some(big)), ..Default::default() }, &actor, None).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains(r#""monthly_usd_limit": "123456789012345.67""#));
+        assert_eq!(BudgetStore::open(Settings::default(), Some(path.clone()), BTreeMap::new()).current().monthly_usd_limit, Some(big));
+        assert!(reopened.authorize(Some(TOKEN)).is_err());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_stores_sharing_a_file_pick_up_changes() {
+        let path = std::env::temp_dir().join(format!("ectusr2-budget-{}/budget.json", uuid::Uuid::new_v4()));
+        let admins = parse_admins(&format!("alice={}", TOKEN)).unwrap();
+        let a = BudgetStore::open(Settings::default(), Some(path.clone()), admins.clone());
+        let b = BudgetStore::open(Settings::default(), Some(path.clone()), admins);
+        a.apply(&Change::from_args(&json!({"monthly_usd_limit": 250})).unwrap(), "alice", None).unwrap();
+        assert_eq!(b.current().monthly_usd_limit, Some(250.into()));
+        // a change through `b` starts from `a`'s
+        b.apply(&Change::from_args(&json!({"policy": "hard"})).unwrap(), "alice", None).unwrap();
+        let s = a.current();
+        assert_eq!((s.monthly_usd_limit, s.policy.as_deref()), (Some(250.into()), Some("hard")));
+        // concurrent writers through different stores each keep their change
+        let (a, b) = (Arc::new(a), Arc::new(b));
+        let writers: Vec<_> = (0..8).map(|i| {
+            let store = if i % 2 == 0 { a.clone() } else { b.clone() };
+            std::thread::spawn(move || {
+                store.apply(&Change::from_args(&json!({"budgets": {format!("team{}", i): {"monthly_usd_limit": i}}})).unwrap(), "alice", None).unwrap();
+            })
+        }).collect();
+        writers.into_iter().for_each(|w| w.join().unwrap());
+        assert_eq!(a.current().budgets.budgets.len(), 8);
+        assert_eq!(b.current().budgets.budgets.len(), 8);
+        // a broken file keeps the settings in effect
+        std::fs::write(&path, "{").unwrap();
+        assert_eq!(a.current().monthly_usd_limit, Some(250.into()));
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_apply_rejects_invalid_changes() {
+        let mut budgets = Budgets::parse(r#"{"budgets": {"a": {}}, "default": "a"}"#, false).unwrap();
+        budgets.budgets.insert("b".into(), Budget::default());
+        let store = BudgetStore::open(Settings { budgets, ..Default::default() }, None, BTreeMap::new());
+        assert!(Change::from_args(&json!({"policy": "strict"})).is_err());
+        assert!(store.apply(&Change::from_args(&json!({"budgets": {"a": null}})).unwrap(), "x", None).is_err());
+        assert!(store.apply(&Change::from_args(&json!({"monthly_usd_limit": -1})).unwrap(), "x", None).is_err());
+        assert!(store.apply(&Change::from_args(&json!({"budgets": {"b": {"limit": 3}}})).unwrap(), "x", None).is_err());
+        assert!(store.apply(&Change::from_args(&json!({"budgets": {"b": null}})).unwrap(), "x", None).is_ok());
+        assert!(!store.current().budgets.budgets.contains_key("b"));
+        assert!(Change::from_args(&json!({})).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Token usage of upstream generate/QA/refactor calls: read from responses, estimated from requests
+//! before they are sent, and priced with the catalog's per-model token prices.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::budget::money::Decimal;
+use crate::budget::pricing::ModelPrices;
+
+/// Rough size of a token in characters of JSON request text.
+const CHARS_PER_TOKEN: usize = 4;
+/// Least output assumed when a request names no `max_tokens`.
+const MIN_OUTPUT_TOKENS: u64 = 256;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Prompt tokens billed at the input price, excluding cache reads
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cached_input_tokens: u64,
+    /// From the request size, because the response reported no usage
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl TokenUsage {
+    /// `usage` of an OpenAI-style (`prompt_tokens`, `completion_tokens`, cache reads counted in the prompt) or
+    /// Anthropic-style (`input_tokens`, `output_tokens`, `cache_read_input_tokens`) response, at the top level or under `result`.
+    pub fn from_response(v: &Value) -> Option<Self> {
+        let usage = v.get("usage").or_else(|| v.pointer("/result/usage"))?;
+        let n = |k: &str| usage.pointer(k).and_then(Value::as_u64);
+        let model = v.get("model").or_else(|| v.pointer("/result/model")).or_else(|| usage.get("model")).and_then(Value::as_str).map(str::to_string);
+        let (input, cached) = match n("/prompt_tokens") {
+            Some(prompt) => {
+                let cached = n("/prompt_tokens_details/cached_tokens").unwrap_or(0);
+                (prompt.saturating_sub(cached), cached)
+            }
+            None => (n("/input_tokens")?, n("/cache_read_input_tokens").unwrap_or(0)),
+        };
+        let output = n("/completion_tokens").or_else(|| n("/output_tokens")).unwrap_or(0);
+        Some(Self { model, input_tokens: input, output_tokens: output, cached_input_tokens: cached, estimated: false })
+    }
+
+    /// Before sending: the request's JSON size in tokens, and `max_tokens` (else as much output as input).
+    pub fn estimate(request: &Value) -> Self {
+        let input = request.to_string().len().div_ceil(CHARS_PER_TOKEN) as u64;
+        let output = request.get("max_tokens").or_else(|| request.get("max_output_tokens")).and_then(Value::as_u64)
+            .unwrap_or(input.max(MIN_OUTPUT_TOKENS));
+        let model = request.get("model").and_then(Value::as_str).map(str::to_string);
+        Self { model, input_tokens: input, output_tokens: output, cached_input_tokens: 0, estimated: true }
+    }
+
+    /// Cost at `prices` (per million tokens, reporting currency); unset prices are free.
+    pub fn cost(&self, prices: &ModelPrices) -> Decimal {
+        let input = prices.input_per_mtok.unwrap_or_default();
+        let total = Decimal::from(self.input_tokens) * input
+            + Decimal::from(self.cached_input_tokens) * prices.cached_input_per_mtok.unwrap_or(input)
+            + Decimal::from(self.output_tokens) * prices.output_per_mtok.unwrap_or_default();
+        total / Decimal::from(1_000_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_usage_from_responses() {
+        let openai = json!({"model": "gpt-4o", "usage": {"prompt_tokens": 1200, "completion_tokens": 300, "prompt_tokens_details": {"cached_tokens": 1000}}});
+        let u = TokenUsage::from_response(&openai).unwrap();
+        assert_eq!((u.model.as_deref(), u.input_tokens, u.cached_input_tokens, u.output_tokens), (Some("gpt-4o"), 200, 1000, 300));
+
+        let anthropic = json!({"result": {"code": "fn main() {}", "model": "claude-sonnet-4", "usage": {"input_tokens": 50, "output_tokens": 70, "cache_read_input_tokens": 10}}});
+        let u = TokenUsage::from_response(&anthropic).unwrap();
+        assert_eq!((u.input_tokens, u.cached_input_tokens, u.output_tokens), (50, 10, 70));
+        assert!(TokenUsage::from_response(&json!({"code": "x"})).is_none());
+
+        let prices = ModelPrices { input_per_mtok: Some(3.into()), output_per_mtok: Some(15.into()), cached_input_per_mtok: Some(Decimal::new(3, 1)), currency: None };
+        // (50 x 3 + 10 x 0.3 + 70 x 15) / 1e6
+        assert_eq!(u.cost(&prices), Decimal::new(1203, 6));
+    }
+
+    #[test]
+    fn test_usage_estimate_from_request() {
+        let req = json!({"prompt": "x".repeat(4000), "model": "gpt-4o"});
+        let u = TokenUsage::estimate(&req);
+        assert!(u.estimated && u.input_tokens > 1000 && u.input_tokens < 1010);
+        assert_eq!(u.output_tokens, u.input_tokens);
+        assert_eq!(TokenUsage::estimate(&json!({"prompt": "hi", "max_tokens": 64})).output_tokens, 64);
+        assert_eq!(TokenUsage::estimate(&json!({"prompt": "hi"})).output_tokens, MIN_OUTPUT_TOKENS);
+    }
+}
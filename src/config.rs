@@ -1,12 +1,60 @@
 use std::env;
 
+use crate::budget::money::{self, Decimal};
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub api_url: String,
     pub api_key: Option<String>,
     pub orchestrator_backend: String,
-    pub budget_limit: Option<f32>,
+    /// Monthly limit in the reporting currency
+    pub budget_limit: Option<Decimal>,
     pub budget_policy: Option<String>,
+    pub idle_minutes: Option<u64>,
+    pub local_worker_command: Option<String>,
+    pub orchestrator_policy: crate::orchestrator::policy::Policy,
+    /// Append-only spend ledger; None keeps spend in memory only
+    pub ledger_path: Option<std::path::PathBuf>,
+    /// Named team/project budgets; empty when none are configured
+    pub budgets: crate::budget::hierarchy::Budgets,
+    /// Settings changed through `budget_config`; None keeps changes in memory only
+    pub budget_config_path: Option<std::path::PathBuf>,
+    /// `budget_config` admin token to name; empty makes it read-only
+    pub budget_admins: std::collections::BTreeMap<String, String>,
+    /// Alert at these percentages of a limit; also when the forecast exceeds it if `budget_alert_forecast`
+    pub budget_alert_thresholds: Vec<u32>,
+    pub budget_alert_forecast: bool,
+    pub budget_alert_webhooks: Vec<crate::budget::alerts::Webhook>,
+    /// Flat cost booked per upstream API call, in the reporting currency
+    pub api_call_usd: Option<Decimal>,
+    /// Run the ModelPool controller alongside the MCP server (kubernetes only)
+    pub operator: bool,
+    #[cfg(feature = "kubernetes")]
+    pub operator_namespace: Option<String>,
+    #[cfg(feature = "kubernetes")]
+    pub kube_clusters: std::collections::BTreeMap<String, crate::orchestrator::kubernetes::ClusterRef>,
+    #[cfg(feature = "kubernetes")]
+    pub kube_default_cluster: Option<String>,
+    #[cfg(feature = "local")]
+    pub docker_host: Option<String>,
+    #[cfg(feature = "aws")]
+    pub aws_region: String,
+    #[cfg(feature = "aws")]
+    pub ecs_endpoint: Option<String>,
+    #[cfg(feature = "gcp")]
+    pub gcp_project: Option<String>,
+    #[cfg(feature = "gcp")]
+    pub gcp_region: String,
+    #[cfg(feature = "gcp")]
+    pub gcp_zone: String,
+    #[cfg(feature = "gcp")]
+    pub gcp_endpoint: Option<String>,
+    #[cfg(feature = "azure")]
+    pub azure_subscription: Option<String>,
+    #[cfg(feature = "azure")]
+    pub azure_resource_group: Option<String>,
+    #[cfg(feature = "azure")]
+    pub azure_endpoint: Option<String>,
 }
 
 impl Config {
@@ -15,11 +63,103 @@ impl Config {
         // Merge env with CLI (CLI wins if provided)
         let api_url = if !c.api_url.is_empty() { c.api_url } else { env::var("ECTUS_R_API_URL").unwrap_or_else(|_| "http://localhost:8000".into()) };
         let api_key = c.api_key.or_else(|| env::var("ECTUS_R_API_KEY").ok());
-        let orchestrator_backend = if !c.orchestrator_backend.is_empty() { c.orchestrator_backend } else { env::var("ORCH_BACKEND").unwrap_or_else(|_| "kubernetes".into()) };
-        let budget_limit = c.budget_limit.or_else(|| env::var("BUDGET_MONTHLY_USD_LIMIT").ok().and_then(|s| s.parse::<f32>().ok()));
+        let orchestrator_backend = if !c.orchestrator_backend.is_empty() { c.orchestrator_backend } else { env::var("ORCH_BACKEND").unwrap_or_else(|_| crate::orchestrator::default_backend().into()) };
+        // Refuse to start on a typo or a backend this binary was built without
+        crate::orchestrator::resolve(&orchestrator_backend)?;
+        // Every amount is reported in BUDGET_CURRENCY; limits and prices in other currencies go through BUDGET_FX_RATES
+        let fx = money::Fx::parse(&env::var("BUDGET_CURRENCY").unwrap_or_else(|_| "USD".into()), &env::var("BUDGET_FX_RATES").unwrap_or_default())?;
+        money::install_fx(fx.clone());
+        let budget_limit = match c.budget_limit.or_else(|| env::var("BUDGET_MONTHLY_USD_LIMIT").ok().and_then(|s| s.parse::<Decimal>().ok())) {
+            Some(limit) => Some(fx.to_base(limit, &env::var("BUDGET_LIMIT_CURRENCY").unwrap_or_else(|_| fx.base.clone()))?),
+            None => None,
+        };
         let budget_policy = c.budget_policy.or_else(|| env::var("BUDGET_POLICY").ok());
+        let idle_minutes = c.idle_minutes.or_else(|| env::var("IDLE_SCALE_TO_ZERO_MINUTES").ok().and_then(|s| s.parse::<u64>().ok()));
+        let local_worker_command = c.local_worker_command.or_else(|| env::var("LOCAL_WORKER_COMMAND").ok());
+        // Inline JSON wins over a file; a malformed policy stops startup rather than allowing everything
+        let policy_json = match (env::var("ORCH_POLICY"), env::var("ORCH_POLICY_FILE")) {
+            (Ok(json), _) => Some(json),
+            (_, Ok(path)) => Some(std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("ORCH_POLICY_FILE {}: {}", path, e))?),
+            _ => None,
+        };
+        let orchestrator_policy = match policy_json {
+            Some(json) => crate::orchestrator::policy::Policy::from_json(&json)?,
+            None => Default::default(),
+        };
+
+        // Set but empty disables persistence; unset uses the XDG state directory
+        let ledger_path = match env::var("BUDGET_LEDGER_PATH") {
+            Ok(p) if p.is_empty() => None,
+            Ok(p) => Some(p.into()),
+            Err(_) => env::var("XDG_STATE_HOME").map(std::path::PathBuf::from)
+                .or_else(|_| env::var("HOME").map(|h| std::path::Path::new(&h).join(".local/state")))
+                .ok().map(|d| d.join("ectusr2/ledger.jsonl")),
+        };
+        // Loaded here so a bad catalog stops startup; reloads keep the old catalog on error
+        crate::budget::pricing::install(match env::var("PRICING_CATALOG").ok().filter(|p| !p.is_empty()) {
+            Some(path) => crate::budget::pricing::Catalog::load(std::path::Path::new(&path))?,
+            None => crate::budget::pricing::Catalog::builtin()?,
+        });
+        // Inline JSON wins over a file, as for ORCH_POLICY; limits are converted with the FX rates above
+        let budgets = match (env::var("BUDGETS"), env::var("BUDGETS_FILE")) {
+            (Ok(json), _) if !json.trim().is_empty() => crate::budget::hierarchy::Budgets::parse(&json, false)?,
+            (_, Ok(path)) if !path.is_empty() => crate::budget::hierarchy::Budgets::load(std::path::Path::new(&path))?,
+            _ => Default::default(),
+        };
+        // Next to the ledger unless set; empty disables persistence
+        let budget_config_path = match env::var("BUDGET_CONFIG_PATH") {
+            Ok(p) if p.is_empty() => None,
+            Ok(p) => Some(p.into()),
+            Err(_) => ledger_path.as_ref().map(|l: &std::path::PathBuf| l.with_file_name("budget.json")),
+        };
+        let budget_admins = crate::budget::store::parse_admins(&env::var("BUDGET_ADMIN_TOKENS").unwrap_or_default())?;
+        let (budget_alert_thresholds, budget_alert_forecast) = crate::budget::alerts::parse_thresholds(&env::var("BUDGET_ALERT_THRESHOLDS").unwrap_or_else(|_| "50,80,100,forecast".into()))?;
+        let budget_alert_webhooks = crate::budget::alerts::parse_webhooks(&env::var("BUDGET_ALERT_WEBHOOKS").unwrap_or_default())?;
+        let api_call_usd = env::var("ECTUS_R_API_CALL_USD").ok().and_then(|s| s.parse::<Decimal>().ok());
+        let operator = c.operator || env::var("ECTUSR2_OPERATOR").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "yes"));
+        if operator && !cfg!(feature = "kubernetes") {
+            anyhow::bail!("operator mode needs a build with --features kubernetes");
+        }
+
+        #[cfg(feature = "kubernetes")]
+        let kube_clusters = crate::orchestrator::kubernetes::parse_clusters(&env::var("KUBE_CLUSTERS").unwrap_or_default())?;
+        #[cfg(feature = "kubernetes")]
+        let kube_default_cluster = env::var("KUBE_DEFAULT_CLUSTER").ok();
+        #[cfg(feature = "kubernetes")]
+        if let Some(d) = kube_default_cluster.as_deref().filter(|d| !kube_clusters.contains_key(*d)) {
+            anyhow::bail!("KUBE_DEFAULT_CLUSTER `{}` is not listed in KUBE_CLUSTERS", d);
+        }
 
-        Ok(Self { api_url, api_key, orchestrator_backend, budget_limit, budget_policy })
+        Ok(Self {
+            api_url, api_key, orchestrator_backend, budget_limit, budget_policy, idle_minutes, local_worker_command, orchestrator_policy, ledger_path, budgets, budget_config_path, budget_admins, budget_alert_thresholds, budget_alert_forecast, budget_alert_webhooks, api_call_usd, operator,
+            // the namespace the chart deploys into; unset watches every namespace
+            #[cfg(feature = "kubernetes")]
+            operator_namespace: env::var("ECTUSR2_NAMESPACE").ok().filter(|n| !n.is_empty()),
+            #[cfg(feature = "kubernetes")]
+            kube_clusters,
+            #[cfg(feature = "kubernetes")]
+            kube_default_cluster,
+            #[cfg(feature = "local")]
+            docker_host: env::var("DOCKER_HOST").ok().filter(|h| h.starts_with("unix://") || h.starts_with('/')),
+            #[cfg(feature = "aws")]
+            aws_region: env::var("AWS_REGION").or_else(|_| env::var("AWS_DEFAULT_REGION")).unwrap_or_else(|_| "us-east-1".into()),
+            #[cfg(feature = "aws")]
+            ecs_endpoint: env::var("ECS_ENDPOINT_URL").or_else(|_| env::var("AWS_ENDPOINT_URL")).ok(),
+            #[cfg(feature = "gcp")]
+            gcp_project: env::var("GCP_PROJECT").or_else(|_| env::var("GOOGLE_CLOUD_PROJECT")).ok(),
+            #[cfg(feature = "gcp")]
+            gcp_region: env::var("GCP_REGION").unwrap_or_else(|_| "us-central1".into()),
+            #[cfg(feature = "gcp")]
+            gcp_zone: env::var("GCP_ZONE").unwrap_or_else(|_| "us-central1-a".into()),
+            #[cfg(feature = "gcp")]
+            gcp_endpoint: env::var("GCP_ENDPOINT_URL").ok(),
+            #[cfg(feature = "azure")]
+            azure_subscription: env::var("AZURE_SUBSCRIPTION_ID").ok(),
+            #[cfg(feature = "azure")]
+            azure_resource_group: env::var("AZURE_RESOURCE_GROUP").ok(),
+            #[cfg(feature = "azure")]
+            azure_endpoint: env::var("AZURE_MANAGEMENT_ENDPOINT").ok(),
+        })
     }
 }
 
@@ -29,8 +169,11 @@ pub struct CliShim {
     pub api_url: String,
     pub api_key: Option<String>,
     pub orchestrator_backend: String,
-    pub budget_limit: Option<f32>,
+    pub budget_limit: Option<Decimal>,
     pub budget_policy: Option<String>,
+    pub idle_minutes: Option<u64>,
+    pub local_worker_command: Option<String>,
+    pub operator: bool,
 }
 
 impl From<crate::Cli> for CliShim {
@@ -38,9 +181,12 @@ impl From<crate::Cli> for CliShim {
         Self {
             api_url: c.api_url,
             api_key: c.api_key,
-            orchestrator_backend: c.orchestrator_backend,
+            orchestrator_backend: c.orchestrator_backend.unwrap_or_default(),
             budget_limit: c.budget_limit,
             budget_policy: c.budget_policy,
+            idle_minutes: c.idle_minutes,
+            local_worker_command: c.local_worker_command,
+            operator: c.operator,
         }
     }
 }
@@ -8,4 +8,8 @@ pub enum EctusError {
     Input(String),
     #[error("Backend error: {0}")]
     Backend(String),
+    #[error("Policy violation: {0}")]
+    Policy(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
 }
@@ -11,6 +11,7 @@ mod resources;
 mod prompts;
 mod errors;
 mod util;
+mod state;
 
 #[derive(Parser, Debug)]
 #[command(name = "ectusr2", version, about = "Ectus-R MCP server (Rust)")]
@@ -21,15 +22,27 @@ struct Cli {
     /// Optional API key
     #[arg(long, )]
     api_key: Option<String>,
-    /// Orchestrator backend
-    #[arg(long = "orchestrator", default_value = "kubernetes")]
-    orchestrator_backend: String,
-    /// Monthly budget limit (USD)
+    /// Orchestrator backend (default: ORCH_BACKEND, else kubernetes if compiled in, else local)
+    #[arg(long = "orchestrator", )]
+    orchestrator_backend: Option<String>,
+    /// Monthly budget limit (in BUDGET_LIMIT_CURRENCY, default the reporting currency)
     #[arg(long = "budget-limit", )]
-    budget_limit: Option<f32>,
+    budget_limit: Option<rust_decimal::Decimal>,
     /// Budget policy (hard|soft)
     #[arg(long = "budget-policy", )]
     budget_policy: Option<String>,
+    /// Scale pools to zero after this many minutes without tool traffic
+    #[arg(long = "idle-minutes", )]
+    idle_minutes: Option<u64>,
+    /// Worker command line for the local orchestrator backend
+    #[arg(long = "local-worker-command", )]
+    local_worker_command: Option<String>,
+    /// Also reconcile ModelPool resources into Deployments/HPAs (needs --features kubernetes)
+    #[arg(long)]
+    operator: bool,
+    /// Print the ModelPool CustomResourceDefinition as JSON and exit
+    #[arg(long = "print-crd")]
+    print_crd: bool,
 }
 
 #[tokio::main]
@@ -39,6 +52,16 @@ async fn main() -> anyhow::Result<()> {
     fmt().with_env_filter(filter).init();
 
     let cli = Cli::parse();
+    if cli.print_crd {
+        #[cfg(feature = "kubernetes")]
+        {
+            use kube::CustomResourceExt;
+            println!("{}", serde_json::to_string_pretty(&orchestrator::operator::ModelPool::crd())?);
+            return Ok(());
+        }
+        #[cfg(not(feature = "kubernetes"))]
+        anyhow::bail!("--print-crd needs a build with --features kubernetes");
+    }
     let cfg = config::Config::from_cli(cli)?;
 
     tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting ectusr2");
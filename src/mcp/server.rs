@@ -1,11 +1,23 @@
-use crate::{api::client::ApiClient, config::Config};
+use crate::{api::client::ApiClient, config::Config, state::AppState};
 use crate::mcp::types::*;
 use serde_json::{json, Value};
 use tokio::sync::mpsc;
 use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Set once a client has sent `initialize`; notifications before that are dropped.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
 
 pub async fn run(cfg: Config) -> anyhow::Result<()> {
     let client = ApiClient::new(cfg.api_url.clone(), cfg.api_key.clone());
+    let state = Arc::new(AppState::new(&cfg));
+    tokio::spawn(crate::orchestrator::idle::run(state.clone()));
+    tokio::spawn(crate::orchestrator::local::supervise(state.local.clone()));
+    tokio::spawn(crate::budget::alerts::run(state.clone()));
+    #[cfg(unix)]
+    tokio::spawn(reload_pricing_on_sighup());
+    let operator = if cfg.operator { Some(tokio::spawn(operator(cfg.clone(), state.clone()))) } else { None };
 
     // Channel for lines read from stdin (blocking thread)
     let (tx, mut rx) = mpsc::channel::<String>(100);
@@ -26,37 +38,76 @@ pub async fn run(cfg: Config) -> anyhow::Result<()> {
                     "jsonrpc":"2.0","id":null,
                     "error": {"code": -32700, "message": format!("parse error: {}", e)}
                 });
-                println!("{}", resp.to_string());
+                println!("{}", resp);
                 continue;
             }
         };
-        let response = handle_request(&client, &cfg, req).await;
-        println!("{}", response.to_string());
+        let response = handle_request(&client, &cfg, &state, req).await;
+        println!("{}", response);
         io::stdout().flush().ok();
     }
+    // In a cluster stdin is usually closed from the start; the controller keeps the process alive
+    if let Some(handle) = operator {
+        handle.await?;
+    }
     Ok(())
 }
 
-async fn handle_request(client: &ApiClient, cfg: &Config, req: JsonRpcRequest) -> Value {
+/// `kill -HUP` re-reads `PRICING_CATALOG`; a broken file is logged and the current rates stay.
+#[cfg(unix)]
+async fn reload_pricing_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut hup) = signal(SignalKind::hangup()) else { return };
+    while hup.recv().await.is_some() {
+        match crate::budget::pricing::reload() {
+            Ok(c) => tracing::info!(source = ?c.source, "pricing catalog reloaded"),
+            Err(e) => tracing::warn!(error = %e, "pricing catalog reload failed; keeping current rates"),
+        }
+    }
+}
+
+#[cfg(feature = "kubernetes")]
+async fn operator(cfg: Config, state: Arc<AppState>) {
+    use crate::orchestrator::{operator, OrchestratorContext};
+    let ctx = OrchestratorContext { namespace: None, name: None, model: None, cluster: None };
+    let client = match state.kube.client(&ctx).await {
+        Ok(c) => c,
+        Err(e) => return tracing::error!(error = %e, "operator mode: no kubernetes client"),
+    };
+    let ctx = operator::OperatorCtx { client, policy: state.policy.clone(), budget: state.budget.clone(), ledger: state.ledger.clone() };
+    operator::run(ctx, cfg.operator_namespace.clone()).await
+}
+
+// Config refuses operator mode in builds without kubernetes
+#[cfg(not(feature = "kubernetes"))]
+async fn operator(_cfg: Config, _state: Arc<AppState>) {}
+
+async fn handle_request(client: &ApiClient, cfg: &Config, state: &AppState, req: JsonRpcRequest) -> Value {
     match req.method.as_str() {
-        "initialize" => json!({
-            "jsonrpc":"2.0", "id": req.id,
-            "result": {
-                "protocolVersion":"2024-11-05",
-                "capabilities": {"tools": {}, "resources": {}, "prompts": {}},
-                "serverInfo": {"name":"ectusr2","version": env!("CARGO_PKG_VERSION")}
-            }
-        }),
+        "initialize" => {
+            let name = req.params.as_ref().and_then(|p| p.pointer("/clientInfo/name")).and_then(|n| n.as_str());
+            *state.client.write().unwrap() = name.map(str::to_string);
+            INITIALIZED.store(true, Ordering::Relaxed);
+            json!({
+                "jsonrpc":"2.0", "id": req.id,
+                "result": {
+                    "protocolVersion":"2024-11-05",
+                    "capabilities": {"tools": {}, "resources": {}, "prompts": {}},
+                    "serverInfo": {"name":"ectusr2","version": env!("CARGO_PKG_VERSION")},
+                    "instructions": instructions(cfg, state)
+                }
+            })
+        }
         "tools/list" => json!({
             "jsonrpc":"2.0", "id": req.id,
             "result": {"tools": crate::tools::list()}
         }),
         "tools/call" => {
-            let (name, args) = match req.params.and_then(|v| parse_call_params(v)) {
+            let (name, args) = match req.params.and_then(parse_call_params) {
                 Some(t) => t,
                 None => return error(req.id, -32602, "invalid params", None),
             };
-            match crate::tools::call(client, cfg, &name, args).await {
+            match crate::tools::call(client, cfg, state, &name, args).await {
                 Ok(v) => json!({"jsonrpc":"2.0","id":req.id,"result": {"content":[{"type":"text","text": v}]}}),
                 Err(e) => error(req.id, -32000, &e.to_string(), None),
             }
@@ -82,6 +133,24 @@ async fn handle_request(client: &ApiClient, cfg: &Config, req: JsonRpcRequest) -
     }
 }
 
+/// Write a JSON-RPC notification to the client; false when no client has initialized yet.
+pub fn notify(method: &str, params: Value) -> bool {
+    if !INITIALIZED.load(Ordering::Relaxed) { return false; }
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", json!({"jsonrpc": "2.0", "method": method, "params": params})).is_ok() && out.flush().is_ok()
+}
+
+fn instructions(cfg: &Config, state: &AppState) -> String {
+    let available = crate::orchestrator::available_backends().join(", ");
+    match crate::orchestrator::new_backend(state, &cfg.orchestrator_backend) {
+        Ok(orch) => {
+            let caps = orch.capabilities();
+            format!("Orchestrator backend: {} (supports: {}). Available backends: {}.", caps.backend, caps.summary(), available)
+        }
+        Err(e) => format!("Orchestrator unavailable: {}.", e),
+    }
+}
+
 fn parse_call_params(v: Value) -> Option<(String, Value)> {
     let name = v.get("name")?.as_str()?.to_string();
     let args = v.get("arguments").cloned().unwrap_or_else(|| Value::Object(Default::default()));
@@ -89,5 +158,9 @@ fn parse_call_params(v: Value) -> Option<(String, Value)> {
 }
 
 fn error(id: Value, code: i32, msg: &str, data: Option<Value>) -> Value {
-    serde_json::json!({"jsonrpc":"2.0","id": id, "error": {"code": code, "message": msg, "data": data}})
+    let resp = JsonRpcResponse {
+        jsonrpc: "2.0".into(), id, result: None,
+        error: Some(JsonRpcError { code, message: msg.to_string(), data }),
+    };
+    serde_json::to_value(resp).unwrap_or(Value::Null)
 }
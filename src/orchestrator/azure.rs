@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::orchestrator::{Capabilities, Orchestrator, OrchestratorContext};
+
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token?api-version=2018-02-01&resource=https://management.azure.com/";
+const ACA_API_VERSION: &str = "2024-03-01";
+const VMSS_API_VERSION: &str = "2024-03-01";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AzureKind {
+    /// Container Apps; `replicas` is the minimum replica count
+    ContainerApps,
+    /// Virtual Machine Scale Sets; `replicas` is the SKU capacity
+    Vmss,
+}
+
+/// Azure pools backed by Container Apps or VM Scale Sets; `namespace` is the resource group.
+#[derive(Clone)]
+pub struct AzureOrchestrator {
+    kind: AzureKind,
+    http: reqwest::Client,
+    subscription: Option<String>,
+    resource_group: Option<String>,
+    endpoint: String,
+    /// Fixed access token instead of the environment or IMDS
+    token: Option<String>,
+}
+
+impl AzureOrchestrator {
+    pub fn new(subscription: Option<&str>, resource_group: Option<&str>, endpoint: Option<&str>) -> Self {
+        let http = reqwest::Client::builder().user_agent("ectusr2/0.1").build().expect("reqwest client");
+        Self {
+            kind: AzureKind::ContainerApps,
+            http,
+            subscription: subscription.map(|s| s.to_string()),
+            resource_group: resource_group.map(|s| s.to_string()),
+            endpoint: endpoint.unwrap_or("https://management.azure.com").trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    pub fn with_kind(&self, kind: AzureKind) -> Self {
+        Self { kind, ..self.clone() }
+    }
+
+    /// For tests: no process environment is read or changed.
+    #[cfg(test)]
+    pub fn with_token(&self, token: &str) -> Self {
+        Self { token: Some(token.to_string()), ..self.clone() }
+    }
+
+    /// The fixed token, else `AZURE_ACCESS_TOKEN` if set, otherwise a managed-identity token from IMDS.
+    async fn token(&self) -> Result<String> {
+        if let Some(t) = &self.token { return Ok(t.clone()); }
+        if let Ok(t) = std::env::var("AZURE_ACCESS_TOKEN") { return Ok(t); }
+        let res = self.http.get(IMDS_TOKEN_URL).header("Metadata", "true").send().await
+            .context("no AZURE_ACCESS_TOKEN and managed identity endpoint unreachable")?;
+        let v: Value = res.error_for_status()?.json().await?;
+        v["access_token"].as_str().map(|s| s.to_string()).ok_or_else(|| anyhow::anyhow!("IMDS token response without access_token"))
+    }
+
+    /// Send a request; `Ok(None)` on 404 so callers can tell "absent" from failures.
+    async fn request(&self, method: Method, url: &str, body: Option<Value>) -> Result<Option<Value>> {
+        let mut req = self.http.request(method.clone(), url).bearer_auth(self.token().await?);
+        if let Some(b) = body { req = req.json(&b); }
+        let res = req.send().await.with_context(|| format!("{} {}", method, url))?;
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        if status == StatusCode::NOT_FOUND { return Ok(None); }
+        if !status.is_success() {
+            anyhow::bail!("{} {} failed ({}): {}", method, url, status, text);
+        }
+        // ARM answers long-running operations with 202 and an empty body
+        Ok(Some(serde_json::from_str(&text).unwrap_or(Value::Null)))
+    }
+
+    async fn must(&self, method: Method, url: &str, body: Option<Value>) -> Result<Value> {
+        self.request(method, url, body).await?.ok_or_else(|| anyhow::anyhow!("{} not found", url))
+    }
+
+    fn resource_url(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let sub = self.subscription.as_deref().ok_or_else(|| anyhow::anyhow!("AZURE_SUBSCRIPTION_ID is not set"))?;
+        let rg = ctx.namespace.as_deref().or(self.resource_group.as_deref())
+            .ok_or_else(|| anyhow::anyhow!("no resource group: pass `namespace` or set AZURE_RESOURCE_GROUP"))?;
+        let (provider, version) = match self.kind {
+            AzureKind::ContainerApps => ("Microsoft.App/containerApps", ACA_API_VERSION),
+            AzureKind::Vmss => ("Microsoft.Compute/virtualMachineScaleSets", VMSS_API_VERSION),
+        };
+        Ok(format!("{}/subscriptions/{}/resourceGroups/{}/providers/{}/{}?api-version={}", self.endpoint, sub, rg, provider, pool_name(ctx), version))
+    }
+}
+
+fn pool_name(ctx: &OrchestratorContext) -> &str {
+    ctx.name.as_deref().unwrap_or("ectusr2-workers")
+}
+
+fn provisioning(v: &Value) -> &str {
+    v["properties"]["provisioningState"].as_str().unwrap_or("Accepted")
+}
+
+/// Raise the replica floor to `replicas`, lifting the ceiling if it would sit below it.
+fn set_min_replicas(app: &mut Value, replicas: u32) {
+    let scale = &mut app["properties"]["template"]["scale"];
+    let max = scale["maxReplicas"].as_u64().unwrap_or(0).max(replicas as u64);
+    scale["minReplicas"] = json!(replicas);
+    scale["maxReplicas"] = json!(max);
+}
+
+/// Container App template from `{ image, env, resources: {cpu, memory}, min_replicas, max_replicas }`.
+fn apply_aca_spec(app: &mut Value, spec: &Value) {
+    let container = super::first_container(&mut app["properties"]["template"], json!({"name": "worker"}));
+    if let Some(image) = spec.get("image") { container["image"] = image.clone(); }
+    if let Some(env) = spec.get("env").and_then(|e| e.as_object()) {
+        container["env"] = json!(env.iter().map(|(k, v)| json!({"name": k, "value": v.as_str().map(|s| s.to_string()).unwrap_or_else(|| v.to_string())})).collect::<Vec<_>>());
+    }
+    if let Some(r) = spec.get("resources") {
+        // ACA wants fractional cores and "Gi" memory, e.g. 0.5 / "1Gi"
+        let cpu = crate::budget::parse_cpu(r["cpu"].as_str().unwrap_or("0.5"));
+        container["resources"] = json!({"cpu": cpu, "memory": r["memory"].as_str().unwrap_or("1Gi")});
+    }
+    let scale = &mut app["properties"]["template"]["scale"];
+    if let Some(v) = spec.get("min_replicas").or_else(|| spec.get("replicas")) { scale["minReplicas"] = v.clone(); }
+    if let Some(v) = spec.get("max_replicas") { scale["maxReplicas"] = v.clone(); }
+}
+
+#[async_trait]
+impl Orchestrator for AzureOrchestrator {
+    fn capabilities(&self) -> Capabilities {
+        let backend = match self.kind { AzureKind::ContainerApps => "aca", AzureKind::Vmss => "vmss" };
+        Capabilities { backend, delete_pool: true, ..Default::default() }
+    }
+    async fn status(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let url = self.resource_url(ctx)?;
+        let Some(r) = self.request(Method::GET, &url, None).await? else { return Ok(format!("{}: not found", pool_name(ctx))) };
+        Ok(match self.kind {
+            AzureKind::ContainerApps => format!(
+                "container app {}: min {} max {} replicas, {} ({}), latest revision {}",
+                pool_name(ctx),
+                r["properties"]["template"]["scale"]["minReplicas"].as_u64().unwrap_or(0),
+                r["properties"]["template"]["scale"]["maxReplicas"].as_u64().unwrap_or(0),
+                r["properties"]["runningStatus"].as_str().unwrap_or("Unknown"),
+                provisioning(&r),
+                r["properties"]["latestRevisionName"].as_str().unwrap_or("-"),
+            ),
+            AzureKind::Vmss => format!(
+                "scale set {}: capacity {} ({}), {}",
+                pool_name(ctx),
+                r["sku"]["capacity"].as_u64().unwrap_or(0),
+                r["sku"]["name"].as_str().unwrap_or("-"),
+                provisioning(&r),
+            ),
+        })
+    }
+    async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
+        let url = self.resource_url(ctx)?;
+        match self.kind {
+            AzureKind::ContainerApps => {
+                let app = self.must(Method::GET, &url, None).await?;
+                let mut patch = json!({"properties": {"template": app["properties"]["template"].clone()}});
+                set_min_replicas(&mut patch, replicas);
+                self.must(Method::PATCH, &url, Some(patch)).await?;
+                Ok(format!("container app {} min replicas set to {}", pool_name(ctx), replicas))
+            }
+            AzureKind::Vmss => {
+                self.must(Method::PATCH, &url, Some(json!({"sku": {"capacity": replicas}}))).await?;
+                Ok(format!("scale set {} capacity set to {}", pool_name(ctx), replicas))
+            }
+        }
+    }
+    async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String> {
+        let parsed: Value = serde_json::from_str(spec).unwrap_or(json!({}));
+        let url = self.resource_url(ctx)?;
+        let existing = self.request(Method::GET, &url, None).await?;
+        match (self.kind, existing) {
+            (AzureKind::ContainerApps, Some(app)) => {
+                let mut patch = json!({"properties": {"template": app["properties"]["template"].clone()}});
+                apply_aca_spec(&mut patch, &parsed);
+                self.must(Method::PATCH, &url, Some(patch)).await?;
+                Ok(format!("updated container app {}", pool_name(ctx)))
+            }
+            (AzureKind::ContainerApps, None) => {
+                let location = parsed.get("location").and_then(|v| v.as_str()).ok_or_else(|| anyhow::anyhow!("spec.location is required to create a container app"))?;
+                let env_id = parsed.get("environment_id").and_then(|v| v.as_str()).ok_or_else(|| anyhow::anyhow!("spec.environment_id is required to create a container app"))?;
+                let mut app = json!({"location": location, "properties": {"managedEnvironmentId": env_id, "template": {"containers": [{"name": "worker"}], "scale": {}}}});
+                apply_aca_spec(&mut app, &parsed);
+                let res = self.must(Method::PUT, &url, Some(app)).await?;
+                Ok(format!("created container app {} ({})", pool_name(ctx), provisioning(&res)))
+            }
+            (AzureKind::Vmss, Some(_)) => {
+                let mut sku = json!({"capacity": parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1)});
+                if let Some(name) = parsed.get("sku").and_then(|v| v.as_str()) { sku["name"] = json!(name); }
+                self.must(Method::PATCH, &url, Some(json!({"sku": sku}))).await?;
+                Ok(format!("updated scale set {} (capacity {})", pool_name(ctx), sku["capacity"]))
+            }
+            (AzureKind::Vmss, None) => {
+                let location = parsed.get("location").and_then(|v| v.as_str()).ok_or_else(|| anyhow::anyhow!("spec.location is required to create a scale set"))?;
+                let properties = parsed.get("properties").cloned().ok_or_else(|| anyhow::anyhow!("spec.properties (virtualMachineProfile etc.) is required to create a scale set"))?;
+                let body = json!({
+                    "location": location,
+                    "sku": {"name": parsed.get("sku").and_then(|v| v.as_str()).unwrap_or("Standard_D2s_v5"), "capacity": parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1)},
+                    "properties": properties,
+                });
+                let res = self.must(Method::PUT, &url, Some(body)).await?;
+                Ok(format!("created scale set {} ({})", pool_name(ctx), provisioning(&res)))
+            }
+        }
+    }
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let url = self.resource_url(ctx)?;
+        match self.request(Method::DELETE, &url, None).await? {
+            Some(_) => Ok(format!("deleting {}", pool_name(ctx))),
+            None => Ok(format!("{} not found; nothing to delete", pool_name(ctx))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::fake_http::FakeServer;
+
+    fn ctx() -> OrchestratorContext {
+        OrchestratorContext { namespace: Some("rg".into()), name: Some("w".into()), model: None, cluster: None }
+    }
+
+    #[tokio::test]
+    async fn test_container_app_and_vmss_scale() {
+        let server = FakeServer::start(|req| match req.method.as_str() {
+            "GET" if req.path.contains("containerApps") => (200, json!({"properties": {"template": {"containers": [{"name": "worker", "image": "img"}], "scale": {"minReplicas": 0, "maxReplicas": 1}}}}).to_string()),
+            "GET" => (200, json!({"sku": {"name": "Standard_D2s_v5", "capacity": 5}, "properties": {"provisioningState": "Succeeded"}}).to_string()),
+            _ => (202, String::new()),
+        }).await;
+        let aca = AzureOrchestrator::new(Some("sub"), None, Some(&server.url)).with_token("tok");
+        aca.scale(&ctx(), 2).await.unwrap();
+        let vmss = aca.with_kind(AzureKind::Vmss);
+        vmss.scale(&ctx(), 3).await.unwrap();
+        assert_eq!(vmss.status(&ctx()).await.unwrap(), "scale set w: capacity 5 (Standard_D2s_v5), Succeeded");
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].headers["authorization"], "Bearer tok");
+        assert_eq!(reqs[0].path, "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/containerApps/w?api-version=2024-03-01");
+        let patch: Value = serde_json::from_str(&reqs[1].body).unwrap();
+        assert_eq!(patch["properties"]["template"]["scale"], json!({"minReplicas": 2, "maxReplicas": 2}));
+        assert_eq!(patch["properties"]["template"]["containers"][0]["image"], "img");
+        assert_eq!((reqs[2].method.as_str(), reqs[2].body.as_str()), ("PATCH", r#"{"sku":{"capacity":3}}"#));
+    }
+
+    #[test]
+    fn test_apply_aca_spec() {
+        let mut app = json!({"properties": {"template": {"containers": [{"name": "worker"}], "scale": {}}}});
+        apply_aca_spec(&mut app, &json!({"image": "acr.io/app:1", "replicas": 1, "max_replicas": 4, "resources": {"cpu": "500m", "memory": "1Gi"}}));
+        let t = &app["properties"]["template"];
+        assert_eq!(t["containers"][0]["resources"], json!({"cpu": 0.5, "memory": "1Gi"}));
+        assert_eq!(t["scale"], json!({"minReplicas": 1, "maxReplicas": 4}));
+        // an app with no containers, or an empty list, gets one rather than panicking
+        for mut app in [json!({"properties": {"template": {}}}), json!({"properties": {"template": {"containers": []}}})] {
+            apply_aca_spec(&mut app, &json!({"image": "acr.io/app:2"}));
+            assert_eq!(app["properties"]["template"]["containers"], json!([{"name": "worker", "image": "acr.io/app:2"}]));
+        }
+    }
+}
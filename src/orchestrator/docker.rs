@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
+
+use crate::orchestrator::{Capabilities, Orchestrator, OrchestratorContext};
+
+/// Oldest Engine API version with everything used here; Podman's compat API serves it too.
+const API_VERSION: &str = "v1.41";
+const POOL_LABEL: &str = "ectusr2.pool";
+const INDEX_LABEL: &str = "ectusr2.index";
+const SPEC_LABEL: &str = "ectusr2.spec";
+
+/// How to run one worker container of a pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerSpec {
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ContainerResources>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerResources {
+    pub cpu: Option<String>,
+    pub memory: Option<String>,
+}
+
+/// Minimal HTTP/1.1 client for the Engine API on a Unix socket.
+#[derive(Clone)]
+struct Engine {
+    socket: PathBuf,
+}
+
+impl Engine {
+    async fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<(u16, Value)> {
+        let stream = UnixStream::connect(&self.socket).await
+            .with_context(|| format!("connect to container engine at {}", self.socket.display()))?;
+        let mut reader = BufReader::new(stream);
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let head = format!(
+            "{} /{}{} HTTP/1.1\r\nHost: docker\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            method, API_VERSION, path, body.len()
+        );
+        let conn = reader.get_mut();
+        conn.write_all(head.as_bytes()).await?;
+        conn.write_all(body.as_bytes()).await?;
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let status: u16 = line.split_whitespace().nth(1).and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("malformed engine response: {:?}", line))?;
+        let (mut length, mut chunked) = (None, false);
+        loop {
+            let mut h = String::new();
+            if reader.read_line(&mut h).await? == 0 { break; }
+            let h = h.trim_end();
+            if h.is_empty() { break; }
+            if let Some((k, v)) = h.split_once(':') {
+                match k.trim().to_ascii_lowercase().as_str() {
+                    "content-length" => length = v.trim().parse::<usize>().ok(),
+                    "transfer-encoding" => chunked = v.trim().eq_ignore_ascii_case("chunked"),
+                    _ => {}
+                }
+            }
+        }
+        let mut raw = Vec::new();
+        if chunked {
+            loop {
+                let mut size = String::new();
+                reader.read_line(&mut size).await?;
+                let n = usize::from_str_radix(size.trim(), 16).unwrap_or(0);
+                let mut chunk = vec![0u8; n + 2]; // data + CRLF
+                reader.read_exact(&mut chunk).await?;
+                if n == 0 { break; }
+                raw.extend_from_slice(&chunk[..n]);
+            }
+        } else if let Some(n) = length {
+            raw.resize(n, 0);
+            reader.read_exact(&mut raw).await?;
+        } else {
+            reader.read_to_end(&mut raw).await?;
+        }
+        let text = String::from_utf8_lossy(&raw);
+        Ok((status, serde_json::from_str(&text).unwrap_or_else(|_| json!({"message": text.trim()}))))
+    }
+
+    /// Like `call`, but any non-2xx status is an error carrying the engine's message.
+    async fn ok(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let (status, v) = self.call(method, path, body).await?;
+        if !(200..300).contains(&status) {
+            anyhow::bail!("{} {} failed ({}): {}", method, path, status, v["message"].as_str().unwrap_or(""));
+        }
+        Ok(v)
+    }
+}
+
+fn pool_key(ctx: &OrchestratorContext) -> String {
+    format!("{}/{}", ctx.namespace.as_deref().unwrap_or("default"), ctx.name.as_deref().unwrap_or("ectusr2-workers"))
+}
+
+fn container_name(key: &str, index: u32) -> String {
+    let safe: String = key.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+    format!("ectusr2-{}-{}", safe, index)
+}
+
+fn query(path: &str, params: &[(&str, &str)]) -> String {
+    let url = reqwest::Url::parse_with_params(&format!("http://engine{}", path), params).expect("static base url");
+    format!("{}?{}", url.path(), url.query().unwrap_or(""))
+}
+
+/// `POST /containers/create` body for worker `index` of pool `key`.
+fn create_body(key: &str, index: u32, spec: &ContainerSpec, model: Option<&str>) -> Value {
+    let mut env: Vec<String> = spec.env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    env.push(format!("ECTUSR2_POOL={}", key));
+    env.push(format!("ECTUSR2_WORKER_INDEX={}", index));
+    if let Some(m) = model { env.push(format!("ECTUSR2_MODEL={}", m)); }
+    let mut host = json!({"RestartPolicy": {"Name": "unless-stopped"}});
+    if let Some(r) = &spec.resources {
+        if let Some(cpu) = &r.cpu { host["NanoCpus"] = json!((crate::budget::parse_cpu(cpu) as f64 * 1e9) as i64); }
+        if let Some(mem) = &r.memory { host["Memory"] = json!((crate::budget::parse_mem_gib(mem) as f64 * 1024.0 * 1024.0 * 1024.0) as i64); }
+    }
+    let mut body = json!({
+        "Image": spec.image,
+        "Env": env,
+        "Labels": {
+            POOL_LABEL: key,
+            INDEX_LABEL: index.to_string(),
+            SPEC_LABEL: serde_json::to_string(spec).unwrap_or_default(),
+        },
+        "HostConfig": host,
+    });
+    if !spec.command.is_empty() { body["Cmd"] = json!(spec.command); }
+    body
+}
+
+/// Pool key -> (spec, model) as last passed to pool_ensure.
+type SpecCache = Arc<Mutex<HashMap<String, (ContainerSpec, Option<String>)>>>;
+
+/// Worker containers on a Docker or Podman engine, one labelled group per pool.
+#[derive(Clone)]
+pub struct DockerOrchestrator {
+    engine: Engine,
+    // specs seen via pool_ensure, so a pool scaled to zero can come back
+    specs: SpecCache,
+}
+
+impl DockerOrchestrator {
+    /// `host` is a `DOCKER_HOST`-style `unix://` URL or a bare socket path.
+    pub fn new(host: Option<&str>) -> Self {
+        let socket = host.map(|h| h.trim_start_matches("unix://")).unwrap_or("/var/run/docker.sock");
+        Self { engine: Engine { socket: PathBuf::from(socket) }, specs: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Containers of a pool, sorted by worker index.
+    async fn containers(&self, key: &str) -> Result<Vec<Value>> {
+        let filters = json!({"label": [format!("{}={}", POOL_LABEL, key)]}).to_string();
+        let list = self.engine.ok("GET", &query("/containers/json", &[("all", "true"), ("filters", &filters)]), None).await?;
+        let mut list = list.as_array().cloned().unwrap_or_default();
+        list.sort_by_key(index_of);
+        Ok(list)
+    }
+
+    async fn spec_for(&self, key: &str, existing: &[Value]) -> Result<(ContainerSpec, Option<String>)> {
+        if let Some(s) = self.specs.lock().await.get(key) { return Ok(s.clone()); }
+        existing.iter()
+            .find_map(|c| c["Labels"][SPEC_LABEL].as_str().and_then(|s| serde_json::from_str(s).ok()))
+            .map(|s| (s, None))
+            .ok_or_else(|| anyhow::anyhow!("pool {} not found; create it with pool_ensure", key))
+    }
+
+    async fn start_worker(&self, key: &str, index: u32, spec: &ContainerSpec, model: Option<&str>) -> Result<()> {
+        let body = create_body(key, index, spec, model);
+        let path = query("/containers/create", &[("name", &container_name(key, index))]);
+        let (status, res) = self.engine.call("POST", &path, Some(&body)).await?;
+        let res = match status {
+            200..=299 => res,
+            404 => {
+                // image not present locally: pull it once, then retry
+                let (image, tag) = spec.image.rsplit_once(':').filter(|(_, t)| !t.contains('/')).unwrap_or((&spec.image, "latest"));
+                self.engine.ok("POST", &query("/images/create", &[("fromImage", image), ("tag", tag)]), None).await?;
+                self.engine.ok("POST", &path, Some(&body)).await?
+            }
+            _ => anyhow::bail!("create {} failed ({}): {}", container_name(key, index), status, res["message"].as_str().unwrap_or("")),
+        };
+        let id = res["Id"].as_str().ok_or_else(|| anyhow::anyhow!("engine returned no container id"))?;
+        self.engine.ok("POST", &format!("/containers/{}/start", id), None).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, container: &Value) -> Result<()> {
+        let id = container["Id"].as_str().unwrap_or_default();
+        self.engine.ok("DELETE", &format!("/containers/{}?force=true", id), None).await?;
+        Ok(())
+    }
+
+    /// Create or remove containers until exactly `replicas` exist; the lowest indices are kept.
+    async fn resize(&self, key: &str, replicas: u32, existing: Vec<Value>, spec: &ContainerSpec, model: Option<&str>) -> Result<(usize, usize)> {
+        let (keep, extra): (Vec<_>, Vec<_>) = existing.into_iter().enumerate().partition(|(i, _)| (*i as u32) < replicas);
+        for (_, c) in &extra { self.remove(c).await?; }
+        let used: Vec<u32> = keep.iter().map(|(_, c)| index_of(c)).collect();
+        let mut started = 0;
+        let mut index = 0;
+        while keep.len() + started < replicas as usize {
+            if !used.contains(&index) {
+                self.start_worker(key, index, spec, model).await?;
+                started += 1;
+            }
+            index += 1;
+        }
+        Ok((started, extra.len()))
+    }
+}
+
+fn index_of(container: &Value) -> u32 {
+    container["Labels"][INDEX_LABEL].as_str().and_then(|s| s.parse().ok()).unwrap_or(u32::MAX)
+}
+
+#[async_trait]
+impl Orchestrator for DockerOrchestrator {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { backend: "docker", delete_pool: true, ..Default::default() }
+    }
+    async fn status(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let key = pool_key(ctx);
+        let containers = self.containers(&key).await?;
+        if containers.is_empty() { return Ok(format!("docker pool {}: no containers", key)); }
+        let mut states: BTreeMap<String, usize> = BTreeMap::new();
+        for c in &containers { *states.entry(c["State"].as_str().unwrap_or("unknown").to_string()).or_default() += 1; }
+        let states: Vec<String> = states.iter().map(|(s, n)| format!("{} {}", n, s)).collect();
+        Ok(format!("docker pool {}: {} containers ({}), image {}", key, containers.len(), states.join(", "), containers[0]["Image"].as_str().unwrap_or("-")))
+    }
+    async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
+        let key = pool_key(ctx);
+        let existing = self.containers(&key).await?;
+        let (spec, model) = self.spec_for(&key, &existing).await?;
+        let model = ctx.model.clone().or(model);
+        let (started, removed) = self.resize(&key, replicas, existing, &spec, model.as_deref()).await?;
+        Ok(format!("docker pool {} scaled to {} (started {}, removed {})", key, replicas, started, removed))
+    }
+    async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String> {
+        let key = pool_key(ctx);
+        let v: Value = serde_json::from_str(spec).unwrap_or(json!({}));
+        let replicas = v.get("replicas").and_then(|r| r.as_u64()).unwrap_or(1) as u32;
+        let spec: ContainerSpec = serde_json::from_value(v).context("spec needs at least an `image`")?;
+        let mut existing = self.containers(&key).await?;
+        // containers from an older spec are replaced rather than left running the old image
+        let stale = existing.iter().any(|c| c["Labels"][SPEC_LABEL].as_str().and_then(|s| serde_json::from_str::<ContainerSpec>(s).ok()).as_ref() != Some(&spec));
+        if stale {
+            for c in &existing { self.remove(c).await?; }
+            existing.clear();
+        }
+        self.specs.lock().await.insert(key.clone(), (spec.clone(), ctx.model.clone()));
+        self.resize(&key, replicas, existing, &spec, ctx.model.as_deref()).await?;
+        Ok(format!("docker pool {} ensured with {} x {}{}", key, replicas, spec.image, if stale { " (replaced outdated containers)" } else { "" }))
+    }
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let key = pool_key(ctx);
+        let existing = self.containers(&key).await?;
+        for c in &existing { self.remove(c).await?; }
+        self.specs.lock().await.remove(&key);
+        Ok(format!("docker pool {} deleted ({} containers removed)", key, existing.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::fake_http::FakeServer;
+    use std::sync::Mutex as StdMutex;
+
+    /// Engine fake that keeps a container table so list/create/delete round-trip.
+    #[tokio::test]
+    async fn test_scale_against_fake_engine() {
+        let dir = std::env::temp_dir().join(format!("ectusr2-docker-{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let sock = dir.join("engine.sock");
+        let table: Arc<StdMutex<Vec<Value>>> = Arc::new(StdMutex::new(Vec::new()));
+        let t = table.clone();
+        let server = FakeServer::start_unix(&sock, move |req| {
+            let mut t = t.lock().unwrap();
+            let path = req.path.trim_start_matches("/v1.41");
+            match req.method.as_str() {
+                "GET" => (200, Value::Array(t.clone()).to_string()),
+                "POST" if path.starts_with("/containers/create") => {
+                    let body: Value = serde_json::from_str(&req.body).unwrap();
+                    let id = format!("c{}", t.len());
+                    t.push(json!({"Id": id, "Image": body["Image"], "State": "running", "Labels": body["Labels"]}));
+                    (201, json!({"Id": id}).to_string())
+                }
+                "DELETE" => {
+                    let id = path.trim_start_matches("/containers/").split('?').next().unwrap().to_string();
+                    t.retain(|c| c["Id"] != id.as_str());
+                    (204, String::new())
+                }
+                _ => (204, String::new()),
+            }
+        }).await;
+        let docker = DockerOrchestrator::new(Some(&format!("unix://{}", sock.display())));
+        let ctx = OrchestratorContext { namespace: None, name: Some("w".into()), model: Some("m".into()), cluster: None };
+
+        assert!(docker.scale(&ctx, 1).await.unwrap_err().to_string().contains("pool_ensure"));
+        docker.ensure_pool(&ctx, r#"{"image": "worker:1", "replicas": 2}"#).await.unwrap();
+        assert_eq!(docker.status(&ctx).await.unwrap(), "docker pool default/w: 2 containers (2 running), image worker:1");
+        docker.scale(&ctx, 1).await.unwrap();
+        assert_eq!(table.lock().unwrap().len(), 1);
+        docker.delete_pool(&ctx).await.unwrap();
+        assert!(table.lock().unwrap().is_empty());
+
+        let reqs = server.requests();
+        let create = reqs.iter().find(|r| r.path.starts_with("/v1.41/containers/create")).unwrap();
+        assert!(create.path.ends_with("name=ectusr2-default-w-0"));
+        assert!(create.body.contains("ECTUSR2_MODEL=m"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_create_body_resources() {
+        let spec = ContainerSpec { image: "w".into(), command: vec![], env: BTreeMap::new(), resources: Some(ContainerResources { cpu: Some("500m".into()), memory: Some("1Gi".into()) }) };
+        let body = create_body("default/w", 3, &spec, None);
+        assert_eq!(body["HostConfig"]["NanoCpus"], 500_000_000);
+        assert_eq!(body["HostConfig"]["Memory"], 1_073_741_824i64);
+        assert_eq!(body["Labels"][INDEX_LABEL], "3");
+        assert!(body.get("Cmd").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::budget::{parse_cpu, parse_mem_gib};
+use crate::orchestrator::{Capabilities, Orchestrator, OrchestratorContext};
+
+const TARGET_PREFIX: &str = "AmazonEC2ContainerServiceV20141113";
+
+/// Amazon ECS services as pools: `namespace` is the cluster, `name` the service.
+#[derive(Clone)]
+pub struct EcsOrchestrator {
+    http: reqwest::Client,
+    endpoint: String,
+    region: String,
+    /// Fixed credentials instead of the `AWS_*` environment
+    credentials: Option<Credentials>,
+}
+
+#[derive(Clone)]
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl Credentials {
+    fn from_env() -> Result<Self> {
+        Ok(Self {
+            access_key: std::env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID is not set")?,
+            secret_key: std::env::var("AWS_SECRET_ACCESS_KEY").context("AWS_SECRET_ACCESS_KEY is not set")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+impl EcsOrchestrator {
+    /// `endpoint` overrides the regional ECS endpoint (e.g. LocalStack at `http://localhost:4566`).
+    pub fn new(region: &str, endpoint: Option<&str>) -> Self {
+        let endpoint = endpoint.map(|e| e.trim_end_matches('/').to_string()).unwrap_or_else(|| format!("https://ecs.{}.amazonaws.com", region));
+        let http = reqwest::Client::builder().user_agent("ectusr2/0.1").build().expect("reqwest client");
+        Self { http, endpoint, region: region.to_string(), credentials: None }
+    }
+
+    /// For tests: no process environment is read or changed.
+    #[cfg(test)]
+    pub fn with_credentials(&self, access_key: &str, secret_key: &str, session_token: Option<&str>) -> Self {
+        let credentials = Credentials { access_key: access_key.into(), secret_key: secret_key.into(), session_token: session_token.map(str::to_string) };
+        Self { credentials: Some(credentials), ..self.clone() }
+    }
+
+    async fn call(&self, action: &str, body: Value) -> Result<Value> {
+        let creds = match &self.credentials { Some(c) => c.clone(), None => Credentials::from_env()? };
+        let url = url::Url::parse(&self.endpoint)?;
+        let host = match url.port() {
+            Some(p) => format!("{}:{}", url.host_str().unwrap_or_default(), p),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let payload = body.to_string();
+        let amz_date = amz_date(time::OffsetDateTime::now_utc());
+        let target = format!("{}.{}", TARGET_PREFIX, action);
+        let mut headers = vec![
+            ("content-type".to_string(), "application/x-amz-json-1.1".to_string()),
+            ("host".to_string(), host),
+            ("x-amz-date".to_string(), amz_date.clone()),
+            ("x-amz-target".to_string(), target),
+        ];
+        if let Some(t) = &creds.session_token { headers.push(("x-amz-security-token".into(), t.clone())); }
+        let auth = sigv4_authorization(&creds, &self.region, "ecs", "POST", "/", &headers, &payload, &amz_date);
+
+        let mut req = self.http.post(format!("{}/", self.endpoint)).header("authorization", auth).body(payload);
+        for (k, v) in headers.iter().filter(|(k, _)| k != "host") { req = req.header(k.as_str(), v.as_str()); }
+        let res = req.send().await.with_context(|| format!("ECS {}", action))?;
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!("ECS {} failed ({}): {}", action, status, text);
+        }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::Null))
+    }
+
+    async fn describe_service(&self, cluster: &str, service: &str) -> Result<Option<Value>> {
+        let out = self.call("DescribeServices", json!({"cluster": cluster, "services": [service]})).await?;
+        Ok(out["services"].as_array()
+            .and_then(|s| s.iter().find(|s| s["status"].as_str() != Some("INACTIVE")))
+            .cloned())
+    }
+}
+
+fn names(ctx: &OrchestratorContext) -> (&str, &str) {
+    (ctx.namespace.as_deref().unwrap_or("default"), ctx.name.as_deref().unwrap_or("ectusr2-workers"))
+}
+
+#[async_trait]
+impl Orchestrator for EcsOrchestrator {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { backend: "ecs", delete_pool: true, ..Default::default() }
+    }
+    async fn status(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let (cluster, service) = names(ctx);
+        match self.describe_service(cluster, service).await? {
+            Some(s) => Ok(format!(
+                "service {} in {}: {} desired {}, running {}, pending {}, task definition {}",
+                service, cluster,
+                s["status"].as_str().unwrap_or("UNKNOWN"),
+                s["desiredCount"].as_i64().unwrap_or(0), s["runningCount"].as_i64().unwrap_or(0), s["pendingCount"].as_i64().unwrap_or(0),
+                s["taskDefinition"].as_str().unwrap_or("-"),
+            )),
+            None => Ok(format!("service {} in {}: not found", service, cluster)),
+        }
+    }
+    async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
+        let (cluster, service) = names(ctx);
+        let _ = self.call("UpdateService", json!({"cluster": cluster, "service": service, "desiredCount": replicas})).await?;
+        Ok(format!("scaled service {} to {} in {}", service, replicas, cluster))
+    }
+    async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String> {
+        let (cluster, service) = names(ctx);
+        let parsed: Value = serde_json::from_str(spec).unwrap_or(json!({}));
+        let replicas = parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1);
+        let registered = self.call("RegisterTaskDefinition", task_definition(service, &parsed)).await?;
+        let task_def = registered["taskDefinition"]["taskDefinitionArn"].as_str().map(|s| s.to_string()).unwrap_or_else(|| service.to_string());
+        if self.describe_service(cluster, service).await?.is_some() {
+            let _ = self.call("UpdateService", json!({"cluster": cluster, "service": service, "taskDefinition": task_def, "desiredCount": replicas})).await?;
+            return Ok(format!("updated service {} in {} (task definition {}, desired {})", service, cluster, task_def, replicas));
+        }
+        let mut create = json!({
+            "cluster": cluster, "serviceName": service, "taskDefinition": task_def, "desiredCount": replicas,
+            "launchType": parsed.get("launch_type").and_then(|v| v.as_str()).unwrap_or("FARGATE"),
+        });
+        if let Some(subnets) = parsed.get("subnets") {
+            create["networkConfiguration"] = json!({"awsvpcConfiguration": {
+                "subnets": subnets,
+                "securityGroups": parsed.get("security_groups").cloned().unwrap_or(json!([])),
+                "assignPublicIp": if parsed.get("assign_public_ip").and_then(|v| v.as_bool()).unwrap_or(false) { "ENABLED" } else { "DISABLED" },
+            }});
+        }
+        let _ = self.call("CreateService", create).await?;
+        Ok(format!("created service {} in {} (task definition {}, desired {})", service, cluster, task_def, replicas))
+    }
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let (cluster, service) = names(ctx);
+        let _ = self.call("DeleteService", json!({"cluster": cluster, "service": service, "force": true})).await?;
+        Ok(format!("deleted service {} in {}", service, cluster))
+    }
+}
+
+/// Fargate task definition for a minimal spec `{ image, resources: {cpu, memory}, env, port, execution_role_arn }`.
+fn task_definition(family: &str, spec: &Value) -> Value {
+    let image = spec.get("image").and_then(|v| v.as_str()).unwrap_or("busybox:stable");
+    let cpu = spec["resources"]["cpu"].as_str().map(parse_cpu).unwrap_or(1.0);
+    let mem_gib = spec["resources"]["memory"].as_str().map(parse_mem_gib).unwrap_or(2.0);
+    let env: Vec<Value> = spec.get("env").and_then(|e| e.as_object())
+        .map(|m| m.iter().map(|(k, v)| json!({"name": k, "value": v.as_str().map(|s| s.to_string()).unwrap_or_else(|| v.to_string())})).collect())
+        .unwrap_or_default();
+    let mut container = json!({"name": family, "image": image, "essential": true, "environment": env});
+    if let Some(port) = spec.get("port").and_then(|v| v.as_u64()) {
+        container["portMappings"] = json!([{"containerPort": port}]);
+    }
+    let mut def = json!({
+        "family": family,
+        "requiresCompatibilities": ["FARGATE"],
+        "networkMode": "awsvpc",
+        "cpu": ((cpu * 1024.0).round() as u32).to_string(),
+        "memory": ((mem_gib * 1024.0).round() as u32).to_string(),
+        "containerDefinitions": [container],
+    });
+    if let Some(role) = spec.get("execution_role_arn") { def["executionRoleArn"] = role.clone(); }
+    def
+}
+
+fn amz_date(t: time::OffsetDateTime) -> String {
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", t.year(), u8::from(t.month()), t.day(), t.hour(), t.minute(), t.second())
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// AWS Signature Version 4 `Authorization` header for a request without query string.
+#[allow(clippy::too_many_arguments)]
+fn sigv4_authorization(creds: &Credentials, region: &str, service: &str, method: &str, path: &str, headers: &[(String, String)], payload: &str, amz_date: &str) -> String {
+    let mut sorted: Vec<(String, String)> = headers.iter().map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string())).collect();
+    sorted.sort();
+    let canonical_headers: String = sorted.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    let signed_headers = sorted.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_request = format!("{}\n{}\n\n{}\n{}\n{}", method, path, canonical_headers, signed_headers, hex::encode(Sha256::digest(payload.as_bytes())));
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes())));
+    let k_date = hmac(format!("AWS4{}", creds.secret_key).as_bytes(), date);
+    let k_signing = hmac(&hmac(&hmac(&k_date, region), service), "aws4_request");
+    let signature = hex::encode(hmac(&k_signing, &string_to_sign));
+    format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", creds.access_key, scope, signed_headers, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::fake_http::FakeServer;
+
+    #[test]
+    fn test_sigv4_get_vanilla() {
+        // aws-sig-v4-test-suite "get-vanilla"
+        let creds = Credentials { access_key: "AKIDEXAMPLE".into(), secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(), session_token: None };
+        let headers = vec![("Host".to_string(), "example.amazonaws.com".to_string()), ("X-Amz-Date".to_string(), "20150830T123600Z".to_string())];
+        let auth = sigv4_authorization(&creds, "us-east-1", "service", "GET", "/", &headers, "", "20150830T123600Z");
+        assert_eq!(auth, "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31");
+    }
+
+    #[tokio::test]
+    async fn test_scale_and_status_against_fake_endpoint() {
+        let server = FakeServer::start(|req| match req.headers.get("x-amz-target").map(|s| s.as_str()) {
+            Some("AmazonEC2ContainerServiceV20141113.DescribeServices") => (200, json!({"services": [
+                {"serviceName": "w", "status": "ACTIVE", "desiredCount": 3, "runningCount": 2, "pendingCount": 1, "taskDefinition": "arn:td/w:4"}
+            ]}).to_string()),
+            _ => (200, json!({"service": {}}).to_string()),
+        }).await;
+        let ecs = EcsOrchestrator::new("us-east-1", Some(&server.url)).with_credentials("test", "test", None);
+        let ctx = OrchestratorContext { namespace: Some("c1".into()), name: Some("w".into()), model: None, cluster: None };
+        ecs.scale(&ctx, 3).await.unwrap();
+        let status = ecs.status(&ctx).await.unwrap();
+        assert!(status.contains("desired 3, running 2, pending 1"), "{}", status);
+
+        let reqs = server.requests();
+        assert_eq!((reqs[0].method.as_str(), reqs[0].path.as_str()), ("POST", "/"));
+        assert_eq!(reqs[0].headers["x-amz-target"], "AmazonEC2ContainerServiceV20141113.UpdateService");
+        assert!(reqs[0].headers["authorization"].starts_with("AWS4-HMAC-SHA256 Credential=test/"));
+        let body: Value = serde_json::from_str(&reqs[0].body).unwrap();
+        assert_eq!(body, json!({"cluster": "c1", "service": "w", "desiredCount": 3}));
+    }
+
+    #[test]
+    fn test_task_definition_sizes() {
+        let def = task_definition("w", &json!({"image": "app:1", "resources": {"cpu": "500m", "memory": "1Gi"}, "port": 8080}));
+        assert_eq!(def["cpu"], "512");
+        assert_eq!(def["memory"], "1024");
+        assert_eq!(def["containerDefinitions"][0]["portMappings"][0]["containerPort"], 8080);
+    }
+}
@@ -0,0 +1,86 @@
+//! Minimal HTTP/1.1 server standing in for cloud APIs in backend tests.
+// Which helpers get used depends on the backend features enabled.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>, // lower-cased names
+    pub body: String,
+}
+
+type Handler = Arc<dyn Fn(&Recorded) -> (u16, String) + Send + Sync>;
+
+pub struct FakeServer {
+    pub url: String,
+    pub requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl FakeServer {
+    /// Serve on an ephemeral localhost port, answering each request with `handler`.
+    pub async fn start(handler: impl Fn(&Recorded) -> (u16, String) + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Handler = Arc::new(handler);
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_conn(stream, handler.clone(), recorded.clone()));
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Same as `start`, but listening on a Unix socket at `path` (for the container engine).
+    #[cfg(feature = "local")]
+    pub async fn start_unix(path: &std::path::Path, handler: impl Fn(&Recorded) -> (u16, String) + Send + Sync + 'static) -> Self {
+        let _ = std::fs::remove_file(path);
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Handler = Arc::new(handler);
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_conn(stream, handler.clone(), recorded.clone()));
+            }
+        });
+        Self { url: format!("unix://{}", path.display()), requests }
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+pub async fn serve_conn<S: AsyncRead + AsyncWrite + Unpin>(stream: S, handler: Handler, recorded: Arc<Mutex<Vec<Recorded>>>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 { return; }
+        let mut parts = line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("").to_string());
+        let mut headers = HashMap::new();
+        loop {
+            let mut h = String::new();
+            if reader.read_line(&mut h).await.unwrap_or(0) == 0 { return; }
+            let h = h.trim_end();
+            if h.is_empty() { break; }
+            if let Some((k, v)) = h.split_once(':') { headers.insert(k.trim().to_ascii_lowercase(), v.trim().to_string()); }
+        }
+        let len = headers.get("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+        let mut body = vec![0u8; len];
+        if reader.read_exact(&mut body).await.is_err() { return; }
+        let req = Recorded { method, path, headers, body: String::from_utf8_lossy(&body).into_owned() };
+        let (status, resp) = handler(&req);
+        recorded.lock().unwrap().push(req);
+        let out = format!("HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", status, resp.len(), resp);
+        if reader.get_mut().write_all(out.as_bytes()).await.is_err() { return; }
+    }
+}
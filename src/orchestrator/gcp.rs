@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::orchestrator::{Capabilities, Orchestrator, OrchestratorContext};
+
+const METADATA_TOKEN_URL: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcpKind {
+    /// Cloud Run services; `namespace` is the region, `replicas` the minimum instance count
+    CloudRun,
+    /// Managed Instance Groups; `namespace` is the zone, `replicas` the target size
+    Mig,
+}
+
+/// Google Cloud pools backed by Cloud Run services or zonal Managed Instance Groups.
+#[derive(Clone)]
+pub struct GcpOrchestrator {
+    kind: GcpKind,
+    http: reqwest::Client,
+    project: Option<String>,
+    region: String,
+    zone: String,
+    run_endpoint: String,
+    compute_endpoint: String,
+    /// Fixed access token instead of the environment or metadata server
+    token: Option<String>,
+}
+
+impl GcpOrchestrator {
+    /// `endpoint` replaces both `run.googleapis.com` and `compute.googleapis.com` (for a local fake).
+    pub fn new(project: Option<&str>, region: &str, zone: &str, endpoint: Option<&str>) -> Self {
+        let endpoint = endpoint.map(|e| e.trim_end_matches('/').to_string());
+        let http = reqwest::Client::builder().user_agent("ectusr2/0.1").build().expect("reqwest client");
+        Self {
+            kind: GcpKind::CloudRun,
+            http,
+            project: project.map(|p| p.to_string()),
+            region: region.to_string(),
+            zone: zone.to_string(),
+            run_endpoint: endpoint.clone().unwrap_or_else(|| "https://run.googleapis.com".into()),
+            compute_endpoint: endpoint.unwrap_or_else(|| "https://compute.googleapis.com".into()),
+            token: None,
+        }
+    }
+
+    pub fn with_kind(&self, kind: GcpKind) -> Self {
+        Self { kind, ..self.clone() }
+    }
+
+    /// For tests: no process environment is read or changed.
+    #[cfg(test)]
+    pub fn with_token(&self, token: &str) -> Self {
+        Self { token: Some(token.to_string()), ..self.clone() }
+    }
+
+    /// The fixed token, else `GOOGLE_OAUTH_ACCESS_TOKEN` if set, otherwise the metadata server's service-account token.
+    async fn token(&self) -> Result<String> {
+        if let Some(t) = &self.token { return Ok(t.clone()); }
+        if let Ok(t) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") { return Ok(t); }
+        let res = self.http.get(METADATA_TOKEN_URL).header("Metadata-Flavor", "Google").send().await
+            .context("no GOOGLE_OAUTH_ACCESS_TOKEN and metadata server unreachable")?;
+        let v: Value = res.error_for_status()?.json().await?;
+        v["access_token"].as_str().map(|s| s.to_string()).ok_or_else(|| anyhow::anyhow!("metadata token response without access_token"))
+    }
+
+    /// Send a request; `Ok(None)` on 404 so callers can tell "absent" from failures.
+    async fn request(&self, method: Method, url: &str, body: Option<Value>) -> Result<Option<Value>> {
+        let mut req = self.http.request(method.clone(), url).bearer_auth(self.token().await?);
+        if let Some(b) = body { req = req.json(&b); }
+        let res = req.send().await.with_context(|| format!("{} {}", method, url))?;
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        if status == StatusCode::NOT_FOUND { return Ok(None); }
+        if !status.is_success() {
+            anyhow::bail!("{} {} failed ({}): {}", method, url, status, text);
+        }
+        Ok(Some(serde_json::from_str(&text).unwrap_or(Value::Null)))
+    }
+
+    async fn must(&self, method: Method, url: &str, body: Option<Value>) -> Result<Value> {
+        self.request(method, url, body).await?.ok_or_else(|| anyhow::anyhow!("{} not found", url))
+    }
+
+    fn project(&self) -> Result<&str> {
+        self.project.as_deref().ok_or_else(|| anyhow::anyhow!("GCP_PROJECT is not set"))
+    }
+
+    fn service_url(&self, ctx: &OrchestratorContext) -> Result<String> {
+        Ok(format!("{}/v2/projects/{}/locations/{}/services/{}", self.run_endpoint, self.project()?, ctx.namespace.as_deref().unwrap_or(&self.region), pool_name(ctx)))
+    }
+
+    fn mig_url(&self, ctx: &OrchestratorContext) -> Result<String> {
+        Ok(format!("{}/compute/v1/projects/{}/zones/{}/instanceGroupManagers/{}", self.compute_endpoint, self.project()?, ctx.namespace.as_deref().unwrap_or(&self.zone), pool_name(ctx)))
+    }
+}
+
+fn pool_name(ctx: &OrchestratorContext) -> &str {
+    ctx.name.as_deref().unwrap_or("ectusr2-workers")
+}
+
+fn operation(v: &Value) -> &str {
+    v["name"].as_str().unwrap_or("-")
+}
+
+/// Raise the instance floor to `replicas`, lifting the ceiling if it would sit below it.
+fn set_min_instances(service: &mut Value, replicas: u32) {
+    let max = service["template"]["scaling"]["maxInstanceCount"].as_u64().unwrap_or(0).max(replicas as u64);
+    service["template"]["scaling"]["minInstanceCount"] = json!(replicas);
+    service["template"]["scaling"]["maxInstanceCount"] = json!(max);
+}
+
+/// Cloud Run v2 template from `{ image, env, port, resources: {cpu, memory}, min_instances, max_instances }`.
+fn apply_run_spec(service: &mut Value, spec: &Value) {
+    let container = super::first_container(&mut service["template"], json!({}));
+    if let Some(image) = spec.get("image") { container["image"] = image.clone(); }
+    if let Some(env) = spec.get("env").and_then(|e| e.as_object()) {
+        container["env"] = json!(env.iter().map(|(k, v)| json!({"name": k, "value": v.as_str().map(|s| s.to_string()).unwrap_or_else(|| v.to_string())})).collect::<Vec<_>>());
+    }
+    if let Some(port) = spec.get("port") { container["ports"] = json!([{"containerPort": port}]); }
+    if let Some(r) = spec.get("resources") {
+        container["resources"] = json!({"limits": {"cpu": r["cpu"].as_str().unwrap_or("1"), "memory": r["memory"].as_str().unwrap_or("512Mi")}});
+    }
+    let scaling = &mut service["template"]["scaling"];
+    if let Some(v) = spec.get("min_instances").or_else(|| spec.get("replicas")) { scaling["minInstanceCount"] = v.clone(); }
+    if let Some(v) = spec.get("max_instances") { scaling["maxInstanceCount"] = v.clone(); }
+}
+
+#[async_trait]
+impl Orchestrator for GcpOrchestrator {
+    fn capabilities(&self) -> Capabilities {
+        let backend = match self.kind { GcpKind::CloudRun => "cloud_run", GcpKind::Mig => "mig" };
+        Capabilities { backend, delete_pool: true, ..Default::default() }
+    }
+    async fn status(&self, ctx: &OrchestratorContext) -> Result<String> {
+        match self.kind {
+            GcpKind::CloudRun => {
+                let url = self.service_url(ctx)?;
+                let Some(s) = self.request(Method::GET, &url, None).await? else { return Ok(format!("cloud run service {}: not found", pool_name(ctx))) };
+                Ok(format!(
+                    "cloud run service {}: min {} max {} instances, ready {}, latest revision {}",
+                    pool_name(ctx),
+                    s["template"]["scaling"]["minInstanceCount"].as_u64().unwrap_or(0),
+                    s["template"]["scaling"]["maxInstanceCount"].as_u64().unwrap_or(0),
+                    s["terminalCondition"]["state"].as_str().unwrap_or("UNKNOWN"),
+                    s["latestReadyRevision"].as_str().unwrap_or("-"),
+                ))
+            }
+            GcpKind::Mig => {
+                let url = self.mig_url(ctx)?;
+                let Some(m) = self.request(Method::GET, &url, None).await? else { return Ok(format!("instance group {}: not found", pool_name(ctx))) };
+                Ok(format!(
+                    "instance group {}: target size {}, stable {}, template {}",
+                    pool_name(ctx),
+                    m["targetSize"].as_u64().unwrap_or(0),
+                    m["status"]["isStable"].as_bool().unwrap_or(false),
+                    m["instanceTemplate"].as_str().unwrap_or("-"),
+                ))
+            }
+        }
+    }
+    async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
+        match self.kind {
+            GcpKind::CloudRun => {
+                let url = self.service_url(ctx)?;
+                let mut service = self.must(Method::GET, &url, None).await?;
+                set_min_instances(&mut service, replicas);
+                let op = self.must(Method::PATCH, &url, Some(service)).await?;
+                Ok(format!("cloud run service {} min instances set to {} (operation {})", pool_name(ctx), replicas, operation(&op)))
+            }
+            GcpKind::Mig => {
+                let url = format!("{}/resize?size={}", self.mig_url(ctx)?, replicas);
+                let op = self.must(Method::POST, &url, None).await?;
+                Ok(format!("instance group {} resized to {} (operation {})", pool_name(ctx), replicas, operation(&op)))
+            }
+        }
+    }
+    async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String> {
+        let parsed: Value = serde_json::from_str(spec).unwrap_or(json!({}));
+        match self.kind {
+            GcpKind::CloudRun => {
+                let url = self.service_url(ctx)?;
+                match self.request(Method::GET, &url, None).await? {
+                    Some(mut service) => {
+                        apply_run_spec(&mut service, &parsed);
+                        let op = self.must(Method::PATCH, &url, Some(service)).await?;
+                        Ok(format!("updated cloud run service {} (operation {})", pool_name(ctx), operation(&op)))
+                    }
+                    None => {
+                        let mut service = json!({"template": {"containers": [{}], "scaling": {}}});
+                        apply_run_spec(&mut service, &parsed);
+                        let parent = url.rsplit_once('/').map(|(p, _)| p).unwrap_or(&url);
+                        let op = self.must(Method::POST, &format!("{}?serviceId={}", parent, pool_name(ctx)), Some(service)).await?;
+                        Ok(format!("created cloud run service {} (operation {})", pool_name(ctx), operation(&op)))
+                    }
+                }
+            }
+            GcpKind::Mig => {
+                let url = self.mig_url(ctx)?;
+                let size = parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1);
+                let template = parsed.get("instance_template").and_then(|v| v.as_str());
+                match self.request(Method::GET, &url, None).await? {
+                    Some(mig) => {
+                        if let Some(t) = template.filter(|t| mig["instanceTemplate"].as_str() != Some(*t)) {
+                            let _ = self.must(Method::POST, &format!("{}/setInstanceTemplate", url), Some(json!({"instanceTemplate": t}))).await?;
+                        }
+                        let op = self.must(Method::POST, &format!("{}/resize?size={}", url, size), None).await?;
+                        Ok(format!("updated instance group {} (target size {}, operation {})", pool_name(ctx), size, operation(&op)))
+                    }
+                    None => {
+                        let template = template.ok_or_else(|| anyhow::anyhow!("spec.instance_template is required to create an instance group"))?;
+                        let body = json!({
+                            "name": pool_name(ctx),
+                            "instanceTemplate": template,
+                            "baseInstanceName": parsed.get("base_instance_name").and_then(|v| v.as_str()).unwrap_or(pool_name(ctx)),
+                            "targetSize": size,
+                        });
+                        let parent = url.rsplit_once('/').map(|(p, _)| p).unwrap_or(&url);
+                        let op = self.must(Method::POST, parent, Some(body)).await?;
+                        Ok(format!("created instance group {} (target size {}, operation {})", pool_name(ctx), size, operation(&op)))
+                    }
+                }
+            }
+        }
+    }
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let url = match self.kind { GcpKind::CloudRun => self.service_url(ctx)?, GcpKind::Mig => self.mig_url(ctx)? };
+        let op = self.must(Method::DELETE, &url, None).await?;
+        Ok(format!("deleting {} (operation {})", pool_name(ctx), operation(&op)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::fake_http::FakeServer;
+
+    fn ctx(ns: &str) -> OrchestratorContext {
+        OrchestratorContext { namespace: Some(ns.into()), name: Some("w".into()), model: None, cluster: None }
+    }
+
+    #[tokio::test]
+    async fn test_cloud_run_scale_and_mig_resize() {
+        let server = FakeServer::start(|req| match (req.method.as_str(), req.path.as_str()) {
+            ("GET", "/v2/projects/p/locations/us-central1/services/w") => (200, json!({"template": {"scaling": {"minInstanceCount": 0, "maxInstanceCount": 2}}}).to_string()),
+            ("GET", _) => (404, "{}".into()),
+            _ => (200, json!({"name": "op-1"}).to_string()),
+        }).await;
+        let gcp = GcpOrchestrator::new(Some("p"), "us-central1", "us-central1-a", Some(&server.url)).with_token("tok");
+
+        let res = gcp.scale(&ctx("us-central1"), 3).await.unwrap();
+        assert!(res.contains("op-1"));
+        let mig = gcp.with_kind(GcpKind::Mig);
+        mig.scale(&ctx("us-central1-a"), 4).await.unwrap();
+        assert!(mig.status(&ctx("us-central1-a")).await.unwrap().contains("not found"));
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].headers["authorization"], "Bearer tok");
+        let patched: Value = serde_json::from_str(&reqs[1].body).unwrap();
+        assert_eq!((reqs[1].method.as_str(), &patched["template"]["scaling"]), ("PATCH", &json!({"minInstanceCount": 3, "maxInstanceCount": 3})));
+        assert_eq!((reqs[2].method.as_str(), reqs[2].path.as_str()), ("POST", "/compute/v1/projects/p/zones/us-central1-a/instanceGroupManagers/w/resize?size=4"));
+    }
+
+    #[test]
+    fn test_apply_run_spec() {
+        let mut svc = json!({"template": {"containers": [{}], "scaling": {}}});
+        apply_run_spec(&mut svc, &json!({"image": "gcr.io/p/app:1", "min_instances": 1, "max_instances": 5, "resources": {"cpu": "2", "memory": "1Gi"}}));
+        assert_eq!(svc["template"]["containers"][0]["image"], "gcr.io/p/app:1");
+        assert_eq!(svc["template"]["containers"][0]["resources"]["limits"]["cpu"], "2");
+        assert_eq!(svc["template"]["scaling"], json!({"minInstanceCount": 1, "maxInstanceCount": 5}));
+        // a service with no containers, or an empty list, gets one rather than panicking
+        for mut svc in [json!({"template": {}}), json!({"template": {"containers": []}}), json!({})] {
+            apply_run_spec(&mut svc, &json!({"image": "gcr.io/p/app:2"}));
+            assert_eq!(svc["template"]["containers"], json!([{"image": "gcr.io/p/app:2"}]));
+        }
+    }
+}
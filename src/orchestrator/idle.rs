@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::budget::money::{hours_from_ms, Decimal};
+use crate::orchestrator::OrchestratorContext;
+use crate::state::AppState;
+use crate::util::now_ms;
+
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub backend: String,
+    pub namespace: String,
+    pub name: String,
+    pub cluster: Option<String>,
+}
+
+impl PoolKey {
+    pub fn new(backend: &str, ctx: &OrchestratorContext) -> Self {
+        Self {
+            backend: backend.to_string(),
+            namespace: ctx.namespace.clone().unwrap_or_else(|| "default".into()),
+            name: ctx.name.clone().unwrap_or_else(|| "ectusr2-workers".into()),
+            cluster: ctx.cluster.clone(),
+        }
+    }
+
+    /// Stable pool id for the spend ledger, e.g. `kubernetes:prod:aion/workers`.
+    pub fn id(&self) -> String {
+        match &self.cluster {
+            Some(c) => format!("{}:{}:{}/{}", self.backend, c, self.namespace, self.name),
+            None => format!("{}:{}/{}", self.backend, self.namespace, self.name),
+        }
+    }
+
+    pub fn context(&self) -> OrchestratorContext {
+        OrchestratorContext { namespace: Some(self.namespace.clone()), name: Some(self.name.clone()), model: None, cluster: self.cluster.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PoolActivity {
+    replicas: u32,            // replicas to restore when traffic returns
+    hourly_usd: Decimal,      // cost of `replicas` per hour, credited while scaled to zero
+    idle_minutes: Option<u64>,
+    last_activity_ms: u128,
+    scaled_to_zero_at: Option<u128>,
+}
+
+/// Tracks tool traffic per pool and decides when a pool may be scaled to zero.
+pub struct IdleTracker {
+    default_minutes: Option<u64>,
+    pools: Mutex<HashMap<PoolKey, PoolActivity>>,
+    credited_usd: Mutex<Decimal>,
+}
+
+impl IdleTracker {
+    pub fn new(default_minutes: Option<u64>) -> Self {
+        Self { default_minutes, pools: Mutex::new(HashMap::new()), credited_usd: Mutex::new(Decimal::ZERO) }
+    }
+
+    /// Register (or refresh) a pool after a scale/ensure. Scaling to zero explicitly stops tracking it.
+    pub fn record(&self, key: PoolKey, replicas: u32, hourly_usd: Decimal, idle_minutes: Option<u64>) {
+        let mut pools = self.pools.lock().unwrap();
+        if replicas == 0 {
+            pools.remove(&key);
+            return;
+        }
+        let idle_minutes = idle_minutes.or_else(|| pools.get(&key).and_then(|p| p.idle_minutes));
+        pools.insert(key, PoolActivity { replicas, hourly_usd, idle_minutes, last_activity_ms: now_ms(), scaled_to_zero_at: None });
+    }
+
+    pub fn forget(&self, key: &PoolKey) {
+        self.pools.lock().unwrap().remove(key);
+    }
+
+    /// Mark traffic for `pool` (by name). Returns the pools that are currently scaled to zero and must be restored.
+    pub fn touch(&self, pool: &str) -> Vec<(PoolKey, u32)> {
+        self.touch_at(pool, now_ms())
+    }
+
+    fn touch_at(&self, pool: &str, now: u128) -> Vec<(PoolKey, u32)> {
+        let mut restore = Vec::new();
+        let mut credited = Decimal::ZERO;
+        let mut pools = self.pools.lock().unwrap();
+        for (key, p) in pools.iter_mut().filter(|(k, _)| k.name == pool) {
+            p.last_activity_ms = now;
+            if let Some(since) = p.scaled_to_zero_at.take() {
+                credited += accrued(p.hourly_usd, since, now);
+                restore.push((key.clone(), p.replicas));
+            }
+        }
+        *self.credited_usd.lock().unwrap() += credited;
+        restore
+    }
+
+    /// Pools whose idle window has elapsed and that are not yet scaled to zero.
+    pub fn due(&self) -> Vec<PoolKey> {
+        self.due_at(now_ms())
+    }
+
+    fn due_at(&self, now: u128) -> Vec<PoolKey> {
+        let pools = self.pools.lock().unwrap();
+        pools.iter()
+            .filter(|(_, p)| p.scaled_to_zero_at.is_none())
+            .filter(|(_, p)| match p.idle_minutes.or(self.default_minutes) {
+                Some(m) => now.saturating_sub(p.last_activity_ms) >= m as u128 * 60_000,
+                None => false,
+            })
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+
+    /// Hourly cost of the pool at its restore size.
+    pub fn hourly_usd(&self, key: &PoolKey) -> Option<Decimal> {
+        self.pools.lock().unwrap().get(key).map(|p| p.hourly_usd)
+    }
+
+    pub fn mark_scaled_to_zero(&self, key: &PoolKey) {
+        if let Some(p) = self.pools.lock().unwrap().get_mut(key) {
+            p.scaled_to_zero_at = Some(now_ms());
+        }
+    }
+
+    /// Total spend avoided by idle scale-to-zero, including pools that are idle right now.
+    pub fn savings_usd(&self) -> Decimal {
+        self.savings_at(now_ms())
+    }
+
+    fn savings_at(&self, now: u128) -> Decimal {
+        let pools = self.pools.lock().unwrap();
+        let ongoing: Decimal = pools.values().filter_map(|p| p.scaled_to_zero_at.map(|s| accrued(p.hourly_usd, s, now))).sum();
+        *self.credited_usd.lock().unwrap() + ongoing
+    }
+
+    pub fn describe(&self) -> Vec<Value> {
+        let pools = self.pools.lock().unwrap();
+        pools.iter().map(|(k, p)| json!({
+            "backend": k.backend, "cluster": k.cluster, "namespace": k.namespace, "name": k.name,
+            "replicas": p.replicas,
+            "idle_minutes": p.idle_minutes.or(self.default_minutes),
+            "scaled_to_zero": p.scaled_to_zero_at.is_some(),
+        })).collect()
+    }
+}
+
+fn accrued(hourly_usd: Decimal, since_ms: u128, now_ms: u128) -> Decimal {
+    hourly_usd * hours_from_ms(now_ms.saturating_sub(since_ms) as u64)
+}
+
+/// Restore `pool` if it was scaled to zero before serving traffic for it; traffic naming no pool wakes none.
+pub async fn wake(state: &AppState, pool: Option<&str>) {
+    let Some(pool) = pool else { return };
+    for (key, replicas) in state.idle.touch(pool) {
+        let res = match crate::orchestrator::new_backend(state, &key.backend) {
+            Ok(orch) => orch.scale(&key.context(), replicas).await,
+            Err(e) => Err(e.into()),
+        };
+        match res {
+            Ok(res) => {
+                state.ledger.rate(&key.id(), replicas, state.idle.hourly_usd(&key).unwrap_or_default(), None);
+                info!(pool = %key.name, replicas, %res, "restored idle pool");
+            }
+            Err(e) => warn!(pool = %key.name, error = %e, "failed to restore idle pool"),
+        }
+    }
+}
+
+/// Background loop that scales idle pools to zero.
+pub async fn run(state: Arc<AppState>) {
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tick.tick().await;
+        for key in state.idle.due() {
+            if let Err(e) = state.policy.check(&key.backend, &key.context(), Some(0)) {
+                // the policy will not change at runtime, so stop tracking instead of retrying every tick
+                warn!(pool = %key.name, error = %e, "policy forbids scaling idle pool to zero");
+                state.idle.forget(&key);
+                continue;
+            }
+            let res = match crate::orchestrator::new_backend(&state, &key.backend) {
+                Ok(orch) => orch.scale(&key.context(), 0).await,
+                Err(e) => Err(e.into()),
+            };
+            match res {
+                Ok(res) => {
+                    state.idle.mark_scaled_to_zero(&key);
+                    state.ledger.rate(&key.id(), 0, Decimal::ZERO, None);
+                    info!(pool = %key.name, %res, "scaled idle pool to zero");
+                }
+                Err(e) => warn!(pool = %key.name, error = %e, "failed to scale idle pool to zero"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> PoolKey {
+        PoolKey { backend: "local".into(), namespace: "default".into(), name: name.into(), cluster: None }
+    }
+
+    #[test]
+    fn test_idle_cycle_credits_savings() {
+        let t = IdleTracker::new(Some(10));
+        t.record(key("a"), 4, Decimal::TWO, None);
+        let start = t.pools.lock().unwrap()[&key("a")].last_activity_ms;
+        assert!(t.due_at(start + 9 * 60_000).is_empty());
+        assert_eq!(t.due_at(start + 10 * 60_000), vec![key("a")]);
+
+        t.pools.lock().unwrap().get_mut(&key("a")).unwrap().scaled_to_zero_at = Some(start);
+        assert_eq!(t.savings_at(start + 1_800_000), Decimal::ONE);
+        assert!(t.touch_at("b", start + 3_600_000).is_empty());
+        let restore = t.touch_at("a", start + 3_600_000);
+        assert_eq!(restore, vec![(key("a"), 4)]);
+        assert_eq!(t.savings_at(start + 7_200_000), Decimal::TWO);
+    }
+
+    #[test]
+    fn test_explicit_zero_and_per_pool_window() {
+        let t = IdleTracker::new(None);
+        t.record(key("a"), 2, Decimal::ONE, None);
+        t.record(key("b"), 2, Decimal::ONE, Some(5));
+        let now = now_ms() + 6 * 60_000;
+        assert_eq!(t.due_at(now), vec![key("b")]);
+        t.record(key("b"), 0, Decimal::ZERO, None);
+        assert!(t.due_at(now).is_empty());
+    }
+}
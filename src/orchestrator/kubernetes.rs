@@ -1,68 +1,726 @@
+/// Where to find a named cluster: a kubeconfig file (default: the ambient one) and/or a context in it.
 #[cfg(feature = "kubernetes")]
-pub struct KubeOrchestrator;
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterRef {
+    pub kubeconfig: Option<std::path::PathBuf>,
+    pub context: Option<String>,
+}
+
+/// Parse `KUBE_CLUSTERS`: comma-separated `name=context` or `name=/path/to/kubeconfig[#context]`.
+#[cfg(feature = "kubernetes")]
+pub fn parse_clusters(s: &str) -> anyhow::Result<std::collections::BTreeMap<String, ClusterRef>> {
+    let mut out = std::collections::BTreeMap::new();
+    for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, target) = entry.split_once('=').ok_or_else(|| anyhow::anyhow!("KUBE_CLUSTERS entry `{}` is not name=target", entry))?;
+        let cluster = if target.contains('/') {
+            let (path, context) = match target.split_once('#') { Some((p, c)) => (p, Some(c.to_string())), None => (target, None) };
+            ClusterRef { kubeconfig: Some(path.into()), context }
+        } else {
+            ClusterRef { kubeconfig: None, context: Some(target.to_string()) }
+        };
+        out.insert(name.trim().to_string(), cluster);
+    }
+    Ok(out)
+}
+
+/// Kubernetes Deployments, on the ambient cluster or any cluster named in `KUBE_CLUSTERS`.
+#[cfg(feature = "kubernetes")]
+#[derive(Clone)]
+pub struct KubeOrchestrator {
+    clusters: std::sync::Arc<std::collections::BTreeMap<String, ClusterRef>>,
+    default_cluster: Option<String>,
+    // connected clients by cluster name ("" = ambient config), reused across calls
+    clients: std::sync::Arc<tokio::sync::Mutex<HashMap<String, kube::Client>>>,
+    // operator mode: pool tools edit ModelPool resources and the controller reconciles them
+    operator: bool,
+}
 
 #[cfg(feature = "kubernetes")]
 #[async_trait::async_trait]
 impl crate::orchestrator::Orchestrator for KubeOrchestrator {
+    fn capabilities(&self) -> crate::orchestrator::Capabilities {
+        crate::orchestrator::Capabilities { backend: "kubernetes", delete_pool: true, dry_run: true, autoscaling: true, batch: true, logs: true }
+    }
     async fn status(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<String> {
-        use kube::{Client, Api, api::ListParams};
+        use kube::{Api, api::ListParams};
         use k8s_openapi::api::apps::v1::Deployment;
         let ns = ctx.namespace.as_deref().unwrap_or("default");
-        let client = Client::try_default().await?;
+        let client = self.client(ctx).await?;
         let api: Api<Deployment> = Api::namespaced(client, ns);
         let list = api.list(&ListParams::default()).await?;
         let mut out = Vec::new();
-        for d in list { let name=d.metadata.name.unwrap_or_default(); out.push(name); }
+        for d in list {
+            let gpus = serde_json::to_value(&d).ok().and_then(|v| gpu_summary(&v));
+            let name = d.metadata.name.unwrap_or_default();
+            out.push(match gpus { Some(g) => format!("{} ({})", name, g), None => name });
+        }
         Ok(format!("deployments in {}: {}", ns, out.join(", ")))
     }
     async fn scale(&self, ctx: &crate::orchestrator::OrchestratorContext, replicas: u32) -> anyhow::Result<String> {
-        use kube::{Client, Api, api::{Patch, PatchParams}};
+        use kube::{Api, api::{Patch, PatchParams}};
         use k8s_openapi::api::apps::v1::Deployment;
         let ns = ctx.namespace.as_deref().unwrap_or("default");
         let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
-        let client = Client::try_default().await?;
+        let client = self.client(ctx).await?;
+        if self.operator {
+            crate::orchestrator::operator::scale_pool(client, ns, name, replicas, false).await?;
+            return Ok(format!("set ModelPool {} to {} replicas in {}", name, replicas, ns));
+        }
+        // An HPA owns spec.replicas; move its bounds instead of fighting it. Scaling to zero still
+        // patches the Deployment directly, since the HPA goes inactive at zero replicas.
+        if replicas > 0 {
+            if let Some(hpa) = Self::find_hpa(client.clone(), ns, name).await? {
+                return Self::adjust_hpa_bounds(client, ns, name, &hpa, replicas).await;
+            }
+        }
         let api: Api<Deployment> = Api::namespaced(client, ns);
-        let patch = serde_json::json!({"spec": {"replicas": replicas as i32}});
         let pp = PatchParams::apply("ectusr2").force();
-        let _ = api.patch(name, &pp, &Patch::Apply(&patch)).await?;
+        let _ = api.patch(name, &pp, &Patch::Apply(&scale_patch(replicas))).await?;
         Ok(format!("scaled {} to {} in {}", name, replicas, ns))
     }
-    async fn ensure_pool(&self, _ctx: &crate::orchestrator::OrchestratorContext, _spec: &str) -> anyhow::Result<String> {
-        Ok("ensure_pool not implemented for k8s yet".into())
+    async fn preview_scale(&self, ctx: &crate::orchestrator::OrchestratorContext, replicas: u32) -> anyhow::Result<crate::orchestrator::Preview> {
+        use kube::{Api, api::{Patch, PatchParams}};
+        use k8s_openapi::api::apps::v1::Deployment;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::scale_pool(client, ns, name, replicas, true).await;
+        }
+        let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+        let live = deployments.get_opt(name).await?;
+        let current_replicas = live.as_ref().and_then(|d| d.spec.as_ref()).and_then(|s| s.replicas).unwrap_or(0).max(0) as u32;
+        if replicas > 0 {
+            if let Some(hpa) = Self::find_hpa(client.clone(), ns, name).await? {
+                let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(client, ns);
+                let hpa_name = hpa.metadata.name.clone().unwrap_or_default();
+                let proposed = hpas.patch(&hpa_name, &PatchParams::default().dry_run(), &Patch::Merge(&hpa_bounds_patch(&hpa, replicas))).await?;
+                return Ok(crate::orchestrator::Preview {
+                    live: Some(strip_server_fields(serde_json::to_value(&hpa)?)),
+                    proposed: strip_server_fields(serde_json::to_value(&proposed)?),
+                    current_replicas,
+                });
+            }
+        }
+        let pp = PatchParams::apply("ectusr2").force().dry_run();
+        let proposed = deployments.patch(name, &pp, &Patch::Apply(&scale_patch(replicas))).await?;
+        Ok(crate::orchestrator::Preview {
+            live: live.map(|d| serde_json::to_value(&d)).transpose()?.map(strip_server_fields),
+            proposed: strip_server_fields(serde_json::to_value(&proposed)?),
+            current_replicas,
+        })
+    }
+    async fn ensure_pool(&self, ctx: &crate::orchestrator::OrchestratorContext, spec: &str) -> anyhow::Result<String> {
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        if self.operator {
+            let applied = crate::orchestrator::operator::apply_pool(self.client(ctx).await?, ctx, spec, false).await?;
+            return Ok(format!("applied ModelPool {} in {} (replicas {})", name, ns, applied.proposed["spec"]["replicas"]));
+        }
+        Self::ensure_deployment(self.client(ctx).await?, ns, name, spec).await
+    }
+    async fn preview_pool(&self, ctx: &crate::orchestrator::OrchestratorContext, spec: &str) -> anyhow::Result<crate::orchestrator::Preview> {
+        use kube::{Api, api::{Patch, PatchParams}};
+        use k8s_openapi::api::apps::v1::Deployment;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::apply_pool(client, ctx, spec, true).await;
+        }
+        let api: Api<Deployment> = Api::namespaced(client, ns);
+        let live = api.get_opt(name).await?;
+        let current_replicas = live.as_ref().and_then(|d| d.spec.as_ref()).and_then(|s| s.replicas).unwrap_or(0).max(0) as u32;
+        let pp = PatchParams::apply("ectusr2").force().dry_run();
+        let proposed = api.patch(name, &pp, &Patch::Apply(&deployment_manifest(name, spec))).await?;
+        Ok(crate::orchestrator::Preview {
+            live: live.map(|d| serde_json::to_value(&d)).transpose()?.map(strip_server_fields),
+            proposed: strip_server_fields(serde_json::to_value(&proposed)?),
+            current_replicas,
+        })
+    }
+    async fn delete_pool(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<String> {
+        use kube::{Api, api::DeleteParams};
+        use k8s_openapi::api::apps::v1::Deployment;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        // Deleting is destructive: never fall back to the default workers name
+        let name = ctx.name.as_deref().ok_or_else(|| anyhow::anyhow!("name is required to delete a pool"))?;
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::delete_pool(client, ns, name).await;
+        }
+        if let Some(hpa) = Self::find_hpa(client.clone(), ns, name).await? {
+            let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(client.clone(), ns);
+            let _ = hpas.delete(&hpa.metadata.name.unwrap_or_default(), &DeleteParams::default()).await?;
+        }
+        let api: Api<Deployment> = Api::namespaced(client, ns);
+        let _ = api.delete(name, &DeleteParams::foreground()).await?;
+        Ok(format!("deleted deployment {} in {}", name, ns))
+    }
+    async fn autoscaled_max(&self, ctx: &crate::orchestrator::OrchestratorContext, replicas: u32) -> anyhow::Result<Option<u32>> {
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        // at zero the Deployment is patched directly and the HPA goes inactive
+        if replicas == 0 { return Ok(None); }
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::autoscaled_max(client, ns, name, replicas).await;
+        }
+        Ok(Self::find_hpa(client, ns, name).await?.map(|hpa| hpa_max(&hpa, replicas)))
+    }
+    async fn ensure_autoscaler(&self, ctx: &crate::orchestrator::OrchestratorContext, spec: &crate::orchestrator::AutoscalerSpec) -> anyhow::Result<String> {
+        use kube::{Api, api::{Patch, PatchParams}};
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::set_autoscaling(client, ns, name, Some(spec)).await;
+        }
+        // Reuse an HPA already targeting the pool (e.g. the chart's) rather than creating a second one
+        let hpa_name = match Self::find_hpa(client.clone(), ns, name).await? {
+            Some(h) => h.metadata.name.unwrap_or_else(|| hpa_name(name)),
+            None => hpa_name(name),
+        };
+        let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client, ns);
+        let pp = PatchParams::apply("ectusr2").force();
+        let _ = api.patch(&hpa_name, &pp, &Patch::Apply(&hpa_manifest(&hpa_name, name, spec))).await?;
+        Ok(format!("ensured hpa {} for {} in {} (min {}, max {})", hpa_name, name, ns, spec.min_replicas, spec.max_replicas))
+    }
+    async fn submit_job(&self, ctx: &crate::orchestrator::OrchestratorContext, spec: &crate::orchestrator::JobSpec) -> anyhow::Result<String> {
+        use kube::{Api, api::PostParams};
+        use k8s_openapi::api::batch::v1::Job;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().ok_or_else(|| anyhow::anyhow!("name is required to submit a job"))?;
+        let job: Job = serde_json::from_value(job_manifest(name, spec))?;
+        let api: Api<Job> = Api::namespaced(self.client(ctx).await?, ns);
+        // create, not apply: a finished Job is immutable and must be deleted before reuse
+        let _ = api.create(&PostParams::default(), &job).await?;
+        Ok(format!("submitted job {} in {} (parallelism {}, completions {})", name, ns, spec.parallelism, spec.completions))
+    }
+    async fn job_status(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<crate::orchestrator::JobProgress> {
+        use kube::Api;
+        use k8s_openapi::api::batch::v1::Job;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().ok_or_else(|| anyhow::anyhow!("name is required"))?;
+        let api: Api<Job> = Api::namespaced(self.client(ctx).await?, ns);
+        let job = api.get_opt(name).await?.ok_or_else(|| anyhow::anyhow!("job {} not found in {}", name, ns))?;
+        let now = (crate::util::now_ms() / 1000) as i64;
+        Ok(job_progress(&serde_json::to_value(&job)?, now))
+    }
+    async fn delete_job(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<String> {
+        use kube::{Api, api::DeleteParams};
+        use k8s_openapi::api::batch::v1::Job;
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().ok_or_else(|| anyhow::anyhow!("name is required to delete a job"))?;
+        let api: Api<Job> = Api::namespaced(self.client(ctx).await?, ns);
+        match api.get_opt(name).await? {
+            Some(_) => {
+                let _ = api.delete(name, &DeleteParams::background()).await?;
+                Ok(format!("deleted job {} and its pods in {}", name, ns))
+            }
+            None => Ok(format!("job {} not found in {}", name, ns)),
+        }
+    }
+    async fn pool_logs(&self, ctx: &crate::orchestrator::OrchestratorContext, query: &crate::orchestrator::LogQuery) -> anyhow::Result<crate::orchestrator::PoolDiagnostics> {
+        use kube::{Api, api::{ListParams, LogParams}};
+        use k8s_openapi::api::core::v1::{Event, Pod};
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        let client = self.client(ctx).await?;
+        let selector = Self::pod_selector(client.clone(), ns, name).await?;
+        let pods: Api<Pod> = Api::namespaced(client.clone(), ns);
+        let mut list: Vec<crate::orchestrator::PodLogs> = pods.list(&ListParams::default().labels(&selector)).await?
+            .into_iter().filter_map(|p| serde_json::to_value(&p).ok()).map(|p| pod_summary(&p)).collect();
+        // the pods worth reading first are the ones that are not ready
+        list.sort_by_key(|p| p.ready);
+        list.truncate(query.max_pods);
+        let per_pod = query.max_bytes / list.len().max(1);
+        for p in list.iter_mut() {
+            let params = LogParams {
+                container: query.container.clone(),
+                tail_lines: Some(query.tail_lines),
+                since_seconds: query.since_seconds,
+                previous: query.previous,
+                limit_bytes: Some(per_pod as i64),
+                ..Default::default()
+            };
+            p.logs = match pods.logs(&p.pod, &params).await {
+                Ok(l) => l,
+                Err(e) => format!("<logs unavailable: {}>", e),
+            };
+            p.truncated = p.logs.len() >= per_pod;
+        }
+        let mut events = Vec::new();
+        if query.events {
+            let api: Api<Event> = Api::namespaced(client, ns);
+            let all: Vec<Json> = api.list(&ListParams::default()).await?.into_iter().filter_map(|e| serde_json::to_value(&e).ok()).collect();
+            events = pool_events(&all, name, MAX_EVENTS);
+        }
+        Ok(crate::orchestrator::PoolDiagnostics { pods: list, events })
+    }
+    async fn delete_autoscaler(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<String> {
+        use kube::{Api, api::DeleteParams};
+        let ns = ctx.namespace.as_deref().unwrap_or("default");
+        let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+        let client = self.client(ctx).await?;
+        if self.operator {
+            return crate::orchestrator::operator::set_autoscaling(client, ns, name, None).await;
+        }
+        match Self::find_hpa(client.clone(), ns, name).await? {
+            Some(hpa) => {
+                let hpa_name = hpa.metadata.name.unwrap_or_default();
+                let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client, ns);
+                let _ = api.delete(&hpa_name, &DeleteParams::default()).await?;
+                Ok(format!("deleted hpa {} for {} in {}", hpa_name, name, ns))
+            }
+            None => Ok(format!("no hpa targets {} in {}", name, ns)),
+        }
     }
 }
 #[cfg(feature = "kubernetes")]
 use serde_json::Value as Json;
+#[cfg(feature = "kubernetes")]
+use std::collections::HashMap;
+#[cfg(feature = "kubernetes")]
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
 
 #[cfg(feature = "kubernetes")]
 impl KubeOrchestrator {
-    async fn ensure_deployment(ns: &str, name: &str, spec: &str) -> anyhow::Result<String> {
-        use kube::{Client, Api, api::{Patch, PatchParams}};
+    pub fn new(clusters: std::collections::BTreeMap<String, ClusterRef>, default_cluster: Option<String>, operator: bool) -> Self {
+        Self { clusters: std::sync::Arc::new(clusters), default_cluster, clients: Default::default(), operator }
+    }
+
+    /// Client for `ctx.cluster` (or the default cluster), connecting on first use.
+    pub(crate) async fn client(&self, ctx: &crate::orchestrator::OrchestratorContext) -> anyhow::Result<kube::Client> {
+        use kube::config::{KubeConfigOptions, Kubeconfig};
+        let name = ctx.cluster.as_deref().or(self.default_cluster.as_deref()).unwrap_or("");
+        let mut clients = self.clients.lock().await;
+        if let Some(c) = clients.get(name) { return Ok(c.clone()); }
+        let client = if name.is_empty() {
+            kube::Client::try_default().await?
+        } else {
+            let cluster = self.clusters.get(name).ok_or_else(|| anyhow::anyhow!(
+                "unknown cluster `{}` (configured: {})", name, self.clusters.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))?;
+            let kubeconfig = match &cluster.kubeconfig { Some(p) => Kubeconfig::read_from(p)?, None => Kubeconfig::read()? };
+            let options = KubeConfigOptions { context: cluster.context.clone(), ..Default::default() };
+            let config = kube::Config::from_custom_kubeconfig(kubeconfig, &options).await
+                .map_err(|e| anyhow::anyhow!("cluster `{}`: {}", name, e))?;
+            kube::Client::try_from(config)?
+        };
+        clients.insert(name.to_string(), client.clone());
+        Ok(client)
+    }
+
+    async fn ensure_deployment(client: kube::Client, ns: &str, name: &str, spec: &str) -> anyhow::Result<String> {
+        use kube::{Api, api::{Patch, PatchParams}};
         use k8s_openapi::api::apps::v1::Deployment;
-        let client = Client::try_default().await?;
         let api: Api<Deployment> = Api::namespaced(client, ns);
-        // Accept prebuilt Deployment JSON or a minimal spec { image, replicas, labels, resources }
-        let parsed: Json = serde_json::from_str(spec).unwrap_or(Json::Object(Default::default()));
-        let replicas = parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1) as i32;
-        let image = parsed.get("image").and_then(|v| v.as_str()).unwrap_or("busybox:stable");
-        let labels = parsed.get("labels").cloned().unwrap_or(serde_json::json!({"app": name}));
-        // Very small patch to set replicas and a single container with image
-        let patch = serde_json::json!({
-           "apiVersion":"apps/v1",
-           "kind":"Deployment",
-           "metadata": {"name": name, "labels": labels},
-           "spec": {
-             "replicas": replicas,
-             "selector": {"matchLabels": {"app": name}},
-             "template": {
-               "metadata": {"labels": {"app": name}},
-               "spec": {"containers": [{"name": name, "image": image}]}
-             }
-           }
-        });
+        let patch = deployment_manifest(name, spec);
+        let replicas = patch["spec"]["replicas"].as_i64().unwrap_or(1);
         let pp = PatchParams::apply("ectusr2").force();
         let _ = api.patch(name, &pp, &Patch::Apply(&patch)).await?;
         Ok(format!("ensured deployment {} in {} (replicas {})", name, ns, replicas))
     }
+
+    /// Label selector for the pods of pool or job `name`: the Deployment's selector, a Job's
+    /// `job-name` label, or `app=<name>` as a last resort.
+    async fn pod_selector(client: kube::Client, ns: &str, name: &str) -> anyhow::Result<String> {
+        use kube::Api;
+        use k8s_openapi::api::{apps::v1::Deployment, batch::v1::Job};
+        let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+        if let Some(labels) = deployments.get_opt(name).await?.and_then(|d| d.spec).and_then(|s| s.selector.match_labels) {
+            return Ok(labels.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(","));
+        }
+        let jobs: Api<Job> = Api::namespaced(client, ns);
+        if jobs.get_opt(name).await?.is_some() {
+            return Ok(format!("job-name={}", name));
+        }
+        Ok(format!("app={}", name))
+    }
+
+    /// The HPA whose scaleTargetRef points at Deployment `name`, if any.
+    async fn find_hpa(client: kube::Client, ns: &str, name: &str) -> anyhow::Result<Option<HorizontalPodAutoscaler>> {
+        use kube::{Api, api::ListParams};
+        let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client, ns);
+        let list = api.list(&ListParams::default()).await?;
+        Ok(list.into_iter().find(|h| h.spec.as_ref().is_some_and(|s| s.scale_target_ref.kind == "Deployment" && s.scale_target_ref.name == name)))
+    }
+
+    async fn adjust_hpa_bounds(client: kube::Client, ns: &str, name: &str, hpa: &HorizontalPodAutoscaler, replicas: u32) -> anyhow::Result<String> {
+        use kube::{Api, api::{Patch, PatchParams}};
+        use k8s_openapi::api::apps::v1::Deployment;
+        let hpa_name = hpa.metadata.name.clone().unwrap_or_default();
+        let patch = hpa_bounds_patch(hpa, replicas);
+        let max = &patch["spec"]["maxReplicas"];
+        let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(client.clone(), ns);
+        let _ = hpas.patch(&hpa_name, &PatchParams::default(), &Patch::Merge(&patch)).await?;
+        // The HPA does nothing while its target sits at zero (e.g. after idle scale-to-zero)
+        let deployments: Api<Deployment> = Api::namespaced(client, ns);
+        let current = deployments.get(name).await?.spec.and_then(|s| s.replicas).unwrap_or(0);
+        if current == 0 {
+            let patch = serde_json::json!({"spec": {"replicas": replicas as i32}});
+            let _ = deployments.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await?;
+        }
+        Ok(format!("hpa {} bounds set to min {} max {} for {} in {}", hpa_name, replicas, max, name, ns))
+    }
+}
+
+#[cfg(feature = "kubernetes")]
+fn scale_patch(replicas: u32) -> Json {
+    serde_json::json!({"apiVersion": "apps/v1", "kind": "Deployment", "spec": {"replicas": replicas as i32}})
+}
+
+#[cfg(feature = "kubernetes")]
+pub(super) fn deployment_manifest(name: &str, spec: &str) -> Json {
+    // Accept prebuilt Deployment JSON or a minimal spec
+    // { image, replicas, labels, resources: {cpu, memory, gpu, gpu_type}, node_selector, tolerations, affinity, gpu_node_label }
+    let parsed: Json = serde_json::from_str(spec).unwrap_or(Json::Object(Default::default()));
+    if parsed.get("kind").and_then(|k| k.as_str()) == Some("Deployment") {
+        let mut d = parsed;
+        d["metadata"]["name"] = Json::String(name.to_string());
+        return d;
+    }
+    let replicas = parsed.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1) as i32;
+    let image = parsed.get("image").and_then(|v| v.as_str()).unwrap_or("busybox:stable");
+    let labels = parsed.get("labels").cloned().unwrap_or(serde_json::json!({"app": name}));
+    serde_json::json!({
+       "apiVersion":"apps/v1",
+       "kind":"Deployment",
+       "metadata": {"name": name, "labels": labels},
+       "spec": {
+         "replicas": replicas,
+         "selector": {"matchLabels": {"app": name}},
+         "template": {
+           "metadata": {"labels": {"app": name}},
+           "spec": pod_spec(name, image, &parsed)
+         }
+       }
+    })
+}
+
+/// Pod spec shared by pool Deployments and batch Jobs: one container plus GPU scheduling.
+#[cfg(feature = "kubernetes")]
+fn pod_spec(name: &str, image: &str, parsed: &Json) -> Json {
+    let mut container = serde_json::json!({"name": name, "image": image});
+    if let Some(cmd) = parsed.get("command") { container["command"] = cmd.clone(); }
+    if let Some(args) = parsed.get("args") { container["args"] = args.clone(); }
+    if let Some(env) = parsed.get("env").and_then(|e| e.as_object()) {
+        container["env"] = serde_json::json!(env.iter().map(|(k, v)| serde_json::json!({"name": k, "value": v.as_str().map(|s| s.to_string()).unwrap_or_else(|| v.to_string())})).collect::<Vec<_>>());
+    }
+    let mut pod = serde_json::json!({});
+    if let Some(r) = parsed.get("resources") {
+        container["resources"] = container_resources(r);
+    }
+    if let Some(sel) = parsed.get("node_selector") { pod["nodeSelector"] = sel.clone(); }
+    if let Some(aff) = parsed.get("affinity") { pod["affinity"] = aff.clone(); }
+    let mut tolerations = parsed.get("tolerations").and_then(|t| t.as_array()).cloned().unwrap_or_default();
+    if gpu_count(parsed.get("resources")) > 0 {
+        // GPU node pools are usually tainted so only GPU workloads land there
+        if !tolerations.iter().any(|t| t["key"] == GPU_RESOURCE) {
+            tolerations.push(serde_json::json!({"key": GPU_RESOURCE, "operator": "Exists", "effect": "NoSchedule"}));
+        }
+        let types: Vec<&str> = parsed["resources"]["gpu_type"].as_str().map(|t| t.split(',').map(str::trim).filter(|t| !t.is_empty()).collect()).unwrap_or_default();
+        if !types.is_empty() && pod.get("affinity").is_none() {
+            let label = parsed.get("gpu_node_label").and_then(|v| v.as_str()).unwrap_or(GPU_PRODUCT_LABEL);
+            pod["affinity"] = serde_json::json!({"nodeAffinity": {"requiredDuringSchedulingIgnoredDuringExecution": {
+                "nodeSelectorTerms": [{"matchExpressions": [{"key": label, "operator": "In", "values": types}]}]
+            }}});
+        }
+    }
+    if !tolerations.is_empty() { pod["tolerations"] = Json::Array(tolerations); }
+    pod["containers"] = serde_json::json!([container]);
+    pod
+}
+
+#[cfg(feature = "kubernetes")]
+const MAX_EVENTS: usize = 30;
+
+/// Phase, readiness, restarts and the most telling container state reason of a Pod (as JSON).
+#[cfg(feature = "kubernetes")]
+fn pod_summary(pod: &Json) -> crate::orchestrator::PodLogs {
+    let statuses = pod["status"]["containerStatuses"].as_array().cloned().unwrap_or_default();
+    let reason = statuses.iter().find_map(|c| {
+        c["state"]["waiting"]["reason"].as_str()
+            .or(c["state"]["terminated"]["reason"].as_str())
+            .or(c["lastState"]["terminated"]["reason"].as_str())
+    });
+    crate::orchestrator::PodLogs {
+        pod: pod["metadata"]["name"].as_str().unwrap_or_default().into(),
+        phase: pod["status"]["phase"].as_str().unwrap_or("Unknown").into(),
+        ready: !statuses.is_empty() && statuses.iter().all(|c| c["ready"] == true),
+        restarts: statuses.iter().map(|c| c["restartCount"].as_u64().unwrap_or(0) as u32).sum(),
+        reason: reason.or(pod["status"]["reason"].as_str()).map(|s| s.to_string()),
+        ..Default::default()
+    }
+}
+
+/// The newest `limit` events about `name` or objects derived from it (ReplicaSets, Pods, HPA).
+#[cfg(feature = "kubernetes")]
+fn pool_events(events: &[Json], name: &str, limit: usize) -> Vec<crate::orchestrator::PoolEvent> {
+    let prefix = format!("{}-", name);
+    let last_seen = |e: &Json| e["lastTimestamp"].as_str().or(e["eventTime"].as_str()).or(e["metadata"]["creationTimestamp"].as_str()).map(|s| s.to_string());
+    let mut out: Vec<crate::orchestrator::PoolEvent> = events.iter()
+        .filter(|e| e["involvedObject"]["name"].as_str().is_some_and(|n| n == name || n.starts_with(&prefix)))
+        .map(|e| crate::orchestrator::PoolEvent {
+            kind: e["type"].as_str().unwrap_or("Normal").into(),
+            reason: e["reason"].as_str().unwrap_or_default().into(),
+            object: format!("{}/{}", e["involvedObject"]["kind"].as_str().unwrap_or("?"), e["involvedObject"]["name"].as_str().unwrap_or_default()),
+            message: e["message"].as_str().unwrap_or_default().into(),
+            count: e["count"].as_u64().unwrap_or(1) as u32,
+            last_seen: last_seen(e),
+        })
+        .collect();
+    // RFC 3339 timestamps in UTC sort lexicographically
+    out.sort_by(|a, b| a.last_seen.cmp(&b.last_seen));
+    let skip = out.len().saturating_sub(limit);
+    out.drain(..skip);
+    out
+}
+
+#[cfg(feature = "kubernetes")]
+fn job_manifest(name: &str, spec: &crate::orchestrator::JobSpec) -> Json {
+    let parsed = Json::Object(spec.pod.clone());
+    let mut pod = pod_spec(name, parsed["image"].as_str().unwrap_or_default(), &parsed);
+    // retries are counted by backoffLimit, so the kubelet must not restart containers in place
+    pod["restartPolicy"] = "Never".into();
+    let mut job = serde_json::json!({
+        "apiVersion": "batch/v1",
+        "kind": "Job",
+        "metadata": {"name": name, "labels": {"app": name, "ectusr2/batch": "true"}},
+        "spec": {
+            "parallelism": spec.parallelism,
+            "completions": spec.completions,
+            "backoffLimit": spec.backoff_limit,
+            "template": {"metadata": {"labels": {"app": name}}, "spec": pod},
+        }
+    });
+    if let Some(ttl) = spec.ttl_seconds_after_finished { job["spec"]["ttlSecondsAfterFinished"] = ttl.into(); }
+    if let Some(d) = spec.active_deadline_seconds { job["spec"]["activeDeadlineSeconds"] = d.into(); }
+    job
+}
+
+/// Progress, elapsed time and per-pod resources of a Job (as JSON).
+#[cfg(feature = "kubernetes")]
+fn job_progress(job: &Json, now_secs: i64) -> crate::orchestrator::JobProgress {
+    let status = &job["status"];
+    let count = |v: &Json| v.as_u64().unwrap_or(0) as u32;
+    let condition = status["conditions"].as_array().into_iter().flatten()
+        .find(|c| c["status"] == "True" && (c["type"] == "Complete" || c["type"] == "Failed"));
+    let state = match condition.and_then(|c| c["type"].as_str()) {
+        Some("Complete") => "complete",
+        Some(_) => "failed",
+        None if count(&status["active"]) > 0 => "running",
+        None => "pending",
+    };
+    let parse = |v: &Json| v.as_str().and_then(|t| k8s_openapi::chrono::DateTime::parse_from_rfc3339(t).ok()).map(|t| t.timestamp());
+    let started = parse(&status["startTime"]);
+    let ended = parse(&status["completionTime"]).or_else(|| condition.and_then(|c| parse(&c["lastTransitionTime"])));
+    let container = &job["spec"]["template"]["spec"]["containers"][0]["resources"];
+    let resources = crate::budget::Resources {
+        cpu: container["requests"]["cpu"].as_str().unwrap_or("1").into(),
+        memory: container["requests"]["memory"].as_str().unwrap_or("1Gi").into(),
+        gpu: container["limits"][GPU_RESOURCE].as_str().map(|s| s.to_string()),
+        gpu_type: None,
+        region: None,
+        instance_class: None,
+    };
+    crate::orchestrator::JobProgress {
+        name: job["metadata"]["name"].as_str().unwrap_or_default().into(),
+        state: state.into(),
+        active: count(&status["active"]),
+        succeeded: count(&status["succeeded"]),
+        failed: count(&status["failed"]),
+        parallelism: job["spec"]["parallelism"].as_u64().unwrap_or(1) as u32,
+        completions: job["spec"]["completions"].as_u64().unwrap_or(1) as u32,
+        elapsed_seconds: started.map(|s| (ended.unwrap_or(now_secs) - s).max(0) as u64),
+        message: condition.and_then(|c| c["message"].as_str().or(c["reason"].as_str())).map(|s| s.to_string()),
+        resources: Some(resources),
+    }
+}
+
+#[cfg(feature = "kubernetes")]
+const GPU_RESOURCE: &str = "nvidia.com/gpu";
+/// Node label set by NVIDIA GPU feature discovery; GKE uses `cloud.google.com/gke-accelerator`.
+#[cfg(feature = "kubernetes")]
+const GPU_PRODUCT_LABEL: &str = "nvidia.com/gpu.product";
+
+#[cfg(feature = "kubernetes")]
+fn gpu_count(resources: Option<&Json>) -> u64 {
+    resources.and_then(|r| r.get("gpu")).and_then(|g| g.as_u64().or_else(|| g.as_str().and_then(|s| s.parse().ok()))).unwrap_or(0)
+}
+
+/// Requests/limits for `{cpu, memory, gpu}`; GPUs can only be set as limits.
+#[cfg(feature = "kubernetes")]
+fn container_resources(r: &Json) -> Json {
+    let mut requests = serde_json::Map::new();
+    let mut limits = serde_json::Map::new();
+    if let Some(cpu) = r.get("cpu").and_then(|v| v.as_str()) { requests.insert("cpu".into(), cpu.into()); }
+    if let Some(mem) = r.get("memory").and_then(|v| v.as_str()) {
+        requests.insert("memory".into(), mem.into());
+        limits.insert("memory".into(), mem.into());
+    }
+    let gpus = gpu_count(Some(r));
+    if gpus > 0 { limits.insert(GPU_RESOURCE.into(), gpus.to_string().into()); }
+    serde_json::json!({"requests": requests, "limits": limits})
+}
+
+/// "2x a100" for a Deployment (as JSON) whose containers request GPUs; the type comes from
+/// the GPU node affinity or node selector, if any.
+#[cfg(feature = "kubernetes")]
+fn gpu_summary(deployment: &Json) -> Option<String> {
+    let pod = &deployment["spec"]["template"]["spec"];
+    let count: u64 = pod["containers"].as_array()?.iter()
+        .filter_map(|c| c["resources"]["limits"][GPU_RESOURCE].as_str().and_then(|s| s.parse::<u64>().ok()))
+        .sum();
+    if count == 0 { return None; }
+    let from_affinity = pod["affinity"]["nodeAffinity"]["requiredDuringSchedulingIgnoredDuringExecution"]["nodeSelectorTerms"]
+        .as_array().into_iter().flatten()
+        .flat_map(|t| t["matchExpressions"].as_array().cloned().unwrap_or_default())
+        .find(|e| e["key"].as_str().is_some_and(|k| k.contains("gpu") || k.contains("accelerator")))
+        .and_then(|e| e["values"].as_array().map(|v| v.iter().filter_map(|x| x.as_str()).collect::<Vec<_>>().join("|")));
+    let from_selector = pod["nodeSelector"].as_object()
+        .and_then(|m| m.iter().find(|(k, _)| k.contains("gpu") || k.contains("accelerator")).and_then(|(_, v)| v.as_str().map(|s| s.to_string())));
+    Some(match from_affinity.or(from_selector) {
+        Some(t) => format!("{}x {}", count, t),
+        None => format!("{}x gpu", count),
+    })
 }
 
+/// The HPA keeps its maximum when scaled to `replicas`, unless that is below the new minimum.
+#[cfg(feature = "kubernetes")]
+fn hpa_max(hpa: &HorizontalPodAutoscaler, replicas: u32) -> u32 {
+    (hpa.spec.as_ref().map(|s| s.max_replicas).unwrap_or(0).max(0) as u32).max(replicas)
+}
+
+#[cfg(feature = "kubernetes")]
+fn hpa_bounds_patch(hpa: &HorizontalPodAutoscaler, replicas: u32) -> Json {
+    serde_json::json!({"spec": {"minReplicas": replicas as i32, "maxReplicas": hpa_max(hpa, replicas) as i32}})
+}
+
+/// Drop server-managed bookkeeping so diffs only show meaningful changes.
+#[cfg(feature = "kubernetes")]
+fn strip_server_fields(mut v: Json) -> Json {
+    if let Some(meta) = v.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+        for k in ["managedFields", "resourceVersion", "generation", "uid", "creationTimestamp"] { meta.remove(k); }
+    }
+    if let Some(obj) = v.as_object_mut() { obj.remove("status"); }
+    v
+}
+
+#[cfg(feature = "kubernetes")]
+pub(super) fn hpa_name(name: &str) -> String {
+    format!("{}-hpa", name) // same convention as the Helm chart
+}
+
+#[cfg(feature = "kubernetes")]
+pub(super) fn hpa_manifest(hpa_name: &str, target: &str, spec: &crate::orchestrator::AutoscalerSpec) -> Json {
+    let resource = |res: &str, pct: u32| serde_json::json!({
+        "type": "Resource",
+        "resource": {"name": res, "target": {"type": "Utilization", "averageUtilization": pct}}
+    });
+    let mut metrics: Vec<Json> = Vec::new();
+    if let Some(p) = spec.cpu_utilization { metrics.push(resource("cpu", p)); }
+    if let Some(p) = spec.memory_utilization { metrics.push(resource("memory", p)); }
+    metrics.extend(spec.metrics.iter().cloned());
+    serde_json::json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {"name": hpa_name, "labels": {"app": target}},
+        "spec": {
+            "scaleTargetRef": {"apiVersion": "apps/v1", "kind": "Deployment", "name": target},
+            "minReplicas": spec.min_replicas,
+            "maxReplicas": spec.max_replicas,
+            "metrics": metrics
+        }
+    })
+}
+
+#[cfg(all(test, feature = "kubernetes"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hpa_manifest_metrics() {
+        let spec: crate::orchestrator::AutoscalerSpec = serde_json::from_value(serde_json::json!({
+            "min_replicas": 2, "max_replicas": 8, "cpu_utilization": 70,
+            "metrics": [{"type": "Pods", "pods": {"metric": {"name": "queue_depth"}, "target": {"type": "AverageValue", "averageValue": "5"}}}]
+        })).unwrap();
+        let m = hpa_manifest("w-hpa", "w", &spec);
+        assert_eq!(m["spec"]["scaleTargetRef"]["name"], "w");
+        assert_eq!(m["spec"]["maxReplicas"], 8);
+        let metrics = m["spec"]["metrics"].as_array().unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0]["resource"]["name"], "cpu");
+        assert_eq!(metrics[1]["type"], "Pods");
+
+        // scaling keeps a larger maximum, which is what the pool is billed at
+        let hpa: HorizontalPodAutoscaler = serde_json::from_value(m).unwrap();
+        assert_eq!(hpa_bounds_patch(&hpa, 3), serde_json::json!({"spec": {"minReplicas": 3, "maxReplicas": 8}}));
+        assert_eq!((hpa_max(&hpa, 3), hpa_max(&hpa, 12)), (8, 12));
+    }
+
+    #[test]
+    fn test_gpu_deployment_manifest() {
+        let m = deployment_manifest("w", r#"{"image": "vllm:1", "resources": {"cpu": "4", "memory": "16Gi", "gpu": "2", "gpu_type": "NVIDIA-A100-SXM4-80GB"}, "node_selector": {"pool": "gpu"}}"#);
+        let pod = &m["spec"]["template"]["spec"];
+        assert_eq!(pod["containers"][0]["resources"]["limits"]["nvidia.com/gpu"], "2");
+        assert_eq!(pod["containers"][0]["resources"]["requests"]["cpu"], "4");
+        assert_eq!(pod["nodeSelector"]["pool"], "gpu");
+        assert_eq!(pod["tolerations"][0]["key"], "nvidia.com/gpu");
+        assert_eq!(pod["affinity"]["nodeAffinity"]["requiredDuringSchedulingIgnoredDuringExecution"]["nodeSelectorTerms"][0]["matchExpressions"][0]["values"][0], "NVIDIA-A100-SXM4-80GB");
+        assert_eq!(gpu_summary(&m).as_deref(), Some("2x NVIDIA-A100-SXM4-80GB"));
+
+        let cpu_only = deployment_manifest("w", r#"{"image": "w:1", "resources": {"cpu": "1", "memory": "1Gi"}}"#);
+        assert!(cpu_only["spec"]["template"]["spec"].get("tolerations").is_none());
+        assert_eq!(gpu_summary(&cpu_only), None);
+    }
+
+    #[test]
+    fn test_job_manifest_and_progress() {
+        let spec: crate::orchestrator::JobSpec = serde_json::from_value(serde_json::json!({
+            "image": "gen:1", "command": ["gen", "--all"], "parallelism": 4, "completions": 20, "ttl_seconds_after_finished": 600,
+            "resources": {"cpu": "2", "memory": "4Gi"}
+        })).unwrap();
+        let mut job = job_manifest("bulk-tests", &spec);
+        assert_eq!(job["spec"]["parallelism"], 4);
+        assert_eq!(job["spec"]["ttlSecondsAfterFinished"], 600);
+        assert_eq!(job["spec"]["template"]["spec"]["restartPolicy"], "Never");
+        assert_eq!(job["spec"]["template"]["spec"]["containers"][0]["command"][1], "--all");
+
+        job["status"] = serde_json::json!({"active": 4, "succeeded": 6, "startTime": "2025-01-01T00:00:00Z"});
+        let p = job_progress(&job, 1735689600 + 90);
+        assert_eq!((p.state.as_str(), p.succeeded, p.elapsed_seconds), ("running", 6, Some(90)));
+        assert_eq!(p.resources.unwrap().memory, "4Gi");
+
+        job["status"]["conditions"] = serde_json::json!([{"type": "Failed", "status": "True", "reason": "BackoffLimitExceeded", "lastTransitionTime": "2025-01-01T00:01:00Z"}]);
+        let p = job_progress(&job, 1735689600 + 900);
+        assert_eq!((p.state.as_str(), p.elapsed_seconds, p.message.as_deref()), ("failed", Some(60), Some("BackoffLimitExceeded")));
+    }
+
+    #[test]
+    fn test_pod_summary_and_events() {
+        let pod = serde_json::json!({
+            "metadata": {"name": "w-5d9f-abc"},
+            "status": {"phase": "Running", "containerStatuses": [
+                {"ready": false, "restartCount": 4, "state": {"waiting": {"reason": "CrashLoopBackOff"}}, "lastState": {"terminated": {"reason": "OOMKilled"}}}
+            ]}
+        });
+        let p = pod_summary(&pod);
+        assert_eq!((p.ready, p.restarts, p.reason.as_deref()), (false, 4, Some("CrashLoopBackOff")));
+
+        let ev = |obj: &str, ts: &str| serde_json::json!({"type": "Warning", "reason": "BackOff", "message": "m", "involvedObject": {"kind": "Pod", "name": obj}, "lastTimestamp": ts});
+        let all = [ev("w-5d9f-abc", "2025-01-01T00:00:02Z"), ev("other-1", "2025-01-01T00:00:03Z"), ev("w", "2025-01-01T00:00:01Z"), ev("w-5d9f-xyz", "2025-01-01T00:00:04Z")];
+        let events = pool_events(&all, "w", 2);
+        assert_eq!(events.iter().map(|e| e.object.as_str()).collect::<Vec<_>>(), ["Pod/w-5d9f-abc", "Pod/w-5d9f-xyz"]);
+    }
+
+    #[test]
+    fn test_parse_clusters() {
+        let c = parse_clusters("staging=kind-staging, prod=/etc/kube/prod.yaml#prod-admin,dr=/etc/kube/dr.yaml").unwrap();
+        assert_eq!(c["staging"], ClusterRef { kubeconfig: None, context: Some("kind-staging".into()) });
+        assert_eq!(c["prod"], ClusterRef { kubeconfig: Some("/etc/kube/prod.yaml".into()), context: Some("prod-admin".into()) });
+        assert_eq!(c["dr"].context, None);
+        assert!(parse_clusters("nope").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::orchestrator::{Capabilities, Orchestrator, OrchestratorContext, Preview};
+use crate::util::now_ms;
+
+const TICK: Duration = Duration::from_secs(2);
+const STABLE_MS: u128 = 60_000;     // a worker up this long resets its crash backoff
+const MAX_BACKOFF_MS: u128 = 60_000;
+
+/// How to start one worker process of a pool.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WorkerSpec {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl WorkerSpec {
+    /// Parse a whitespace-separated command line such as `python -m worker --port 0`.
+    pub fn from_command_line(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace().map(|s| s.to_string());
+        let command = parts.next()?;
+        Some(Self { command, args: parts.collect(), env: BTreeMap::new() })
+    }
+}
+
+struct Worker {
+    child: Option<Child>,
+    pid: Option<u32>,
+    started_ms: u128,
+    restarts: u32,
+    failures: u32, // consecutive exits before the worker became stable
+    restart_at_ms: Option<u128>,
+    last_exit: Option<String>,
+}
+
+struct Pool {
+    spec: WorkerSpec,
+    model: Option<String>,
+    desired: u32,
+    workers: Vec<Worker>,
+}
+
+impl Pool {
+    fn spawn(&self, key: &str, index: usize) -> std::io::Result<Child> {
+        let mut cmd = Command::new(&self.spec.command);
+        cmd.args(&self.spec.args)
+            .envs(&self.spec.env)
+            .env("ECTUSR2_POOL", key)
+            .env("ECTUSR2_WORKER_INDEX", index.to_string())
+            // stdout carries the MCP protocol; workers must not write to it
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+        if let Some(m) = &self.model { cmd.env("ECTUSR2_MODEL", m); }
+        cmd.spawn()
+    }
+
+    /// Start or stop workers until `desired` exist. Spawn failures are returned to the caller.
+    fn resize(&mut self, key: &str) -> Result<()> {
+        while self.workers.len() > self.desired as usize {
+            if let Some(mut w) = self.workers.pop() {
+                if let Some(c) = w.child.as_mut() { let _ = c.start_kill(); }
+            }
+        }
+        while self.workers.len() < self.desired as usize {
+            let index = self.workers.len();
+            let child = self.spawn(key, index).map_err(|e| anyhow::anyhow!("failed to start `{}` for {}: {}", self.spec.command, key, e))?;
+            self.workers.push(Worker { pid: child.id(), child: Some(child), started_ms: now_ms(), restarts: 0, failures: 0, restart_at_ms: None, last_exit: None });
+        }
+        Ok(())
+    }
+
+    /// Reap exited workers and restart them once their backoff has elapsed.
+    fn supervise(&mut self, key: &str, now: u128) {
+        for index in 0..self.workers.len() {
+            let w = &mut self.workers[index];
+            if let Some(child) = w.child.as_mut() {
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        w.failures = if now.saturating_sub(w.started_ms) < STABLE_MS { w.failures + 1 } else { 0 };
+                        w.restart_at_ms = Some(now + backoff_ms(w.failures));
+                        w.last_exit = Some(status.to_string());
+                        w.child = None;
+                        w.pid = None;
+                        warn!(pool = key, index, %status, "local worker exited");
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!(pool = key, index, error = %e, "failed to poll local worker"),
+                }
+                continue;
+            }
+            if w.restart_at_ms.is_some_and(|t| t <= now) {
+                match self.spawn(key, index) {
+                    Ok(child) => {
+                        let w = &mut self.workers[index];
+                        w.pid = child.id();
+                        w.child = Some(child);
+                        w.started_ms = now;
+                        w.restarts += 1;
+                        w.restart_at_ms = None;
+                        info!(pool = key, index, restarts = w.restarts, "restarted local worker");
+                    }
+                    Err(e) => {
+                        let w = &mut self.workers[index];
+                        w.failures += 1;
+                        w.restart_at_ms = Some(now + backoff_ms(w.failures));
+                        w.last_exit = Some(format!("spawn failed: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    fn kill_all(&mut self) {
+        for w in self.workers.iter_mut() {
+            if let Some(c) = w.child.as_mut() { let _ = c.start_kill(); }
+        }
+        self.workers.clear();
+    }
+
+    fn running(&self) -> usize {
+        self.workers.iter().filter(|w| w.child.is_some()).count()
+    }
+
+    fn to_json(&self) -> Value {
+        json!({"command": self.spec.command, "args": self.spec.args, "env": self.spec.env, "model": self.model, "replicas": self.desired})
+    }
+
+    fn describe(&self, key: &str) -> String {
+        let restarts: u32 = self.workers.iter().map(|w| w.restarts).sum();
+        let pids: Vec<String> = self.workers.iter().filter_map(|w| w.pid.map(|p| p.to_string())).collect();
+        let mut out = format!("{}: {}/{} running, restarts {}, pids [{}]", key, self.running(), self.desired, restarts, pids.join(", "));
+        if let Some(exit) = self.workers.iter().rev().find_map(|w| w.last_exit.as_deref()) {
+            out.push_str(&format!(", last exit: {}", exit));
+        }
+        out
+    }
+}
+
+fn backoff_ms(failures: u32) -> u128 {
+    (1000u128 << failures.min(6)).min(MAX_BACKOFF_MS)
+}
+
+fn pool_key(ctx: &OrchestratorContext) -> String {
+    format!("{}/{}", ctx.namespace.as_deref().unwrap_or("default"), ctx.name.as_deref().unwrap_or("ectusr2-workers"))
+}
+
+/// Runs pools as supervised child processes of the server; no cluster required.
+#[derive(Clone)]
+pub struct LocalOrchestrator {
+    default_spec: Option<WorkerSpec>,
+    pools: Arc<Mutex<HashMap<String, Pool>>>,
+}
+
+impl LocalOrchestrator {
+    pub fn new(default_command: Option<&str>) -> Self {
+        Self { default_spec: default_command.and_then(WorkerSpec::from_command_line), pools: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    fn parse_spec(&self, spec: &str) -> Result<(WorkerSpec, u32)> {
+        let v: Value = serde_json::from_str(spec).unwrap_or(json!({}));
+        let replicas = v.get("replicas").and_then(|r| r.as_u64()).unwrap_or(1) as u32;
+        let worker = match v.get("command") {
+            Some(_) => serde_json::from_value(v)?,
+            None => self.default_spec.clone().ok_or_else(|| anyhow::anyhow!("spec has no `command` and LOCAL_WORKER_COMMAND is not set"))?,
+        };
+        Ok((worker, replicas))
+    }
+
+    fn missing(&self, key: &str) -> anyhow::Error {
+        anyhow::anyhow!("pool {} not found; create it with pool_ensure or set LOCAL_WORKER_COMMAND", key)
+    }
+}
+
+#[async_trait]
+impl Orchestrator for LocalOrchestrator {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { backend: "local", delete_pool: true, dry_run: true, ..Default::default() }
+    }
+    async fn status(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let pools = self.pools.lock().await;
+        if ctx.name.is_some() {
+            let key = pool_key(ctx);
+            return Ok(pools.get(&key).map(|p| p.describe(&key)).unwrap_or_else(|| format!("{}: not found", key)));
+        }
+        let prefix = format!("{}/", ctx.namespace.as_deref().unwrap_or("default"));
+        let mut lines: Vec<String> = pools.iter().filter(|(k, _)| k.starts_with(&prefix)).map(|(k, p)| p.describe(k)).collect();
+        lines.sort();
+        if lines.is_empty() { return Ok(format!("local: no pools in {}", prefix.trim_end_matches('/'))); }
+        Ok(lines.join("\n"))
+    }
+    async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
+        let key = pool_key(ctx);
+        let mut pools = self.pools.lock().await;
+        if !pools.contains_key(&key) {
+            let spec = self.default_spec.clone().ok_or_else(|| self.missing(&key))?;
+            pools.insert(key.clone(), Pool { spec, model: ctx.model.clone(), desired: 0, workers: Vec::new() });
+        }
+        let pool = pools.get_mut(&key).expect("pool inserted above");
+        pool.desired = replicas;
+        pool.resize(&key)?;
+        Ok(format!("scaled {} to {} ({} running)", key, replicas, pool.running()))
+    }
+    async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String> {
+        let key = pool_key(ctx);
+        let (worker, replicas) = self.parse_spec(spec)?;
+        let mut pools = self.pools.lock().await;
+        let pool = pools.entry(key.clone()).or_insert_with(|| Pool { spec: worker.clone(), model: ctx.model.clone(), desired: 0, workers: Vec::new() });
+        if pool.spec != worker || pool.model != ctx.model {
+            // Spec changed: replace every worker so none keeps running the old command
+            pool.kill_all();
+            pool.spec = worker;
+            pool.model = ctx.model.clone();
+        }
+        pool.desired = replicas;
+        if let Err(e) = pool.resize(&key) {
+            if pool.running() == 0 { pools.remove(&key); }
+            return Err(e);
+        }
+        Ok(format!("ensured pool {} (replicas {}, {} running)", key, replicas, pool.running()))
+    }
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String> {
+        let key = pool_key(ctx);
+        let mut pool = self.pools.lock().await.remove(&key).ok_or_else(|| anyhow::anyhow!("pool {} not found", key))?;
+        let stopped = pool.running();
+        pool.kill_all();
+        Ok(format!("deleted pool {} ({} workers stopped)", key, stopped))
+    }
+    async fn preview_scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<Preview> {
+        let key = pool_key(ctx);
+        let pools = self.pools.lock().await;
+        let (live, spec) = match pools.get(&key) {
+            Some(p) => (Some(p.to_json()), p.to_json()),
+            None => {
+                let spec = self.default_spec.clone().ok_or_else(|| self.missing(&key))?;
+                (None, json!({"command": spec.command, "args": spec.args, "env": spec.env, "model": ctx.model, "replicas": 0}))
+            }
+        };
+        let mut proposed = spec;
+        proposed["replicas"] = json!(replicas);
+        let current_replicas = live.as_ref().and_then(|l| l["replicas"].as_u64()).unwrap_or(0) as u32;
+        Ok(Preview { live, proposed, current_replicas })
+    }
+    async fn preview_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<Preview> {
+        let key = pool_key(ctx);
+        let (worker, replicas) = self.parse_spec(spec)?;
+        let live = self.pools.lock().await.get(&key).map(|p| p.to_json());
+        let current_replicas = live.as_ref().and_then(|l| l["replicas"].as_u64()).unwrap_or(0) as u32;
+        let proposed = json!({"command": worker.command, "args": worker.args, "env": worker.env, "model": ctx.model, "replicas": replicas});
+        Ok(Preview { live, proposed, current_replicas })
+    }
+}
+
+/// Background loop restarting crashed workers and topping pools back up to their desired size.
+pub async fn supervise(orch: LocalOrchestrator) {
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tick.tick().await;
+        let mut pools = orch.pools.lock().await;
+        for (key, pool) in pools.iter_mut() {
+            pool.supervise(key, now_ms());
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn ctx(name: &str) -> OrchestratorContext {
+        OrchestratorContext { namespace: Some("test".into()), name: Some(name.into()), model: None, cluster: None }
+    }
+
+    #[tokio::test]
+    async fn test_scale_and_delete_processes() {
+        let orch = LocalOrchestrator::new(Some("sleep 30"));
+        orch.scale(&ctx("a"), 2).await.unwrap();
+        assert!(orch.status(&ctx("a")).await.unwrap().contains("2/2 running"));
+        orch.scale(&ctx("a"), 1).await.unwrap();
+        assert!(orch.status(&ctx("a")).await.unwrap().contains("1/1 running"));
+        let preview = orch.preview_scale(&ctx("a"), 3).await.unwrap();
+        assert_eq!(preview.current_replicas, 1);
+        assert_eq!(preview.proposed["replicas"], 3);
+        assert!(orch.delete_pool(&ctx("a")).await.unwrap().contains("1 workers stopped"));
+        assert!(orch.status(&ctx("a")).await.unwrap().contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_crashed_worker_is_restarted() {
+        let orch = LocalOrchestrator::new(None);
+        assert!(orch.scale(&ctx("b"), 1).await.is_err());
+        orch.ensure_pool(&ctx("b"), r#"{"command":"sh","args":["-c","exit 3"],"replicas":1}"#).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let mut pools = orch.pools.lock().await;
+        let pool = pools.get_mut("test/b").unwrap();
+        let now = now_ms();
+        pool.supervise("test/b", now);
+        assert_eq!(pool.running(), 0);
+        assert!(pool.workers[0].last_exit.as_deref().unwrap().contains('3'));
+        pool.supervise("test/b", now + backoff_ms(1));
+        assert_eq!(pool.workers[0].restarts, 1);
+        pool.kill_all();
+    }
+}
@@ -1,43 +1,375 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::EctusError;
 
 pub struct OrchestratorContext {
     pub namespace: Option<String>,
     pub name: Option<String>, // deployment/job name for k8s or similar
     pub model: Option<String>,
+    pub cluster: Option<String>, // named cluster (kubernetes); None = default cluster
 }
 
 #[async_trait]
 pub trait Orchestrator: Send + Sync {
+    /// What this backend can do; callers check it before relying on optional operations.
+    fn capabilities(&self) -> Capabilities;
     async fn status(&self, ctx: &OrchestratorContext) -> Result<String>;
     async fn scale(&self, ctx: &OrchestratorContext, replicas: u32) -> Result<String>;
     async fn ensure_pool(&self, ctx: &OrchestratorContext, spec: &str) -> Result<String>;
+    async fn delete_pool(&self, ctx: &OrchestratorContext) -> Result<String>;
+    /// Server-side dry-run of `scale`: the live object and what it would become.
+    async fn preview_scale(&self, _ctx: &OrchestratorContext, _replicas: u32) -> Result<Preview> {
+        anyhow::bail!("dry-run is not supported by this backend")
+    }
+    /// Server-side dry-run of `ensure_pool`.
+    async fn preview_pool(&self, _ctx: &OrchestratorContext, _spec: &str) -> Result<Preview> {
+        anyhow::bail!("dry-run is not supported by this backend")
+    }
+    /// The most replicas the pool's autoscaler may run once `scale` sets it to `replicas`; None without one.
+    async fn autoscaled_max(&self, _ctx: &OrchestratorContext, _replicas: u32) -> Result<Option<u32>> {
+        Ok(None)
+    }
+    async fn ensure_autoscaler(&self, _ctx: &OrchestratorContext, _spec: &AutoscalerSpec) -> Result<String> {
+        anyhow::bail!("autoscaling is not supported by this backend")
+    }
+    async fn delete_autoscaler(&self, _ctx: &OrchestratorContext) -> Result<String> {
+        anyhow::bail!("autoscaling is not supported by this backend")
+    }
+    /// Start a run-to-completion batch job named `ctx.name`.
+    async fn submit_job(&self, _ctx: &OrchestratorContext, _spec: &JobSpec) -> Result<String> {
+        anyhow::bail!("batch jobs are not supported by this backend")
+    }
+    async fn job_status(&self, _ctx: &OrchestratorContext) -> Result<JobProgress> {
+        anyhow::bail!("batch jobs are not supported by this backend")
+    }
+    /// Delete a job and its pods, finished or not.
+    async fn delete_job(&self, _ctx: &OrchestratorContext) -> Result<String> {
+        anyhow::bail!("batch jobs are not supported by this backend")
+    }
+    /// Recent logs of the pool's pods (unhealthy first) and related events, unredacted.
+    async fn pool_logs(&self, _ctx: &OrchestratorContext, _query: &LogQuery) -> Result<PoolDiagnostics> {
+        anyhow::bail!("logs are not supported by this backend")
+    }
 }
 
-pub struct LocalOrchestrator;
+/// Operations a backend supports beyond status/scale/ensure_pool.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Capabilities {
+    pub backend: &'static str,
+    pub delete_pool: bool,
+    pub dry_run: bool,
+    pub autoscaling: bool,
+    pub batch: bool,
+    pub logs: bool,
+}
 
-#[async_trait]
-impl Orchestrator for LocalOrchestrator {
-    async fn status(&self, _ctx: &OrchestratorContext) -> Result<String> {
-        Ok("local: ok".into())
+impl Capabilities {
+    /// Short human-readable form, e.g. `scale, ensure_pool, delete_pool, dry_run`.
+    pub fn summary(&self) -> String {
+        let mut ops = vec!["status", "scale", "ensure_pool"];
+        for (op, on) in [("delete_pool", self.delete_pool), ("dry_run", self.dry_run), ("autoscaling", self.autoscaling), ("batch", self.batch), ("logs", self.logs)] {
+            if on { ops.push(op); }
+        }
+        ops.join(", ")
     }
-    async fn scale(&self, _ctx: &OrchestratorContext, replicas: u32) -> Result<String> {
-        Ok(format!("scaled to {} (local stub)", replicas))
+}
+
+/// Result of a dry-run: the live object (if any) and the object the operation would produce.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub live: Option<serde_json::Value>,
+    pub proposed: serde_json::Value,
+    pub current_replicas: u32,
+}
+
+/// Autoscaling bounds and targets for a pool (maps to an autoscaling/v2 HPA on Kubernetes).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoscalerSpec {
+    pub min_replicas: u32,
+    pub max_replicas: u32,
+    #[serde(default)]
+    pub cpu_utilization: Option<u32>,    // target average utilization, percent
+    #[serde(default)]
+    pub memory_utilization: Option<u32>, // target average utilization, percent
+    /// Additional metric specs (Pods/Object/External) passed through verbatim
+    #[serde(default)]
+    pub metrics: Vec<serde_json::Value>,
+}
+
+/// A batch job: how many pods run at once, how many must succeed, and the pod itself.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobSpec {
+    #[serde(default = "one")]
+    pub parallelism: u32,
+    #[serde(default = "one")]
+    pub completions: u32,
+    #[serde(default = "default_backoff_limit")]
+    pub backoff_limit: u32,
+    #[serde(default)]
+    pub ttl_seconds_after_finished: Option<u32>,
+    #[serde(default)]
+    pub active_deadline_seconds: Option<u64>,
+    /// Container and scheduling fields as in a `pool_ensure` spec (image, command, args, env, resources, ...)
+    #[serde(flatten)]
+    pub pod: serde_json::Map<String, serde_json::Value>,
+}
+
+fn one() -> u32 { 1 }
+fn default_backoff_limit() -> u32 { 3 }
+
+impl JobSpec {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.parallelism == 0 || self.completions == 0 { return Err("parallelism and completions must be at least 1".into()); }
+        if !self.pod.get("image").is_some_and(|i| i.is_string()) { return Err("job spec requires `image`".into()); }
+        Ok(())
+    }
+}
+
+/// Which logs to fetch for a pool; `clamp` bounds what a caller may ask for.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogQuery {
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default = "default_tail_lines")]
+    pub tail_lines: i64,
+    #[serde(default)]
+    pub since_seconds: Option<i64>,
+    /// Logs of the previous (crashed) container instance
+    #[serde(default)]
+    pub previous: bool,
+    #[serde(default = "default_log_bytes")]
+    pub max_bytes: usize, // total across pods
+    #[serde(default = "default_log_pods")]
+    pub max_pods: usize,
+    #[serde(default = "default_true")]
+    pub events: bool,
+}
+
+fn default_tail_lines() -> i64 { 100 }
+fn default_log_bytes() -> usize { 64 * 1024 }
+fn default_log_pods() -> usize { 3 }
+fn default_true() -> bool { true }
+
+const MAX_LOG_BYTES: usize = 256 * 1024;
+const MAX_TAIL_LINES: i64 = 2000;
+const MAX_LOG_PODS: usize = 20;
+
+impl LogQuery {
+    pub fn clamp(mut self) -> Self {
+        self.tail_lines = self.tail_lines.clamp(1, MAX_TAIL_LINES);
+        self.max_bytes = self.max_bytes.clamp(1024, MAX_LOG_BYTES);
+        self.max_pods = self.max_pods.clamp(1, MAX_LOG_PODS);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PodLogs {
+    pub pod: String,
+    pub phase: String,
+    pub ready: bool,
+    pub restarts: u32,
+    /// Why the container is not running or last stopped (e.g. CrashLoopBackOff, OOMKilled)
+    pub reason: Option<String>,
+    pub logs: String,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PoolEvent {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub reason: String,
+    pub object: String,
+    pub message: String,
+    pub count: u32,
+    pub last_seen: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PoolDiagnostics {
+    pub pods: Vec<PodLogs>,
+    pub events: Vec<PoolEvent>,
+}
+
+/// Progress of a batch job as reported by the backend.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobProgress {
+    pub name: String,
+    pub state: String, // pending | running | complete | failed
+    pub active: u32,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub parallelism: u32,
+    pub completions: u32,
+    pub elapsed_seconds: Option<u64>,
+    pub message: Option<String>,
+    /// Per-pod resources, for costing
+    #[serde(skip)]
+    pub resources: Option<crate::budget::Resources>,
+}
+
+impl JobProgress {
+    pub fn finished(&self) -> bool {
+        self.state == "complete" || self.state == "failed"
     }
-    async fn ensure_pool(&self, _ctx: &OrchestratorContext, _spec: &str) -> Result<String> {
-        Ok("pool ensured (local stub)".into())
+}
+
+impl AutoscalerSpec {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.min_replicas == 0 { return Err("min_replicas must be at least 1".into()); }
+        if self.max_replicas < self.min_replicas {
+            return Err(format!("max_replicas ({}) must be >= min_replicas ({})", self.max_replicas, self.min_replicas));
+        }
+        for (what, v) in [("cpu_utilization", self.cpu_utilization), ("memory_utilization", self.memory_utilization)] {
+            if v.is_some_and(|p| p == 0 || p > 100) { return Err(format!("{} must be within 1..=100", what)); }
+        }
+        if let Some(m) = self.metrics.iter().find(|m| m.get("type").and_then(|t| t.as_str()).is_none()) {
+            return Err(format!("metric spec without `type`: {}", m));
+        }
+        Ok(())
     }
 }
 
+pub mod idle;
+pub mod local;
+pub mod policy;
+#[cfg(feature = "kubernetes")]
+pub mod kubernetes;
 #[cfg(feature = "kubernetes")]
-mod kubernetes;
+pub mod operator;
+#[cfg(feature = "aws")]
+pub mod ecs;
+#[cfg(feature = "gcp")]
+pub mod gcp;
+#[cfg(feature = "azure")]
+pub mod azure;
+#[cfg(feature = "local")]
+pub mod docker;
+#[cfg(all(test, any(feature = "aws", feature = "gcp", feature = "azure", feature = "local")))]
+mod fake_http;
+
+/// Every backend this crate knows about: canonical name, aliases, cargo feature that compiles it in.
+const BACKENDS: &[(&str, &[&str], Option<&str>)] = &[
+    ("local", &[], None),
+    ("docker", &["podman"], Some("local")),
+    ("kubernetes", &["k8s"], Some("kubernetes")),
+    ("ecs", &["aws"], Some("aws")),
+    ("cloud_run", &["cloudrun", "gcp"], Some("gcp")),
+    ("mig", &["gce"], Some("gcp")),
+    ("aca", &["containerapps", "azure"], Some("azure")),
+    ("vmss", &[], Some("azure")),
+];
+
+fn compiled(name: &str) -> bool {
+    name == "local"
+        || (cfg!(feature = "local") && name == "docker")
+        || (cfg!(feature = "kubernetes") && name == "kubernetes")
+        || (cfg!(feature = "aws") && name == "ecs")
+        || (cfg!(feature = "gcp") && (name == "cloud_run" || name == "mig"))
+        || (cfg!(feature = "azure") && (name == "aca" || name == "vmss"))
+}
+
+/// The first of `template`'s `containers`, created as `default` when the list is missing, empty or
+/// holds something other than an object, as a template fetched from a cloud API may.
+#[cfg(any(feature = "gcp", feature = "azure"))]
+pub(crate) fn first_container(template: &mut serde_json::Value, default: serde_json::Value) -> &mut serde_json::Value {
+    if !template.is_object() { *template = serde_json::json!({}); }
+    let containers = &mut template["containers"];
+    if !containers.is_array() { *containers = serde_json::json!([]); }
+    if let Some(list) = containers.as_array_mut() {
+        match list.first_mut() {
+            Some(first) if first.is_object() => {}
+            Some(first) => *first = default,
+            None => list.push(default),
+        }
+    }
+    &mut containers[0]
+}
 
-pub fn new_backend(name: &str) -> Box<dyn Orchestrator> {
-    match name {
+/// Backends compiled into this binary.
+pub fn available_backends() -> Vec<&'static str> {
+    BACKENDS.iter().map(|b| b.0).filter(|n| compiled(n)).collect()
+}
+
+/// Preferred backend when none is configured: Kubernetes if compiled in, otherwise local.
+pub fn default_backend() -> &'static str {
+    if compiled("kubernetes") { "kubernetes" } else { "local" }
+}
+
+fn find_backend(name: &str) -> Option<&'static (&'static str, &'static [&'static str], Option<&'static str>)> {
+    BACKENDS.iter().find(|(n, aliases, _)| n.eq_ignore_ascii_case(name) || aliases.iter().any(|a| a.eq_ignore_ascii_case(name)))
+}
+
+/// Canonical name for a backend name or alias, whether or not it is compiled in.
+pub fn canonical(name: &str) -> Option<&'static str> {
+    find_backend(name).map(|b| b.0)
+}
+
+/// Map a configured backend name (or alias) to its canonical name, failing on unknown or
+/// not-compiled backends instead of silently falling back.
+pub fn resolve(name: &str) -> std::result::Result<&'static str, EctusError> {
+    let found = find_backend(name);
+    let available = available_backends().join(", ");
+    match found {
+        Some((n, _, _)) if compiled(n) => Ok(n),
+        Some((n, _, feature)) => Err(EctusError::Backend(format!(
+            "orchestrator backend `{}` is not compiled into this binary; rebuild with `--features {}` (available: {})",
+            n, feature.unwrap_or(n), available
+        ))),
+        None => Err(EctusError::Backend(format!("unknown orchestrator backend `{}` (available: {})", name, available))),
+    }
+}
+
+pub fn new_backend(state: &crate::state::AppState, name: &str) -> std::result::Result<Box<dyn Orchestrator>, EctusError> {
+    match resolve(name)? {
         #[cfg(feature = "kubernetes")]
-        s if s.eq_ignore_ascii_case("kubernetes") || s.eq_ignore_ascii_case("k8s") => {
-            Box::new(kubernetes::KubeOrchestrator {})
+        "kubernetes" => Ok(Box::new(state.kube.clone())),
+        #[cfg(feature = "aws")]
+        "ecs" => Ok(Box::new(state.ecs.clone())),
+        #[cfg(feature = "gcp")]
+        "cloud_run" => Ok(Box::new(state.gcp.with_kind(gcp::GcpKind::CloudRun))),
+        #[cfg(feature = "gcp")]
+        "mig" => Ok(Box::new(state.gcp.with_kind(gcp::GcpKind::Mig))),
+        #[cfg(feature = "azure")]
+        "aca" => Ok(Box::new(state.azure.with_kind(azure::AzureKind::ContainerApps))),
+        #[cfg(feature = "azure")]
+        "vmss" => Ok(Box::new(state.azure.with_kind(azure::AzureKind::Vmss))),
+        #[cfg(feature = "local")]
+        "docker" => Ok(Box::new(state.docker.clone())),
+        "local" => Ok(Box::new(state.local.clone())),
+        other => Err(EctusError::Backend(format!("orchestrator backend `{}` has no constructor", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_backends() {
+        assert_eq!(resolve("local").unwrap(), "local");
+        assert_eq!(resolve("LOCAL").unwrap(), "local");
+        let err = resolve("kubernets").unwrap_err().to_string();
+        assert!(err.contains("unknown orchestrator backend `kubernets`") && err.contains("available: local"));
+        if cfg!(feature = "kubernetes") {
+            assert_eq!(resolve("k8s").unwrap(), "kubernetes");
+        } else {
+            assert!(resolve("k8s").unwrap_err().to_string().contains("--features kubernetes"));
         }
-        _ => Box::new(LocalOrchestrator),
+    }
+
+    #[test]
+    fn test_autoscaler_spec_validate() {
+        let spec: AutoscalerSpec = serde_json::from_value(serde_json::json!({"min_replicas": 2, "max_replicas": 10, "cpu_utilization": 70})).unwrap();
+        assert!(spec.validate().is_ok());
+        let bad = AutoscalerSpec { min_replicas: 5, max_replicas: 2, ..spec.clone() };
+        assert!(bad.validate().is_err());
+        let bad = AutoscalerSpec { min_replicas: 0, ..spec.clone() };
+        assert!(bad.validate().is_err());
+        let bad = AutoscalerSpec { memory_utilization: Some(150), ..spec };
+        assert!(bad.validate().is_err());
     }
 }
@@ -0,0 +1,585 @@
+//! Operator mode: `ModelPool` custom resources reconciled into Deployments and HPAs.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::watcher;
+use kube::{CustomResource, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::budget::hierarchy::POOL_LABEL;
+use crate::budget::ledger::{month_bounds, Ledger};
+use crate::budget::money::{self, dec, hours_from_ms, Decimal};
+use crate::budget::reservations::{self, Held};
+use crate::budget::{estimate_cost, PolicyKind, Resources};
+use crate::errors::EctusError;
+use crate::orchestrator::kubernetes::{deployment_manifest, hpa_manifest, hpa_name};
+use crate::orchestrator::policy::Policy;
+use crate::orchestrator::idle::PoolKey;
+use crate::orchestrator::{AutoscalerSpec, OrchestratorContext};
+
+const MANAGER: &str = "ectusr2-operator";
+const RESYNC: Duration = Duration::from_secs(60);
+const RETRY: Duration = Duration::from_secs(30);
+
+/// A model-serving worker pool; the operator keeps a Deployment (and optional HPA) in line with it.
+/// Field names also accept the snake_case keys of a `pool_ensure` spec.
+#[derive(CustomResource, Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "ectusr2.io", version = "v1alpha1", kind = "ModelPool", namespaced,
+    status = "ModelPoolStatus", shortname = "mp",
+    printcolumn = r#"{"name":"Model","type":"string","jsonPath":".spec.model"}"#,
+    printcolumn = r#"{"name":"Replicas","type":"integer","jsonPath":".status.replicas"}"#,
+    printcolumn = r#"{"name":"Ready","type":"integer","jsonPath":".status.readyReplicas"}"#,
+    printcolumn = r#"{"name":"Monthly USD","type":"number","jsonPath":".status.monthlyProjectedUsd"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPoolSpec {
+    #[serde(default)]
+    pub model: Option<String>,
+    pub image: String,
+    /// Desired replicas; 0 suspends the pool (and its autoscaler)
+    #[serde(default = "one")]
+    pub replicas: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub resources: Option<PoolResources>,
+    #[serde(default, alias = "node_selector", skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    #[serde(default)]
+    pub budget: Option<PoolBudget>,
+    #[serde(default)]
+    pub autoscaling: Option<PoolAutoscaling>,
+}
+
+fn one() -> u32 { 1 }
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolResources {
+    pub cpu: String,
+    pub memory: String,
+    #[serde(default)]
+    pub gpu: Option<String>,
+    #[serde(default, alias = "gpu_type")]
+    pub gpu_type: Option<String>,
+}
+
+/// Monthly cap on this pool's own cost, checked on top of the server-wide limit and the pool's budget.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolBudget {
+    #[serde(alias = "monthly_usd_limit")]
+    pub monthly_usd_limit: f64,
+    /// Currency of the limit (default: the server's reporting currency)
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// `hard` clamps replicas to the cap; `soft` (default) only reports the overrun
+    #[serde(default)]
+    pub policy: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolAutoscaling {
+    #[serde(alias = "min_replicas")]
+    pub min_replicas: u32,
+    #[serde(alias = "max_replicas")]
+    pub max_replicas: u32,
+    #[serde(default, alias = "cpu_utilization")]
+    pub cpu_utilization: Option<u32>,
+    #[serde(default, alias = "memory_utilization")]
+    pub memory_utilization: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPoolStatus {
+    pub replicas: u32,
+    pub ready_replicas: u32,
+    /// In the server's reporting currency
+    pub monthly_projected_usd: f64,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub observed_generation: Option<i64>,
+    #[serde(default)]
+    pub conditions: Vec<PoolCondition>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolCondition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub status: String,
+    pub reason: String,
+    pub message: String,
+    #[serde(default)]
+    pub last_transition_time: Option<String>,
+}
+
+impl ModelPoolSpec {
+    fn resources(&self) -> Resources {
+        let r = self.resources.clone().unwrap_or_else(|| PoolResources { cpu: "1".into(), memory: "1Gi".into(), ..Default::default() });
+        Resources { cpu: r.cpu, memory: r.memory, gpu: r.gpu, gpu_type: r.gpu_type, region: None, instance_class: None }
+    }
+
+    /// The `pool_ensure`-style spec `deployment_manifest` understands.
+    fn deployment_spec(&self, replicas: u32) -> Value {
+        let mut spec = json!({"image": self.image, "replicas": replicas});
+        if !self.command.is_empty() { spec["command"] = json!(self.command); }
+        if !self.args.is_empty() { spec["args"] = json!(self.args); }
+        if !self.env.is_empty() { spec["env"] = json!(self.env); }
+        if !self.node_selector.is_empty() { spec["node_selector"] = json!(self.node_selector); }
+        if let Some(r) = &self.resources {
+            spec["resources"] = json!({"cpu": r.cpu, "memory": r.memory, "gpu": r.gpu, "gpu_type": r.gpu_type});
+        }
+        if let Some(m) = &self.model { spec["env"]["ECTUSR2_MODEL"] = json!(m); }
+        spec
+    }
+}
+
+/// Server-wide settings the controller needs on every reconcile.
+pub struct OperatorCtx {
+    pub client: kube::Client,
+    pub policy: Policy,
+    /// Server-wide limit, budgets and policies, read on every reconcile so `budget_config` changes apply
+    pub budget: Arc<crate::budget::store::BudgetStore>,
+    /// The server's spend ledger: pools are checked against the rest of the spend and book their rate in it
+    pub ledger: Arc<Ledger>,
+}
+
+/// What a reconcile will apply, decided from the spec, budgets, spend and policy.
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    replicas: u32,
+    autoscaling: Option<(u32, u32)>,
+    monthly_usd: Decimal,
+    hourly_usd: Decimal,
+    budget: PoolCondition,
+    blocked: Option<String>,
+}
+
+/// A limit the pool counts against: what is committed to it without the pool, and what each replica adds.
+struct Level {
+    name: String,
+    limit: Decimal,
+    hard: bool,
+    base: Decimal,
+    per_replica: Decimal,
+}
+
+impl Level {
+    fn cost(&self, replicas: u32) -> Decimal {
+        self.base + self.per_replica * Decimal::from(replicas)
+    }
+
+    fn affordable(&self, worst: u32) -> u32 {
+        if self.per_replica <= Decimal::ZERO { return worst; }
+        u32::try_from(((self.limit - self.base).max(Decimal::ZERO) / self.per_replica).floor()).unwrap_or(u32::MAX).min(worst)
+    }
+
+    fn overrun(&self, replicas: u32) -> String {
+        if self.name == "pool" {
+            format!("{} replicas would cost {}/month over the {} cap", replicas, money::format(self.cost(replicas), 2), money::format(self.limit, 2))
+        } else {
+            format!("{} replicas would take {} to {} this month, over its {} limit", replicas, self.name, money::format(self.cost(replicas), 2), money::format(self.limit, 2))
+        }
+    }
+}
+
+/// The pool's id in the ledger, as `pool_ensure` books it.
+fn ledger_id(ns: &str, name: &str) -> String {
+    PoolKey::new("kubernetes", &OrchestratorContext { namespace: Some(ns.into()), name: Some(name.into()), model: None, cluster: None }).id()
+}
+
+/// The budget the pool is billed to: its `ectusr2.io/budget` label, then the budget it was last booked to, then the default.
+fn pool_budget(pool: &ModelPool, ctx: &OperatorCtx, id: &str) -> Result<Option<String>, String> {
+    let settings = ctx.budget.current();
+    let booked = ctx.ledger.budget_of(id).filter(|b| settings.budgets.known(b).is_ok());
+    settings.budgets.resolve(None, pool.labels().get(POOL_LABEL).map(String::as_str).or(booked.as_deref()), None)
+}
+
+fn condition(kind: &str, ok: bool, reason: &str, message: String) -> PoolCondition {
+    PoolCondition { kind: kind.into(), status: if ok { "True" } else { "False" }.into(), reason: reason.into(), message, last_transition_time: None }
+}
+
+/// Check the pool at its worst case (autoscaling max) against its own cap, the global limit and every
+/// budget from the root down to `budget`, each with the rest of the month's spend without this pool and
+/// what changes in flight hold. Hard limits clamp the replicas; soft ones are only reported.
+fn plan(spec: &ModelPoolSpec, ctx: &OperatorCtx, ns: &str, name: &str, budget: Result<Option<String>, String>, held: &Held) -> Plan {
+    // resources no replica could have are not priced, and nothing is applied
+    let resources = spec.resources();
+    let bad_resources = resources.validate().err();
+    let (per_replica, hourly) = match bad_resources {
+        None => { let one = estimate_cost("kubernetes", 1, &resources, 1.0); (one.monthly_projected_usd, one.hourly_total_usd) }
+        Some(_) => (Decimal::ZERO, Decimal::ZERO),
+    };
+    let now = crate::util::now_ms() as u64;
+    let rest_of_month = hourly * hours_from_ms(month_bounds(now).1.saturating_sub(now));
+    let mut replicas = spec.replicas;
+    let mut autoscaling = spec.autoscaling.as_ref().filter(|_| replicas > 0).map(|a| (a.min_replicas.max(1), a.max_replicas.max(a.min_replicas.max(1))));
+    let worst = autoscaling.map(|(_, max)| max).unwrap_or(replicas);
+
+    // a limit that cannot be checked (no FX rate, unknown budget, unreadable spend) applies nothing
+    let mut invalid = budget.as_ref().err().map(|e| format!("budget: {}", e));
+    let hard = |p: Option<&str>| p.is_some_and(|p| p.eq_ignore_ascii_case("hard"));
+    let mut levels = Vec::new();
+    if let Some(b) = &spec.budget {
+        match money::fx().to_base(dec(b.monthly_usd_limit), b.currency.as_deref().unwrap_or(&money::currency())) {
+            Ok(limit) => levels.push(Level { name: "pool".into(), limit, hard: hard(b.policy.as_deref()), base: Decimal::ZERO, per_replica }),
+            Err(e) => invalid = invalid.or(Some(format!("budget: {}", e))),
+        }
+    }
+    let settings = ctx.budget.current();
+    let id = ledger_id(ns, name);
+    if let Some(limit) = settings.monthly_usd_limit {
+        match held.ledger().spend_without(&id) {
+            Ok(s) => levels.push(Level { name: "total spend".into(), limit, hard: hard(settings.policy.as_deref()), base: s.projected_eom_usd + held.usd(None), per_replica: rest_of_month }),
+            Err(e) => invalid = invalid.or(Some(format!("spend: {}", e))),
+        }
+    }
+    let path = budget.ok().flatten();
+    for (b_name, b) in path.as_deref().map(|p| settings.budgets.chain(p)).unwrap_or_default() {
+        let Some(limit) = b.monthly_usd_limit else { continue };
+        match held.ledger().spend_in(b_name, Some(&id)) {
+            Ok(s) => levels.push(Level {
+                name: format!("budget `{}`", b_name), limit, hard: b.policy().policy == Some(PolicyKind::Hard), base: s.projected_eom_usd + held.usd(Some(b_name)), per_replica: rest_of_month,
+            }),
+            Err(e) => invalid = invalid.or(Some(format!("spend: {}", e))),
+        }
+    }
+
+    let cost = |n: u32| Decimal::from(n) * per_replica;
+    let over: Vec<&Level> = levels.iter().filter(|l| l.cost(worst) > l.limit).collect();
+    let budget = if let Some(msg) = invalid.clone() {
+        condition("BudgetExceeded", true, "InvalidBudget", msg)
+    } else if let Some(affordable) = over.iter().filter(|l| l.hard).map(|l| l.affordable(worst)).min() {
+        replicas = replicas.min(affordable);
+        autoscaling = autoscaling.and_then(|(min, max)| (affordable >= min).then_some((min, max.min(affordable))));
+        let first = over.iter().find(|l| l.hard).map(|l| l.overrun(worst)).unwrap_or_default();
+        condition("BudgetExceeded", true, "Clamped", format!("{}; limited to {}", first, affordable))
+    } else if let Some(l) = over.first() {
+        condition("BudgetExceeded", true, "OverBudget", l.overrun(worst))
+    } else if levels.is_empty() {
+        condition("BudgetExceeded", false, "NoBudget", "no budget cap configured".into())
+    } else {
+        let limits: Vec<String> = levels.iter().map(|l| format!("{} {}", l.name, money::format(l.limit, 2))).collect();
+        condition("BudgetExceeded", false, "WithinBudget", format!("{}/month projected, within {}", money::format(cost(worst), 2), limits.join(", ")))
+    };
+
+    let pool = OrchestratorContext { namespace: Some(ns.into()), name: Some(name.into()), model: spec.model.clone(), cluster: None };
+    let counts = match autoscaling { Some((min, max)) => vec![min, max], None => vec![replicas] };
+    let blocked = bad_resources.or(invalid).or_else(|| counts.into_iter().find_map(|n| ctx.policy.check("kubernetes", &pool, Some(n)).err()).map(|e| e.to_string()));
+    let effective = autoscaling.map(|(_, max)| max).unwrap_or(replicas);
+    Plan { replicas, autoscaling, monthly_usd: cost(effective), hourly_usd: hourly * Decimal::from(effective), budget, blocked }
+}
+
+fn with_owner(mut manifest: Value, pool: &ModelPool) -> Value {
+    if let Some(owner) = pool.controller_owner_ref(&()) {
+        manifest["metadata"]["ownerReferences"] = json!([owner]);
+    }
+    manifest
+}
+
+fn backend_err(e: impl std::fmt::Display) -> EctusError {
+    EctusError::Backend(e.to_string())
+}
+
+/// Keep `lastTransitionTime` when a condition's status did not change.
+fn stamp(mut new: PoolCondition, old: &[PoolCondition]) -> PoolCondition {
+    new.last_transition_time = old.iter()
+        .find(|c| c.kind == new.kind && c.status == new.status)
+        .and_then(|c| c.last_transition_time.clone())
+        .or_else(|| Some(k8s_openapi::chrono::Utc::now().to_rfc3339()));
+    new
+}
+
+async fn reconcile(pool: Arc<ModelPool>, ctx: Arc<OperatorCtx>) -> Result<Action, EctusError> {
+    let ns = pool.namespace().unwrap_or_else(|| "default".into());
+    let name = pool.name_any();
+    let id = ledger_id(&ns, &name);
+    let budget = pool_budget(&pool, &ctx, &id);
+    let plan = plan(&pool.spec, &ctx, &ns, &name, budget.clone(), &reservations::lock(&ctx.ledger));
+    let pp = PatchParams::apply(MANAGER).force();
+    let deployments: Api<Deployment> = Api::namespaced(ctx.client.clone(), &ns);
+    let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(ctx.client.clone(), &ns);
+
+    if plan.blocked.is_none() {
+        let mut manifest = with_owner(deployment_manifest(&name, &pool.spec.deployment_spec(plan.replicas).to_string()), &pool);
+        if plan.autoscaling.is_some() {
+            // leave spec.replicas to the HPA; dropping the field releases our ownership of it
+            if let Some(spec) = manifest["spec"].as_object_mut() { spec.remove("replicas"); }
+        }
+        deployments.patch(&name, &pp, &Patch::Apply(&manifest)).await.map_err(backend_err)?;
+
+        let hpa = hpa_name(&name);
+        match (plan.autoscaling, &pool.spec.autoscaling) {
+            (Some((min, max)), Some(a)) => {
+                let spec = AutoscalerSpec { min_replicas: min, max_replicas: max, cpu_utilization: a.cpu_utilization, memory_utilization: a.memory_utilization, metrics: vec![] };
+                hpas.patch(&hpa, &pp, &Patch::Apply(&with_owner(hpa_manifest(&hpa, &name, &spec), &pool))).await.map_err(backend_err)?;
+            }
+            _ => {
+                let owned = hpas.get_opt(&hpa).await.map_err(backend_err)?
+                    .is_some_and(|h| h.owner_references().iter().any(|o| Some(&o.uid) == pool.metadata.uid.as_ref()));
+                if owned { hpas.delete(&hpa, &DeleteParams::default()).await.map_err(backend_err)?; }
+            }
+        }
+        // booked at the autoscaling max, as `autoscaler_ensure` does; repeats of the booked rate are not written
+        let replicas = plan.autoscaling.map(|(_, max)| max).unwrap_or(plan.replicas);
+        ctx.ledger.rate(&id, replicas, plan.hourly_usd, budget.ok().flatten().as_deref());
+    }
+
+    let ready = deployments.get_opt(&name).await.map_err(backend_err)?
+        .and_then(|d| d.status).and_then(|s| s.ready_replicas).unwrap_or(0).max(0) as u32;
+    let want = plan.autoscaling.map(|(min, _)| min).unwrap_or(plan.replicas);
+    let ready_cond = match &plan.blocked {
+        Some(msg) => condition("Ready", false, "PolicyViolation", msg.clone()),
+        None if ready >= want => condition("Ready", true, "Available", format!("{}/{} replicas ready", ready, want)),
+        None => condition("Ready", false, "Progressing", format!("{}/{} replicas ready", ready, want)),
+    };
+    let old = pool.status.as_ref().map(|s| s.conditions.clone()).unwrap_or_default();
+    let status = ModelPoolStatus {
+        replicas: plan.autoscaling.map(|(min, _)| min.max(ready)).unwrap_or(plan.replicas),
+        ready_replicas: ready,
+        monthly_projected_usd: f64::try_from(plan.monthly_usd.round_dp(2)).unwrap_or_default(),
+        currency: Some(money::currency()),
+        observed_generation: pool.metadata.generation,
+        conditions: vec![stamp(ready_cond, &old), stamp(plan.budget.clone(), &old)],
+    };
+    let pools: Api<ModelPool> = Api::namespaced(ctx.client.clone(), &ns);
+    pools.patch_status(&name, &PatchParams::default(), &Patch::Merge(&json!({"status": status}))).await.map_err(backend_err)?;
+    // requeue even without changes: prices, budgets and readiness move without the object changing
+    Ok(Action::requeue(RESYNC))
+}
+
+fn error_policy(pool: Arc<ModelPool>, err: &EctusError, _ctx: Arc<OperatorCtx>) -> Action {
+    warn!(pool = %pool.name_any(), error = %err, "reconcile failed");
+    Action::requeue(RETRY)
+}
+
+/// Run the ModelPool controller until the process exits; `namespace` None watches all namespaces.
+pub async fn run(ctx: OperatorCtx, namespace: Option<String>) {
+    let client = ctx.client.clone();
+    let (pools, deployments): (Api<ModelPool>, Api<Deployment>) = match &namespace {
+        Some(ns) => (Api::namespaced(client.clone(), ns), Api::namespaced(client, ns)),
+        None => (Api::all(client.clone()), Api::all(client)),
+    };
+    info!(namespace = namespace.as_deref().unwrap_or("*"), "starting ModelPool controller");
+    Controller::new(pools, watcher::Config::default())
+        .owns(deployments, watcher::Config::default())
+        .run(reconcile, error_policy, Arc::new(ctx))
+        .for_each(|res| async move {
+            match res {
+                Ok((obj, _)) => tracing::debug!(pool = %obj.name, "reconciled"),
+                Err(e) => warn!(error = %e, "controller error"),
+            }
+        })
+        .await;
+}
+
+/// A ModelPool for `pool_ensure` in operator mode: the tool spec plus the call's model.
+pub fn modelpool_manifest(name: &str, model: Option<&str>, spec: &Value) -> Result<Value, EctusError> {
+    let mut spec: ModelPoolSpec = serde_json::from_value(spec.clone()).map_err(|e| EctusError::Input(format!("ModelPool spec: {}", e)))?;
+    if spec.model.is_none() { spec.model = model.map(|m| m.to_string()); }
+    let mut pool = serde_json::to_value(ModelPool::new(name, spec)).map_err(backend_err)?;
+    if let Some(m) = pool["metadata"].as_object_mut() { m.retain(|k, _| k == "name"); }
+    Ok(pool)
+}
+
+fn pool_api(client: kube::Client, ns: &str) -> Api<ModelPool> {
+    Api::namespaced(client, ns)
+}
+
+/// `spec.replicas` patch; with autoscaling the bounds move the same way `scale` moves an HPA's.
+fn replicas_patch(spec: &ModelPoolSpec, replicas: u32) -> Value {
+    match (&spec.autoscaling, replicas) {
+        (Some(a), n) if n > 0 => json!({"spec": {"replicas": n, "autoscaling": {"minReplicas": n, "maxReplicas": a.max_replicas.max(n)}}}),
+        _ => json!({"spec": {"replicas": replicas}}),
+    }
+}
+
+fn strip(pool: &ModelPool) -> anyhow::Result<Value> {
+    let mut v = serde_json::to_value(pool)?;
+    if let Some(m) = v["metadata"].as_object_mut() { m.retain(|k, _| k == "name" || k == "namespace" || k == "generation"); }
+    Ok(v)
+}
+
+/// Apply (or dry-run apply) a ModelPool for `pool_ensure`; returns the live and proposed objects.
+pub async fn apply_pool(client: kube::Client, ctx: &OrchestratorContext, spec: &str, dry_run: bool) -> anyhow::Result<crate::orchestrator::Preview> {
+    let ns = ctx.namespace.as_deref().unwrap_or("default");
+    let name = ctx.name.as_deref().unwrap_or("ectusr2-workers");
+    let spec: Value = serde_json::from_str(spec).map_err(|e| anyhow::anyhow!("spec must be JSON in operator mode: {}", e))?;
+    let manifest = modelpool_manifest(name, ctx.model.as_deref(), &spec)?;
+    let api = pool_api(client, ns);
+    let live = api.get_opt(name).await?;
+    let mut pp = PatchParams::apply("ectusr2").force();
+    if dry_run { pp = pp.dry_run(); }
+    let proposed = api.patch(name, &pp, &Patch::Apply(&manifest)).await?;
+    Ok(crate::orchestrator::Preview {
+        current_replicas: live.as_ref().map(|p| p.spec.replicas).unwrap_or(0),
+        live: live.as_ref().map(strip).transpose()?,
+        proposed: strip(&proposed)?,
+    })
+}
+
+/// Set `spec.replicas` on ModelPool `name` (dry-run when previewing); the controller does the rest.
+pub async fn scale_pool(client: kube::Client, ns: &str, name: &str, replicas: u32, dry_run: bool) -> anyhow::Result<crate::orchestrator::Preview> {
+    let api = pool_api(client, ns);
+    let live = api.get_opt(name).await?.ok_or_else(|| anyhow::anyhow!("ModelPool {} not found in {}", name, ns))?;
+    let mut pp = PatchParams::default();
+    if dry_run { pp = pp.dry_run(); }
+    let proposed = api.patch(name, &pp, &Patch::Merge(&replicas_patch(&live.spec, replicas))).await?;
+    Ok(crate::orchestrator::Preview { current_replicas: live.spec.replicas, live: Some(strip(&live)?), proposed: strip(&proposed)? })
+}
+
+/// The autoscaling maximum ModelPool `name` keeps when scaled to `replicas`, as `replicas_patch` sets it.
+pub async fn autoscaled_max(client: kube::Client, ns: &str, name: &str, replicas: u32) -> anyhow::Result<Option<u32>> {
+    let live = pool_api(client, ns).get_opt(name).await?;
+    Ok(live.and_then(|p| p.spec.autoscaling).map(|a| a.max_replicas.max(replicas)))
+}
+
+/// Replace (or with None, drop) `spec.autoscaling` on ModelPool `name`.
+pub async fn set_autoscaling(client: kube::Client, ns: &str, name: &str, spec: Option<&AutoscalerSpec>) -> anyhow::Result<String> {
+    let value = spec.map(|s| json!({"minReplicas": s.min_replicas, "maxReplicas": s.max_replicas, "cpuUtilization": s.cpu_utilization, "memoryUtilization": s.memory_utilization}));
+    let _ = pool_api(client, ns).patch(name, &PatchParams::default(), &Patch::Merge(&json!({"spec": {"autoscaling": value}}))).await?;
+    Ok(match spec {
+        Some(s) => format!("set autoscaling on ModelPool {} in {} (min {}, max {})", name, ns, s.min_replicas, s.max_replicas),
+        None => format!("removed autoscaling from ModelPool {} in {}", name, ns),
+    })
+}
+
+/// Delete ModelPool `name`; its Deployment and HPA go with it through their owner references.
+pub async fn delete_pool(client: kube::Client, ns: &str, name: &str) -> anyhow::Result<String> {
+    let _ = pool_api(client, ns).delete(name, &DeleteParams::foreground()).await?;
+    Ok(format!("deleted ModelPool {} in {}", name, ns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(limit: Option<Decimal>, policy: &str, orch_policy: &str) -> OperatorCtx {
+        // a client is never used by `plan`; build one against an unroutable address
+        let config = kube::Config::new("http://127.0.0.1:9".parse().unwrap());
+        OperatorCtx {
+            client: kube::Client::try_from(config).unwrap(),
+            policy: Policy::from_json(orch_policy).unwrap(),
+            budget: Arc::new(crate::budget::store::BudgetStore::open(
+                crate::budget::store::Settings { monthly_usd_limit: limit, policy: Some(policy.into()), ..Default::default() }, None, Default::default(),
+            )),
+            ledger: Arc::new(Ledger::memory()),
+        }
+    }
+
+    fn plan_of(spec: &ModelPoolSpec, ctx: &OperatorCtx) -> Plan {
+        plan(spec, ctx, "aion", "w", Ok(None), &reservations::lock(&ctx.ledger))
+    }
+
+    /// One replica from now to the end of the month, what the global limit and budgets are checked with.
+    fn rest_of_month(spec: &ModelPoolSpec) -> Decimal {
+        let now = crate::util::now_ms() as u64;
+        estimate_cost("kubernetes", 1, &spec.resources(), 1.0).hourly_total_usd * hours_from_ms(month_bounds(now).1 - now)
+    }
+
+    fn spec(json: Value) -> ModelPoolSpec {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plan_budget_and_policy() {
+        let s = spec(json!({"image": "w:1", "replicas": 10, "resources": {"cpu": "1", "memory": "1Gi"}}));
+        let per = estimate_cost("kubernetes", 1, &s.resources(), 1.0).monthly_projected_usd;
+        let rest = rest_of_month(&s);
+
+        let p = plan_of(&s, &ctx(Some(rest * Decimal::new(45, 1)), "hard", "{}"));
+        assert_eq!((p.replicas, p.budget.reason.as_str(), p.monthly_usd), (4, "Clamped", per * Decimal::from(4)));
+        let p = plan_of(&s, &ctx(Some(rest * Decimal::new(45, 1)), "soft", "{}"));
+        assert_eq!((p.replicas, p.budget.reason.as_str()), (10, "OverBudget"));
+        let p = plan_of(&s, &ctx(None, "soft", r#"{"kubernetes": {"max_replicas": 5}}"#));
+        assert!(p.blocked.unwrap().contains("outside the allowed range"));
+
+        // a pool cap applies to the pool's own cost, as well as the global limit; autoscaling max is what gets capped
+        let s = spec(json!({"image": "w:1", "replicas": 2, "budget": {"monthly_usd_limit": per * Decimal::from(6), "policy": "hard"}, "autoscaling": {"min_replicas": 2, "max_replicas": 10}}));
+        let p = plan_of(&s, &ctx(Some(rest * Decimal::new(75, 1)), "hard", "{}"));
+        assert_eq!(p.autoscaling, Some((2, 6)));
+        let p = plan_of(&s, &ctx(Some(rest * Decimal::new(55, 1)), "hard", "{}"));
+        assert_eq!(p.autoscaling, Some((2, 5)));
+        assert_eq!(plan_of(&s, &ctx(Some(rest), "hard", "{}")).autoscaling, None);
+        // a limit in a currency with no FX rate blocks the reconcile
+        let s = spec(json!({"image": "w:1", "budget": {"monthlyUsdLimit": 100, "currency": "JPY"}}));
+        let p = plan_of(&s, &ctx(None, "soft", "{}"));
+        assert_eq!(p.budget.reason, "InvalidBudget");
+        assert!(p.blocked.unwrap().contains("no FX rate from JPY"));
+        let suspended = spec(json!({"image": "w:1", "replicas": 0, "autoscaling": {"minReplicas": 2, "maxReplicas": 4}}));
+        assert_eq!(plan_of(&suspended, &ctx(None, "soft", "{}")).autoscaling, None);
+        let huge = spec(json!({"image": "w:1", "resources": {"cpu": "9999999999999999999999999999", "memory": "1Gi"}}));
+        assert!(plan_of(&huge, &ctx(Some(Decimal::ONE), "hard", "{}")).blocked.unwrap().contains("cpu"));
+        assert_eq!(replicas_patch(&suspended.clone(), 6)["spec"]["autoscaling"], json!({"minReplicas": 6, "maxReplicas": 6}));
+        assert_eq!(replicas_patch(&suspended, 0), json!({"spec": {"replicas": 0}}));
+    }
+
+    #[tokio::test]
+    async fn test_plan_counts_other_spend_holds_and_budgets() {
+        let s = spec(json!({"image": "w:1", "replicas": 10, "resources": {"cpu": "1", "memory": "1Gi"}}));
+        let rest = rest_of_month(&s);
+        let hourly = estimate_cost("kubernetes", 1, &s.resources(), 1.0).hourly_total_usd;
+        let mut c = ctx(None, "soft", "{}");
+        let mut budgets = crate::budget::hierarchy::Budgets::parse(r#"{"budgets": {"acme": {"policy": "hard"}, "acme/ml": {}}}"#, false).unwrap();
+        budgets.budgets.get_mut("acme").unwrap().monthly_usd_limit = Some(rest * Decimal::new(75, 1));
+        c.budget = Arc::new(crate::budget::store::BudgetStore::open(crate::budget::store::Settings { budgets, ..Default::default() }, None, Default::default()));
+        // another pool in the budget runs 3 replicas' worth; this pool's own booking is left out
+        c.ledger.rate(&ledger_id("aion", "other"), 3, hourly * Decimal::from(3), Some("acme/ml"));
+        c.ledger.rate(&ledger_id("aion", "w"), 10, hourly * Decimal::from(10), Some("acme"));
+        let p = plan(&s, &c, "aion", "w", Ok(Some("acme/ml".into())), &reservations::lock(&c.ledger));
+        assert_eq!((p.replicas, p.budget.reason.as_str()), (4, "Clamped"));
+        assert!(p.budget.message.contains("budget `acme`"));
+        // and a change in flight holds another replica's worth
+        let _hold = reservations::lock(&c.ledger).hold("kubernetes:aion/third", Some("acme"), rest);
+        assert_eq!(plan(&s, &c, "aion", "w", Ok(Some("acme".into())), &reservations::lock(&c.ledger)).replicas, 3);
+        // outside the budget only the global limit counts, with the same spend
+        assert_eq!(plan(&s, &c, "aion", "w", Ok(None), &reservations::lock(&c.ledger)).budget.reason, "NoBudget");
+        let p = plan(&s, &c, "aion", "w", Err("unknown budget `acme/web`".into()), &reservations::lock(&c.ledger));
+        assert!(p.budget.reason == "InvalidBudget" && p.blocked.is_some());
+    }
+
+    #[test]
+    fn test_modelpool_manifest_and_crd() {
+        let m = modelpool_manifest("w", Some("llama"), &json!({"image": "w:1", "replicas": 3, "node_selector": {"pool": "gpu"}, "resources": {"cpu": "2", "memory": "8Gi", "gpu": "1", "gpu_type": "L4"}})).unwrap();
+        assert_eq!((m["apiVersion"].as_str(), m["kind"].as_str()), (Some("ectusr2.io/v1alpha1"), Some("ModelPool")));
+        assert_eq!(m["spec"]["nodeSelector"]["pool"], "gpu");
+        assert_eq!(m["spec"]["resources"]["gpuType"], "L4");
+        assert_eq!(m["spec"]["model"], "llama");
+        assert!(modelpool_manifest("w", None, &json!({"replicas": 1})).is_err());
+
+        use kube::CustomResourceExt;
+        let crd = ModelPool::crd();
+        assert_eq!(crd.metadata.name.as_deref(), Some("modelpools.ectusr2.io"));
+        // the chart's copy is `--print-crd` output; regenerate it when the spec or its docs change
+        let chart: Value = serde_json::from_str(include_str!("../../deploy/helm/ectusr2/crds/modelpools.ectusr2.io.json")).unwrap();
+        assert_eq!(chart, serde_json::to_value(&crd).unwrap(), "deploy/helm/ectusr2/crds/modelpools.ectusr2.io.json is stale");
+    }
+
+    #[test]
+    fn test_deployment_spec_round_trip() {
+        let s = spec(json!({"image": "w:1", "model": "m", "resources": {"cpu": "1", "memory": "2Gi", "gpu": "1"}}));
+        let d = deployment_manifest("w", &s.deployment_spec(3).to_string());
+        assert_eq!(d["spec"]["replicas"], 3);
+        assert_eq!(d["spec"]["template"]["spec"]["containers"][0]["resources"]["limits"]["nvidia.com/gpu"], "1");
+        assert_eq!(d["spec"]["template"]["spec"]["containers"][0]["env"][0], json!({"name": "ECTUSR2_MODEL", "value": "m"}));
+    }
+}
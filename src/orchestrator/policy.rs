@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use serde::Deserialize;
+
+use crate::errors::EctusError;
+use crate::orchestrator::OrchestratorContext;
+
+/// What one backend may touch. Empty `allow` means any pool; unset bounds mean no limit.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// `namespace/name` globs where `*` matches within one segment, e.g. `aion/*-workers`
+    #[serde(default)]
+    pub allow: Vec<String>,
+    pub min_replicas: Option<u32>,
+    pub max_replicas: Option<u32>,
+}
+
+/// Per-backend allowlists checked before any orchestrator call; `*` applies to backends without their own rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Policy {
+    rules: BTreeMap<String, Rule>,
+}
+
+impl Policy {
+    /// Parse `{ "<backend or *>": { allow, min_replicas, max_replicas }, ... }`; aliases map to canonical names.
+    pub fn from_json(s: &str) -> Result<Self, EctusError> {
+        let raw: BTreeMap<String, Rule> = serde_json::from_str(s).map_err(|e| EctusError::Input(format!("orchestrator policy: {}", e)))?;
+        let mut rules = BTreeMap::new();
+        for (key, rule) in raw {
+            let backend = if key == "*" { "*" } else {
+                crate::orchestrator::canonical(&key).ok_or_else(|| EctusError::Input(format!("orchestrator policy: unknown backend `{}`", key)))?
+            };
+            if let (Some(min), Some(max)) = (rule.min_replicas, rule.max_replicas) {
+                if min > max { return Err(EctusError::Input(format!("orchestrator policy: {} min_replicas {} exceeds max_replicas {}", key, min, max))); }
+            }
+            rules.insert(backend.to_string(), rule);
+        }
+        Ok(Self { rules })
+    }
+
+    fn rule(&self, backend: &str) -> Option<&Rule> {
+        self.rules.get(backend).or_else(|| self.rules.get("*"))
+    }
+
+    /// Reject the pool (and `replicas`, when the operation sets a count) if the backend's rule forbids it.
+    pub fn check(&self, backend: &str, ctx: &OrchestratorContext, replicas: Option<u32>) -> Result<(), EctusError> {
+        let Some(rule) = self.rule(backend) else { return Ok(()) };
+        let pool = format!("{}/{}", ctx.namespace.as_deref().unwrap_or("default"), ctx.name.as_deref().unwrap_or("ectusr2-workers"));
+        if !rule.allow.is_empty() && !rule.allow.iter().any(|p| glob_match(p, &pool)) {
+            return Err(EctusError::Policy(format!("{} may not touch {} (allowed: {})", backend, pool, rule.allow.join(", "))));
+        }
+        if let Some(r) = replicas {
+            let (min, max) = (rule.min_replicas.unwrap_or(0), rule.max_replicas.unwrap_or(u32::MAX));
+            if r < min || r > max {
+                let max = rule.max_replicas.map(|m| m.to_string()).unwrap_or_else(|| "unbounded".into());
+                return Err(EctusError::Policy(format!("{} replicas for {} is outside the allowed range {}..{} on {}", r, pool, min, max, backend)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `*` matches any run of characters other than `/`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, rest)) => {
+            let Some(text) = text.strip_prefix(head) else { return false };
+            let segment = text.find('/').unwrap_or(text.len());
+            (0..=segment)
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(ns: &str, name: &str) -> OrchestratorContext {
+        OrchestratorContext { namespace: Some(ns.into()), name: Some(name.into()), model: None, cluster: None }
+    }
+
+    #[test]
+    fn test_policy_allowlist_and_replicas() {
+        let p = Policy::from_json(r#"{"k8s": {"allow": ["aion/*-workers"], "max_replicas": 20}, "*": {"max_replicas": 2}}"#).unwrap();
+        assert!(p.check("kubernetes", &ctx("aion", "gpu-workers"), Some(20)).is_ok());
+        assert!(p.check("kubernetes", &ctx("aion", "gpu-workers"), Some(21)).unwrap_err().to_string().contains("0..20"));
+        assert!(p.check("kubernetes", &ctx("default", "gpu-workers"), None).unwrap_err().to_string().contains("may not touch default/gpu-workers"));
+        assert!(p.check("kubernetes", &ctx("aion", "api"), None).is_err());
+        assert!(p.check("local", &ctx("any", "thing"), Some(3)).is_err());
+        assert!(Policy::default().check("local", &ctx("any", "thing"), Some(300)).is_ok());
+        assert!(Policy::from_json(r#"{"nomad": {}}"#).is_err());
+        assert!(Policy::from_json(r#"{"*": {"min_replicas": 3, "max_replicas": 1}}"#).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("aion/*", "aion/w"));
+        assert!(glob_match("*/w", "aion/w"));
+        assert!(!glob_match("*", "aion/w"));
+        assert!(!glob_match("aion/*-workers", "aion/x-workers-2"));
+        assert!(glob_match("default/w*-x", "default/wörker-x"));
+        assert!(!glob_match("default/w*-y", "default/wörker-x"));
+        assert!(glob_match("*ö*", "wörker"));
+    }
+}
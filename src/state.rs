@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use crate::budget::alerts::Alerter;
+use crate::budget::ledger::Ledger;
+use crate::budget::store::{BudgetStore, Settings};
+use crate::config::Config;
+use crate::orchestrator::{idle::IdleTracker, local::LocalOrchestrator, policy::Policy};
+
+/// Runtime state shared across MCP requests and background tasks.
+pub struct AppState {
+    pub idle: IdleTracker,
+    pub local: LocalOrchestrator,
+    pub policy: Policy,
+    /// Spend so far, shared with the operator
+    pub ledger: Arc<Ledger>,
+    /// Limits and policies in effect, shared with the operator
+    pub budget: Arc<BudgetStore>,
+    /// Threshold alerts and what has been delivered this month
+    pub alerts: Alerter,
+    /// `clientInfo.name` from `initialize`; selects the client's budget
+    pub client: std::sync::RwLock<Option<String>>,
+    #[cfg(feature = "kubernetes")]
+    pub kube: crate::orchestrator::kubernetes::KubeOrchestrator,
+    #[cfg(feature = "local")]
+    pub docker: crate::orchestrator::docker::DockerOrchestrator,
+    #[cfg(feature = "aws")]
+    pub ecs: crate::orchestrator::ecs::EcsOrchestrator,
+    #[cfg(feature = "gcp")]
+    pub gcp: crate::orchestrator::gcp::GcpOrchestrator,
+    #[cfg(feature = "azure")]
+    pub azure: crate::orchestrator::azure::AzureOrchestrator,
+}
+
+impl AppState {
+    pub fn new(cfg: &Config) -> Self {
+        Self {
+            idle: IdleTracker::new(cfg.idle_minutes),
+            local: LocalOrchestrator::new(cfg.local_worker_command.as_deref()),
+            policy: cfg.orchestrator_policy.clone(),
+            ledger: Arc::new(match &cfg.ledger_path {
+                Some(path) => Ledger::open(path).unwrap_or_else(|e| {
+                    tracing::warn!(error = %e, "spend ledger unavailable; keeping spend in memory");
+                    Ledger::memory()
+                }),
+                None => Ledger::memory(),
+            }),
+            budget: Arc::new(BudgetStore::open(
+                Settings { monthly_usd_limit: cfg.budget_limit, policy: cfg.budget_policy.clone(), budgets: cfg.budgets.clone(), currency: crate::budget::money::currency() },
+                cfg.budget_config_path.clone(),
+                cfg.budget_admins.clone(),
+            )),
+            alerts: Alerter::new(
+                cfg.budget_alert_thresholds.clone(),
+                cfg.budget_alert_forecast,
+                cfg.budget_alert_webhooks.clone(),
+                cfg.ledger_path.as_ref().map(|l| l.with_file_name("budget-alerts.json")),
+            ),
+            client: Default::default(),
+            #[cfg(feature = "kubernetes")]
+            kube: crate::orchestrator::kubernetes::KubeOrchestrator::new(cfg.kube_clusters.clone(), cfg.kube_default_cluster.clone(), cfg.operator),
+            #[cfg(feature = "local")]
+            docker: crate::orchestrator::docker::DockerOrchestrator::new(cfg.docker_host.as_deref()),
+            #[cfg(feature = "aws")]
+            ecs: crate::orchestrator::ecs::EcsOrchestrator::new(&cfg.aws_region, cfg.ecs_endpoint.as_deref()),
+            #[cfg(feature = "gcp")]
+            gcp: crate::orchestrator::gcp::GcpOrchestrator::new(cfg.gcp_project.as_deref(), &cfg.gcp_region, &cfg.gcp_zone, cfg.gcp_endpoint.as_deref()),
+            #[cfg(feature = "azure")]
+            azure: crate::orchestrator::azure::AzureOrchestrator::new(cfg.azure_subscription.as_deref(), cfg.azure_resource_group.as_deref(), cfg.azure_endpoint.as_deref()),
+        }
+    }
+}
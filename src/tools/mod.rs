@@ -1,5 +1,10 @@
 use serde_json::{json, Value};
-use crate::{api::client::ApiClient, config::Config};
+use crate::{api::client::ApiClient, config::Config, errors::EctusError, state::AppState};
+use crate::budget::ledger::Plan;
+use crate::budget::reservations::{self, Held};
+use crate::budget::tokens::TokenUsage;
+use crate::budget::money::{self, dec, hours_from_ms, Decimal};
+use crate::orchestrator::{idle::{self, PoolKey}, Orchestrator, OrchestratorContext};
 use tracing::{info, warn};
 
 pub fn list() -> Vec<Value> {
@@ -9,112 +14,610 @@ pub fn list() -> Vec<Value> {
         json!({"name":"refactor_code","description":"Apply safe refactorings","inputSchema":{"type":"object"}}),
         json!({"name":"orchestrator_scale","description":"Scale worker pool","inputSchema":{"type":"object"}}),
         json!({"name":"orchestrator_status","description":"Cluster status","inputSchema":{"type":"object"}}),
+        json!({"name":"orchestrator_backends","description":"List available orchestrator backends and their capabilities","inputSchema":{"type":"object"}}),
         json!({"name":"pool_ensure","description":"Ensure model pool","inputSchema":{"type":"object"}}),
+        json!({"name":"pool_delete","description":"Delete model pool","inputSchema":{"type":"object"}}),
+        json!({"name":"autoscaler_ensure","description":"Create/update pool autoscaler (HPA)","inputSchema":{"type":"object"}}),
+        json!({"name":"autoscaler_delete","description":"Remove pool autoscaler (HPA)","inputSchema":{"type":"object"}}),
+        json!({"name":"pool_logs","description":"Recent pod logs and events for a pool (size-limited, secrets redacted)","inputSchema":{"type":"object"}}),
+        json!({"name":"job_submit","description":"Submit a batch job (Kubernetes Job) with cost estimate and budget check","inputSchema":{"type":"object"}}),
+        json!({"name":"job_status","description":"Batch job progress and cost; `wait_seconds` (max 6) watches briefly for it to finish","inputSchema":{"type":"object"}}),
+        json!({"name":"job_delete","description":"Delete a batch job and its pods","inputSchema":{"type":"object"}}),
         json!({"name":"cost_estimate","description":"Estimate cost","inputSchema":{"type":"object"}}),
-        json!({"name":"budget_config","description":"Configure budget policy (read-only in this build; use CLI/env)","inputSchema":{"type":"object"}}),
-        json!({"name":"budget_status","description":"Budget status","inputSchema":{"type":"object"}}),
+        json!({"name":"pricing_rates","description":"Effective pricing catalog rates per backend/region/instance class; `reload` re-reads the catalog file","inputSchema":{"type":"object"}}),
+        json!({"name":"budget_config","description":"Show budget limits and policies; with `admin_token`, change the global or per-budget limits and policies (persisted and audited)","inputSchema":{"type":"object"}}),
+        json!({"name":"budget_status","description":"Budget status; per team/project budgets roll up the hierarchy (`budget` limits the output to one subtree)","inputSchema":{"type":"object"}}),
     ]
 }
 
-pub async fn call(client: &ApiClient, cfg: &Config, name: &str, args: Value) -> anyhow::Result<String> {
+pub async fn call(client: &ApiClient, cfg: &Config, state: &AppState, name: &str, args: Value) -> anyhow::Result<String> {
     match name {
-        "generate_code" => generate_code(client, args).await,
-        "run_qa" => run_qa(client, args).await,
-        "refactor_code" => refactor_code(client, args).await,
-        "orchestrator_scale" => orchestrator_scale(cfg, args).await,
-        "orchestrator_status" => orchestrator_status(cfg, args).await,
-        "pool_ensure" => pool_ensure(cfg, args).await,
+        "generate_code" => generate_code(client, cfg, state, args).await,
+        "run_qa" => run_qa(client, cfg, state, args).await,
+        "refactor_code" => refactor_code(client, cfg, state, args).await,
+        "orchestrator_scale" => orchestrator_scale(cfg, state, args).await,
+        "orchestrator_status" => orchestrator_status(cfg, state, args).await,
+        "orchestrator_backends" => orchestrator_backends(cfg, state).await,
+        "pool_ensure" => pool_ensure(cfg, state, args).await,
+        "pool_delete" => pool_delete(cfg, state, args).await,
+        "autoscaler_ensure" => autoscaler_ensure(cfg, state, args).await,
+        "autoscaler_delete" => autoscaler_delete(cfg, state, args).await,
+        "pool_logs" => pool_logs(cfg, state, args).await,
+        "job_submit" => job_submit(cfg, state, args).await,
+        "job_status" => job_status(cfg, state, args).await,
+        "job_delete" => job_delete(cfg, state, args).await,
         "cost_estimate" => cost_estimate(args).await,
-        "budget_config" => budget_config(cfg, args).await,
-        "budget_status" => budget_status(cfg).await,
+        "pricing_rates" => pricing_rates(args).await,
+        "budget_config" => budget_config(state, args).await,
+        "budget_status" => budget_status(state, args).await,
         _ => anyhow::bail!("unknown tool: {name}"),
     }
 }
 
-async fn generate_code(client: &ApiClient, args: Value) -> anyhow::Result<String> {
-    let v = client.post_json("/api/v1/generate", &args).await?;
-    Ok(v.to_string())
+/// Resolve `backend` (or the configured default) to an orchestrator and its canonical name.
+fn backend_from_args(cfg: &Config, state: &AppState, args: &Value) -> Result<(Box<dyn Orchestrator>, &'static str), EctusError> {
+    let name = args.get("backend").and_then(|v| v.as_str()).unwrap_or(&cfg.orchestrator_backend);
+    let orch = crate::orchestrator::new_backend(state, name)?;
+    let backend = orch.capabilities().backend;
+    Ok((orch, backend))
 }
 
-async fn run_qa(client: &ApiClient, args: Value) -> anyhow::Result<String> {
-    let v = client.post_json("/api/v1/qa", &args).await?;
-    Ok(v.to_string())
+fn ctx_from_args(args: &Value) -> OrchestratorContext {
+    OrchestratorContext {
+        namespace: args.get("namespace").and_then(|v| v.as_str().map(|s| s.to_string())),
+        name: args.get("name").and_then(|v| v.as_str().map(|s| s.to_string())),
+        model: args.get("model").and_then(|v| v.as_str().map(|s| s.to_string())),
+        cluster: args.get("cluster").or_else(|| args.get("context")).and_then(|v| v.as_str().map(|s| s.to_string())),
+    }
+}
+
+/// Flat per-call cost plus the tokens at the catalog's price for their model.
+fn call_cost(cfg: &Config, tokens: &TokenUsage) -> Decimal {
+    let catalog = crate::budget::pricing::current();
+    cfg.api_call_usd.unwrap_or_default() + catalog.model(tokens.model.as_deref()).map(|(_, p)| tokens.cost(p)).unwrap_or_default()
 }
 
-async fn refactor_code(client: &ApiClient, args: Value) -> anyhow::Result<String> {
-    let v = client.post_json("/api/v1/refactor", &args).await?;
+/// Upstream calls count as traffic for the pool named by `pool` (optional); `budget` bills the call. Neither is forwarded.
+/// The call's cost, estimated from the request, is checked and reserved first: hard limits block it, soft limits warn.
+async fn forward(client: &ApiClient, cfg: &Config, state: &AppState, path: &str, mut args: Value) -> anyhow::Result<String> {
+    let budget = budget_for(state, &args, None)?;
+    let mut take = |k: &str| args.as_object_mut().and_then(|m| m.remove(k)).and_then(|v| v.as_str().map(|s| s.to_string()));
+    let pool = take("pool");
+    take("budget");
+    let estimate = TokenUsage::estimate(&args);
+    let estimated_usd = call_cost(cfg, &estimate);
+    let reservation = {
+        let held = reservations::lock(&state.ledger);
+        let levels = check_budgets(state, &held, budget.as_deref(), None, estimated_usd, true)?;
+        if let Err(msg) = levels.verdict {
+            warn!(%msg, path, "upstream call blocked by budget policy");
+            return Ok(json!({"ok": false, "reason": msg, "estimate": {"usd": estimated_usd, "tokens": estimate}, "budgets": levels.levels}).to_string());
+        }
+        for w in &levels.warnings {
+            warn!(warning = %w, path, "upstream call over a soft budget limit");
+            crate::mcp::server::notify("notifications/message", json!({"level": "warning", "logger": "ectusr2.budget", "data": {"text": w, "estimate_usd": estimated_usd}}));
+        }
+        held.hold(path, budget.as_deref(), estimated_usd)
+    };
+    idle::wake(state, pool.as_deref()).await;
+    let (v, usage) = client.post_metered(path, &args).await?;
+    // without reported usage the estimate is booked, so spend is not undercounted
+    let tokens = usage.map(|mut u| { u.model = u.model.or(estimate.model.clone()); u }).unwrap_or(estimate);
+    let usd = call_cost(cfg, &tokens);
+    state.ledger.charge(path, usd, budget.as_deref(), Some(tokens));
+    reservation.commit();
     Ok(v.to_string())
 }
 
-async fn orchestrator_scale(cfg: &Config, args: Value) -> anyhow::Result<String> {
-    use crate::budget::{Resources, estimate_cost, enforce_budget, BudgetPolicy, PolicyKind};
-    let backend = args.get("backend").and_then(|v| v.as_str()).unwrap_or(&cfg.orchestrator_backend);
-    let replicas = args.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
-    let resources: Resources = serde_json::from_value(args.get("resources").cloned().unwrap_or(json!({"cpu":"1","memory":"1Gi"})))?;
-    let duration_hours = args.get("duration_hours").and_then(|v| v.as_f64()).unwrap_or(24.0) as f32;
+async fn generate_code(client: &ApiClient, cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    forward(client, cfg, state, "/api/v1/generate", args).await
+}
+
+async fn run_qa(client: &ApiClient, cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    forward(client, cfg, state, "/api/v1/qa", args).await
+}
+
+async fn refactor_code(client: &ApiClient, cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    forward(client, cfg, state, "/api/v1/refactor", args).await
+}
+
+/// Book the pool's new running rate, and when it is expected to run, in the spend ledger.
+fn book(state: &AppState, id: &str, backend: &str, replicas: u32, resources: &crate::budget::Resources, budget: Option<&str>, plan: Plan) {
+    let hourly = crate::budget::estimate_cost(backend, replicas, resources, 1.0).hourly_total_usd;
+    state.ledger.rate_planned(id, replicas, hourly, budget, Some(plan));
+}
+
+/// The budget a call is billed to: the `budget` argument, then the pool's `ectusr2.io/budget` label or
+/// the budget it was last booked to, then the MCP client's budget, then the default. None without budgets.
+fn budget_for(state: &AppState, args: &Value, pool: Option<&str>) -> Result<Option<String>, EctusError> {
+    use crate::budget::hierarchy::POOL_LABEL;
+    let label = args.pointer("/spec/labels").and_then(|l| l.get(POOL_LABEL)).and_then(|v| v.as_str()).map(str::to_string);
+    // a booking to a budget that has since been removed is ignored rather than blocking the pool
+    let settings = state.budget.current();
+    let booked = || pool.and_then(|id| state.ledger.budget_of(id)).filter(|b| settings.budgets.known(b).is_ok());
+    let client = state.client.read().unwrap().clone();
+    settings.budgets.resolve(args.get("budget").and_then(|v| v.as_str()), label.or_else(booked).as_deref(), client.as_deref()).map_err(EctusError::Input)
+}
+
+/// Per-level verdicts for a change billed to a budget, and the limits it exceeds but may still pass.
+struct BudgetLevels {
+    levels: Vec<Value>,
+    verdict: Result<(), String>,
+    warnings: Vec<String>,
+}
+
+/// Check the global limit, then every budget from the root down to `budget`: its month-end forecast
+/// with `pool`'s current rate replaced by `added` (the change's cost for the rest of the month), plus
+/// what changes still in flight have reserved. Without a `pool` the change adds to every current rate.
+fn check_budgets(state: &AppState, held: &Held, budget: Option<&str>, pool: Option<&str>, added: Decimal, override_ok: bool) -> anyhow::Result<BudgetLevels> {
+    use crate::budget::hierarchy::GLOBAL;
+    let settings = state.budget.current();
+    let mut out = BudgetLevels { levels: Vec::new(), verdict: Ok(()), warnings: Vec::new() };
+    let mut level = |name: &str, policy: crate::budget::BudgetPolicy, policy_name: Option<&str>, spend: crate::budget::ledger::Spend| {
+        let reserved = held.usd((name != GLOBAL).then_some(name));
+        let added = added + reserved;
+        let projected = spend.projected_eom_usd + added;
+        let verdict = crate::budget::enforce_budget(&policy, projected, override_ok);
+        out.levels.push(json!({
+            "budget": name, "monthly_usd_limit": policy.monthly_usd_limit, "policy": policy_name,
+            "projected_eom_usd": projected, "range_usd": [spend.forecast.low_usd + added, spend.forecast.high_usd + added], "reserved_usd": reserved, "allowed": verdict.is_ok(),
+        }));
+        if let Some(limit) = policy.monthly_usd_limit.filter(|l| verdict.is_ok() && projected > *l) {
+            out.warnings.push(format!("{} projected at {}, over its {} limit", if name == GLOBAL { "spend".into() } else { format!("budget `{}`", name) },
+                money::format(projected, 2), money::format(limit, 2)));
+        }
+        if out.verdict.is_ok() {
+            out.verdict = verdict.map_err(|e| if name == GLOBAL { e } else { format!("budget `{}`: {}", name, e) });
+        }
+    };
+    if settings.monthly_usd_limit.is_some() {
+        level(GLOBAL, settings.global(), settings.policy.as_deref(), match pool { Some(p) => held.ledger().spend_without(p)?, None => held.ledger().spend()? });
+    }
+    for (name, b) in budget.map(|p| settings.budgets.chain(p)).unwrap_or_default() {
+        level(name, b.policy(), Some(b.policy.as_deref().unwrap_or("soft")), held.ledger().spend_in(name, pool)?);
+    }
+    Ok(out)
+}
+
+/// Cost of running at `hourly` from now to the end of the month, while `schedule` allows.
+fn rest_of_month(hourly: Decimal, schedule: &crate::budget::schedule::Schedule) -> Decimal {
+    let now = crate::util::now_ms() as u64;
+    hourly * dec(schedule.running_hours(now, crate::budget::ledger::month_bounds(now).1))
+}
+
+/// `replicas` (default 1); a count no `u32` holds is rejected rather than checked and priced as a smaller one.
+fn replicas_arg(args: &Value) -> Result<u32, EctusError> {
+    match args.get("replicas").and_then(|v| v.as_u64()) {
+        Some(n) => u32::try_from(n).map_err(|_| EctusError::Input(format!("`replicas` {} is out of range", n))),
+        None => Ok(1),
+    }
+}
+
+/// Replicas and per-replica resources a `pool_ensure` spec runs. A prebuilt Deployment (`kind: Deployment`)
+/// is applied as written, so its own `spec.replicas` and containers are what is checked and priced.
+fn pool_shape(spec: &Value) -> Result<(u32, crate::budget::Resources), EctusError> {
+    use crate::budget::Resources;
+    if spec.get("kind").and_then(|k| k.as_str()) != Some("Deployment") {
+        return Ok((replicas_arg(spec)?, Resources::from_value(spec.get("resources")).map_err(EctusError::Input)?));
+    }
+    let replicas = replicas_arg(spec.get("spec").unwrap_or(&Value::Null))?;
+    let containers = spec.pointer("/spec/template/spec/containers").and_then(|c| c.as_array()).map(Vec::as_slice).unwrap_or_default();
+    Ok((replicas, Resources::from_containers(containers).map_err(EctusError::Input)?))
+}
+
+/// Hours in `key` (default `default_hours`), from 0 to `MAX_DURATION_HOURS`: a negative duration would price a change below zero.
+fn hours_arg(args: &Value, key: &str, default_hours: f32) -> Result<f32, EctusError> {
+    use crate::budget::MAX_DURATION_HOURS;
+    let hours = args.get(key).and_then(|v| v.as_f64()).unwrap_or(default_hours as f64);
+    if hours.is_nan() || hours < 0.0 {
+        return Err(EctusError::Input(format!("`{}` must be a number of hours, 0 or more", key)));
+    }
+    if hours > MAX_DURATION_HOURS {
+        return Err(EctusError::Input(format!("`{}` is over {} hours", key, MAX_DURATION_HOURS)));
+    }
+    Ok(hours as f32)
+}
+
+/// `duration_hours` (default `default_hours`), `schedule` and `month` (`YYYY-MM`, where the duration starts) of an estimate.
+fn usage_from_args(args: &Value, default_hours: f32) -> Result<crate::budget::Usage, EctusError> {
+    use crate::budget::{schedule, Usage};
+    let mut usage = Usage::hours(hours_arg(args, "duration_hours", default_hours)?);
+    usage.schedule = schedule::Schedule::from_value(args.get("schedule").unwrap_or(&Value::Null)).map_err(EctusError::Input)?;
+    if let Some(m) = args.get("month").and_then(|v| v.as_str()) {
+        usage.start_ms = schedule::parse_month(m).map_err(EctusError::Input)?;
+    }
+    Ok(usage)
+}
+
+async fn orchestrator_scale(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::budget::{Resources, estimate_usage};
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let replicas = replicas_arg(&args)?;
+    let resources = Resources::from_value(args.get("resources")).map_err(EctusError::Input)?;
+    let usage = usage_from_args(&args, 24.0)?;
     let budget_enforce = args.get("budget_enforce").and_then(|v| v.as_bool()).unwrap_or(true);
     let override_ok = args.get("override").and_then(|v| v.as_bool()).unwrap_or(false);
+    let idle_minutes = args.get("idle_minutes").and_then(|v| v.as_u64());
+    let dry_run = args.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, Some(replicas))?;
+    let key = PoolKey::new(backend, &ctx);
+    let budget = budget_for(state, &args, Some(&key.id()))?;
+    // an autoscaler may run the pool up to its maximum, so that is what is checked and booked
+    let max_replicas = orch.autoscaled_max(&ctx, replicas).await?;
+    let billed = max_replicas.unwrap_or(replicas);
 
-    let est = estimate_cost(backend, replicas, &resources, duration_hours);
-    info!(backend, replicas, breakdown=%est.breakdown, monthly=%est.monthly_projected_usd, "cost estimated");
+    let est = estimate_usage(backend, billed, &resources, &usage);
+    info!(backend, replicas, billed, breakdown=%est.breakdown, monthly=%est.monthly_projected_usd, "cost estimated");
+    // checked and reserved atomically, so a concurrent call sees this change's spend
+    let added = rest_of_month(est.hourly_total_usd, &usage.schedule);
+    let (levels, reservation) = {
+        let held = reservations::lock(&state.ledger);
+        let levels = check_budgets(state, &held, budget.as_deref(), Some(&key.id()), added, override_ok)?;
+        let reserve = !dry_run && (levels.verdict.is_ok() || !budget_enforce);
+        let reservation = reserve.then(|| held.hold(&key.id(), budget.as_deref(), added));
+        (levels, reservation)
+    };
+
+    if dry_run {
+        let preview = orch.preview_scale(&ctx, replicas).await?;
+        return Ok(dry_run_report("scale", backend, &preview, (replicas, max_replicas), &resources, &usage, &levels).to_string());
+    }
 
     if budget_enforce {
-        let policy = BudgetPolicy { monthly_usd_limit: cfg.budget_limit, policy: cfg.budget_policy.as_deref().map(|p| if p.eq_ignore_ascii_case("hard") { PolicyKind::Hard } else { PolicyKind::Soft }) };
-        if let Err(msg) = enforce_budget(&policy, est.monthly_projected_usd, override_ok) {
+        if let Err(msg) = levels.verdict {
             warn!(%msg, "scale blocked by budget policy");
-            return Ok(json!({"ok": false, "reason": msg, "estimate": {"monthly": est.monthly_projected_usd}}).to_string());
+            return Ok(json!({"ok": false, "reason": msg, "estimate": {"monthly": est.monthly_projected_usd}, "budgets": levels.levels}).to_string());
         }
     }
 
-    let ctx = crate::orchestrator::OrchestratorContext {
-        namespace: args.get("namespace").and_then(|v| v.as_str().map(|s| s.to_string())),
-        name: args.get("name").and_then(|v| v.as_str().map(|s| s.to_string())),
-        model: args.get("model").and_then(|v| v.as_str().map(|s| s.to_string())),
-    };
-    let orch = crate::orchestrator::new_backend(backend);
+    // released if the orchestrator call fails
     let res = orch.scale(&ctx, replicas).await?;
-    Ok(json!({"ok": true, "action": "scale", "backend": backend, "replicas": replicas, "result": res, "estimate": {"monthly": est.monthly_projected_usd}}).to_string())
+    let plan = Plan { schedule: args.get("schedule").filter(|s| !s.is_null()).cloned(), until_ms: None };
+    book(state, &key.id(), backend, billed, &resources, budget.as_deref(), plan);
+    if let Some(r) = reservation { r.commit(); }
+    state.idle.record(key, replicas, est.hourly_total_usd, idle_minutes);
+    Ok(json!({
+        "ok": true, "action": "scale", "backend": backend, "replicas": replicas, "max_replicas": max_replicas, "result": res, "budget": budget,
+        "estimate": {"monthly": est.monthly_projected_usd, "duration_total": est.duration_total_usd, "assumptions": est.assumptions},
+    }).to_string())
 }
 
-async fn orchestrator_status(cfg: &Config, args: Value) -> anyhow::Result<String> {
-    let backend = args.get("backend").and_then(|v| v.as_str()).unwrap_or(&cfg.orchestrator_backend);
-    let ctx = crate::orchestrator::OrchestratorContext {
-        namespace: args.get("namespace").and_then(|v| v.as_str().map(|s| s.to_string())),
-        name: args.get("name").and_then(|v| v.as_str().map(|s| s.to_string())),
-        model: args.get("model").and_then(|v| v.as_str().map(|s| s.to_string())),
-    };
-    let orch = crate::orchestrator::new_backend(backend);
+
+/// Diff and cost delta for a previewed operation, priced at the autoscaler's maximum when there is one;
+/// the budget verdict is reported, not enforced.
+fn dry_run_report(action: &str, backend: &str, preview: &crate::orchestrator::Preview, (replicas, max_replicas): (u32, Option<u32>), resources: &crate::budget::Resources, usage: &crate::budget::Usage, levels: &BudgetLevels) -> Value {
+    use crate::budget::estimate_usage;
+    let proposed = estimate_usage(backend, max_replicas.unwrap_or(replicas), resources, usage);
+    let current = estimate_usage(backend, preview.current_replicas, resources, usage);
+    let verdict = levels.verdict.clone();
+    json!({
+        "ok": true, "dry_run": true, "action": action, "backend": backend,
+        "replicas": {"current": preview.current_replicas, "proposed": replicas, "max": max_replicas},
+        "diff": crate::util::json_diff(preview.live.as_ref().unwrap_or(&Value::Null), &preview.proposed),
+        "estimate": {
+            "monthly": proposed.monthly_projected_usd,
+            "current_monthly": current.monthly_projected_usd,
+            "delta_monthly": proposed.monthly_projected_usd - current.monthly_projected_usd,
+            "delta_hourly": proposed.hourly_total_usd - current.hourly_total_usd,
+            "assumptions": proposed.assumptions,
+        },
+        "budget": {"allowed": verdict.is_ok(), "reason": verdict.err(), "levels": levels.levels},
+    })
+}
+
+async fn orchestrator_status(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, None)?;
     let status = orch.status(&ctx).await?;
-    Ok(json!({"backend": backend, "status": status}).to_string())
+    Ok(json!({"backend": backend, "status": status, "capabilities": orch.capabilities()}).to_string())
 }
 
-async fn pool_ensure(cfg: &Config, args: Value) -> anyhow::Result<String> {
-    let backend = args.get("backend").and_then(|v| v.as_str()).unwrap_or(&cfg.orchestrator_backend);
-    let spec = args.get("spec").cloned().unwrap_or(json!({})).to_string();
-    let ctx = crate::orchestrator::OrchestratorContext {
-        namespace: args.get("namespace").and_then(|v| v.as_str().map(|s| s.to_string())),
-        name: args.get("name").and_then(|v| v.as_str().map(|s| s.to_string())),
-        model: args.get("model").and_then(|v| v.as_str().map(|s| s.to_string())),
+async fn orchestrator_backends(cfg: &Config, state: &AppState) -> anyhow::Result<String> {
+    let mut backends = Vec::new();
+    for name in crate::orchestrator::available_backends() {
+        backends.push(serde_json::to_value(crate::orchestrator::new_backend(state, name)?.capabilities())?);
+    }
+    Ok(json!({"default": cfg.orchestrator_backend, "backends": backends}).to_string())
+}
+
+async fn pool_ensure(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::budget::estimate_cost;
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let spec_v = args.get("spec").cloned().unwrap_or(json!({}));
+    let spec = spec_v.to_string();
+    let idle_minutes = args.get("idle_minutes").and_then(|v| v.as_u64());
+    let dry_run = args.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+    let (replicas, resources) = pool_shape(&spec_v)?;
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, Some(replicas))?;
+    let key = PoolKey::new(backend, &ctx);
+    let budget = budget_for(state, &args, Some(&key.id()))?;
+    let usage = usage_from_args(&args, 24.0)?;
+    let budget_enforce = args.get("budget_enforce").and_then(|v| v.as_bool()).unwrap_or(true);
+    let override_ok = args.get("override").and_then(|v| v.as_bool()).unwrap_or(false);
+    let est = estimate_cost(backend, replicas, &resources, 1.0);
+    // checked and reserved atomically, as for `orchestrator_scale`
+    let added = rest_of_month(est.hourly_total_usd, &usage.schedule);
+    let (levels, reservation) = {
+        let held = reservations::lock(&state.ledger);
+        let levels = check_budgets(state, &held, budget.as_deref(), Some(&key.id()), added, override_ok)?;
+        let reserve = !dry_run && (levels.verdict.is_ok() || !budget_enforce);
+        let reservation = reserve.then(|| held.hold(&key.id(), budget.as_deref(), added));
+        (levels, reservation)
     };
-    let orch = crate::orchestrator::new_backend(backend);
+    if dry_run {
+        let preview = orch.preview_pool(&ctx, &spec).await?;
+        return Ok(dry_run_report("pool_ensure", backend, &preview, (replicas, None), &resources, &usage, &levels).to_string());
+    }
+    if budget_enforce {
+        if let Err(msg) = levels.verdict {
+            warn!(%msg, "pool_ensure blocked by budget policy");
+            return Ok(json!({"ok": false, "reason": msg, "estimate": {"monthly": est.monthly_projected_usd}, "budgets": levels.levels}).to_string());
+        }
+    }
     let res = orch.ensure_pool(&ctx, &spec).await?;
+    let plan = Plan { schedule: args.get("schedule").filter(|s| !s.is_null()).cloned(), until_ms: None };
+    book(state, &key.id(), backend, replicas, &resources, budget.as_deref(), plan);
+    if let Some(r) = reservation { r.commit(); }
+    state.idle.record(key, replicas, est.hourly_total_usd, idle_minutes);
+    Ok(json!({"ok": true, "backend": backend, "result": res, "budget": budget}).to_string())
+}
+
+async fn pool_delete(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    if ctx.name.is_none() { return Err(EctusError::Input("pool_delete requires `name`".into()).into()); }
+    state.policy.check(backend, &ctx, None)?;
+    let res = orch.delete_pool(&ctx).await.map_err(|e| EctusError::Backend(e.to_string()))?;
+    let key = PoolKey::new(backend, &ctx);
+    state.ledger.rate(&key.id(), 0, Decimal::ZERO, None);
+    state.idle.forget(&key);
     Ok(json!({"backend": backend, "result": res}).to_string())
 }
 
-async fn cost_estimate(args: Value) -> anyhow::Result<String> {
+async fn autoscaler_ensure(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::orchestrator::AutoscalerSpec;
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let spec: AutoscalerSpec = serde_json::from_value(args.clone()).map_err(|e| EctusError::Input(e.to_string()))?;
+    spec.validate().map_err(EctusError::Input)?;
+    let ctx = ctx_from_args(&args);
+    // the HPA may move the pool anywhere between its bounds, so both must be allowed
+    state.policy.check(backend, &ctx, Some(spec.min_replicas))?;
+    state.policy.check(backend, &ctx, Some(spec.max_replicas))?;
+    // and it may run `max_replicas` all month, which is what is checked and booked
+    let resources = crate::budget::Resources::from_value(args.get("resources")).map_err(EctusError::Input)?;
+    let usage = usage_from_args(&args, 24.0)?;
+    let budget_enforce = args.get("budget_enforce").and_then(|v| v.as_bool()).unwrap_or(true);
+    let override_ok = args.get("override").and_then(|v| v.as_bool()).unwrap_or(false);
+    let key = PoolKey::new(backend, &ctx);
+    let budget = budget_for(state, &args, Some(&key.id()))?;
+    let est = crate::budget::estimate_cost(backend, spec.max_replicas, &resources, 1.0);
+    let added = rest_of_month(est.hourly_total_usd, &usage.schedule);
+    let reservation = {
+        let held = reservations::lock(&state.ledger);
+        let levels = check_budgets(state, &held, budget.as_deref(), Some(&key.id()), added, override_ok)?;
+        if budget_enforce {
+            if let Err(msg) = levels.verdict {
+                warn!(%msg, "autoscaler blocked by budget policy");
+                return Ok(json!({"ok": false, "reason": msg, "estimate": {"monthly": est.monthly_projected_usd}, "budgets": levels.levels}).to_string());
+            }
+        }
+        held.hold(&key.id(), budget.as_deref(), added)
+    };
+    let res = orch.ensure_autoscaler(&ctx, &spec).await?;
+    let plan = Plan { schedule: args.get("schedule").filter(|s| !s.is_null()).cloned(), until_ms: None };
+    book(state, &key.id(), backend, spec.max_replicas, &resources, budget.as_deref(), plan);
+    reservation.commit();
+    Ok(json!({"backend": backend, "result": res, "budget": budget, "estimate": {"monthly_at_max": est.monthly_projected_usd}}).to_string())
+}
+
+async fn autoscaler_delete(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, None)?;
+    let res = orch.delete_autoscaler(&ctx).await?;
+    Ok(json!({"backend": backend, "result": res}).to_string())
+}
+
+async fn pool_logs(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::orchestrator::LogQuery;
+    use crate::util::{redact_secrets, truncate_tail};
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, None)?;
+    let query: LogQuery = serde_json::from_value(args.clone()).map_err(|e| EctusError::Input(e.to_string()))?;
+    let query = query.clamp();
+    let mut diag = orch.pool_logs(&ctx, &query).await?;
+    // Enforce the byte budget here too, whatever the backend honoured, and never pass secrets on
+    let per_pod = query.max_bytes / diag.pods.len().max(1);
+    for p in diag.pods.iter_mut() {
+        let (logs, cut) = truncate_tail(&redact_secrets(&p.logs), per_pod);
+        p.logs = logs;
+        p.truncated |= cut;
+    }
+    for e in diag.events.iter_mut() {
+        e.message = redact_secrets(&e.message);
+    }
+    Ok(json!({"backend": backend, "query": query, "pods": diag.pods, "events": diag.events}).to_string())
+}
+
+/// Ledger id of a batch job, kept apart from a pool of the same name.
+fn job_id(backend: &str, ctx: &OrchestratorContext) -> String {
+    format!("job:{}", PoolKey::new(backend, ctx).id())
+}
+
+const JOB_POLL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Kept short: calls are served one at a time, so a long wait would stall every other tool. Clients poll for longer.
+const MAX_JOB_WAIT_SECS: u64 = 6;
+
+async fn job_submit(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
     use crate::budget::{Resources, estimate_cost};
+    use crate::orchestrator::JobSpec;
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    if ctx.name.is_none() { return Err(EctusError::Input("job_submit requires `name`".into()).into()); }
+    let spec: JobSpec = serde_json::from_value(args.get("spec").cloned().unwrap_or(json!({}))).map_err(|e| EctusError::Input(e.to_string()))?;
+    spec.validate().map_err(EctusError::Input)?;
+    state.policy.check(backend, &ctx, Some(spec.parallelism))?;
+
+    // Pods run `parallelism` at a time for the expected wall-clock duration
+    let expected_hours = hours_arg(&args, "expected_hours", 1.0)?;
+    let resources = Resources::from_value(spec.pod.get("resources")).map_err(EctusError::Input)?;
+    let est = estimate_cost(backend, spec.parallelism, &resources, 1.0);
+    let job_usd = est.hourly_total_usd * dec(expected_hours as f64);
+    let budget_enforce = args.get("budget_enforce").and_then(|v| v.as_bool()).unwrap_or(true);
+    let override_ok = args.get("override").and_then(|v| v.as_bool()).unwrap_or(false);
+    let budget = budget_for(state, &args, None)?;
+    let reservation = {
+        let held = reservations::lock(&state.ledger);
+        if budget_enforce {
+            let levels = check_budgets(state, &held, budget.as_deref(), Some(&job_id(backend, &ctx)), job_usd, override_ok)?;
+            if let Err(msg) = levels.verdict {
+                warn!(%msg, "job blocked by budget policy");
+                return Ok(json!({"ok": false, "reason": msg, "estimate": {"job_usd": job_usd}, "budgets": levels.levels}).to_string());
+            }
+        }
+        held.hold(&job_id(backend, &ctx), budget.as_deref(), job_usd)
+    };
+    let res = orch.submit_job(&ctx, &spec).await?;
+    // the job is expected to stop after `expected_hours`
+    let plan = Plan { schedule: None, until_ms: Some((crate::util::now_ms() as u64).saturating_add((expected_hours as f64 * 3_600_000.0) as u64)) };
+    book(state, &job_id(backend, &ctx), backend, spec.parallelism, &resources, budget.as_deref(), plan);
+    reservation.commit();
+    Ok(json!({
+        "ok": true, "backend": backend, "result": res, "budget": budget,
+        "job": {
+            "parallelism": spec.parallelism, "completions": spec.completions, "backoff_limit": spec.backoff_limit,
+            "ttl_seconds_after_finished": spec.ttl_seconds_after_finished, "active_deadline_seconds": spec.active_deadline_seconds,
+        },
+        "estimate": {"hourly_usd": est.hourly_total_usd, "expected_hours": expected_hours, "job_usd": job_usd},
+    }).to_string())
+}
+
+async fn job_status(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::budget::estimate_cost;
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    state.policy.check(backend, &ctx, None)?;
+    let wait = args.get("wait_seconds").and_then(|v| v.as_u64()).unwrap_or(0).min(MAX_JOB_WAIT_SECS);
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(wait);
+    let mut progress = orch.job_status(&ctx).await?;
+    while !progress.finished() && tokio::time::Instant::now() + JOB_POLL <= deadline {
+        tokio::time::sleep(JOB_POLL).await;
+        progress = orch.job_status(&ctx).await?;
+    }
+    // Upper bound: every parallel slot billed for the whole elapsed time
+    if progress.finished() {
+        state.ledger.rate(&job_id(backend, &ctx), 0, Decimal::ZERO, None);
+    }
+    // resources read back from the backend are priced only when they pass the same checks as a submission
+    let cost = progress.resources.as_ref().filter(|r| r.validate().is_ok()).map(|r| {
+        let hourly = estimate_cost(backend, progress.parallelism, r, 1.0).hourly_total_usd;
+        json!({"hourly_usd": hourly, "so_far_usd": hourly * hours_from_ms(progress.elapsed_seconds.unwrap_or(0) * 1000)})
+    });
+    Ok(json!({"backend": backend, "job": progress, "finished": progress.finished(), "cost": cost}).to_string())
+}
+
+async fn job_delete(cfg: &Config, state: &AppState, args: Value) -> anyhow::Result<String> {
+    let (orch, backend) = backend_from_args(cfg, state, &args)?;
+    let ctx = ctx_from_args(&args);
+    if ctx.name.is_none() { return Err(EctusError::Input("job_delete requires `name`".into()).into()); }
+    state.policy.check(backend, &ctx, None)?;
+    let res = orch.delete_job(&ctx).await?;
+    state.ledger.rate(&job_id(backend, &ctx), 0, Decimal::ZERO, None);
+    Ok(json!({"backend": backend, "result": res}).to_string())
+}
+
+async fn cost_estimate(args: Value) -> anyhow::Result<String> {
+    use crate::budget::{Resources, estimate_usage};
     let backend = args.get("backend").and_then(|v| v.as_str()).unwrap_or("kubernetes");
-    let replicas = args.get("replicas").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
-    let resources: Resources = serde_json::from_value(args.get("resources").cloned().unwrap_or(json!({"cpu":"1","memory":"1Gi"})))?;
-    let duration_hours = args.get("duration_hours").and_then(|v| v.as_f64()).unwrap_or(24.0) as f32;
-    let est = estimate_cost(backend, replicas, &resources, duration_hours);
-    Ok(json!({"backend": backend, "replicas": replicas, "hourly_total_usd": est.hourly_total_usd, "monthly_projected_usd": est.monthly_projected_usd, "breakdown": est.breakdown}).to_string())
+    let replicas = replicas_arg(&args)?;
+    let resources = Resources::from_value(args.get("resources")).map_err(EctusError::Input)?;
+    let est = estimate_usage(backend, replicas, &resources, &usage_from_args(&args, 24.0)?);
+    Ok(json!({
+        "backend": backend, "replicas": replicas, "currency": money::currency(),
+        "hourly_total_usd": est.hourly_total_usd,
+        "duration_hours": est.duration_hours, "running_hours": est.running_hours, "duration_total_usd": est.duration_total_usd,
+        "month": est.month, "monthly_running_hours": est.monthly_running_hours, "monthly_projected_usd": est.monthly_projected_usd,
+        "breakdown": est.breakdown, "assumptions": est.assumptions,
+    }).to_string())
 }
 
-async fn budget_config(cfg: &Config, _args: Value) -> anyhow::Result<String> {
-    Ok(json!({"policy": cfg.budget_policy, "monthly_usd_limit": cfg.budget_limit}).to_string())
+async fn pricing_rates(args: Value) -> anyhow::Result<String> {
+    use crate::budget::pricing;
+    let catalog = if args.get("reload").and_then(|v| v.as_bool()).unwrap_or(false) { pricing::reload()? } else { pricing::current() };
+    let region = args.get("region").and_then(|v| v.as_str());
+    let class = args.get("instance_class").and_then(|v| v.as_str());
+    let backends: Vec<&str> = match args.get("backend").and_then(|v| v.as_str()) {
+        Some(b) => vec![b],
+        None => catalog.backends.keys().map(String::as_str).filter(|b| *b != "*").collect(),
+    };
+    let rates: Vec<_> = backends.iter().map(|b| catalog.rates(b, region, class)).collect();
+    let mut out = json!({
+        "source": catalog.source.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "built-in".into()),
+        "loaded_at_ms": catalog.loaded_at_ms,
+        "currency": money::currency(),
+        "rates": rates,
+        "models": catalog.models,
+    });
+    if let Some(gpu_type) = args.get("gpu_type").and_then(|v| v.as_str()) {
+        out["gpu_hour"] = json!(rates.iter().map(|r| (r.backend.clone(), r.gpu_rate(Some(gpu_type)))).collect::<std::collections::BTreeMap<_, _>>());
+    }
+    Ok(out.to_string())
+}
+
+/// Without changes, the settings in effect; changes need an admin's `admin_token` and apply to the next check.
+async fn budget_config(state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::budget::store::Change;
+    let change = Change::from_args(&args).map_err(EctusError::Input)?;
+    let mut changed = Vec::new();
+    if !change.is_empty() {
+        let actor = state.budget.authorize(args.get("admin_token").and_then(|v| v.as_str())).map_err(EctusError::Unauthorized)?;
+        let client = state.client.read().unwrap().clone();
+        changed = state.budget.apply(&change, &actor, client.as_deref()).map_err(|e| EctusError::Input(e.to_string()))?;
+    }
+    let settings = state.budget.current();
+    Ok(json!({
+        "policy": settings.policy, "monthly_usd_limit": settings.monthly_usd_limit, "currency": money::currency(), "budgets": settings.budgets.describe(),
+        "writable": state.budget.writable(), "persisted_to": state.budget.path(),
+        "changed": changed, "audit": state.budget.audit(20),
+    }).to_string())
 }
 
-async fn budget_status(cfg: &Config) -> anyhow::Result<String> {
-    Ok(json!({"month_to_date_usd": 0.0, "projected_eom_usd": cfg.budget_limit.unwrap_or(0.0), "headroom_usd": cfg.budget_limit.unwrap_or(0.0)}).to_string())
+async fn budget_status(state: &AppState, args: Value) -> anyhow::Result<String> {
+    use crate::budget::hierarchy::within;
+    let spend = state.ledger.spend()?;
+    let settings = state.budget.current();
+    let under = args.get("budget").and_then(|v| v.as_str()).map(|b| settings.budgets.known(b)).transpose().map_err(EctusError::Input)?;
+    let mut budgets = Vec::new();
+    for (name, b) in settings.budgets.budgets.iter().filter(|(name, _)| under.as_deref().is_none_or(|u| within(name, u))) {
+        let s = state.ledger.spend_in(name, None)?;
+        budgets.push(json!({
+            "budget": name, "monthly_usd_limit": b.monthly_usd_limit, "policy": b.policy.as_deref().unwrap_or("soft"),
+            "month_to_date_usd": s.month_to_date_usd, "projected_eom_usd": s.projected_eom_usd,
+            "range_usd": [s.forecast.low_usd, s.forecast.high_usd],
+            "headroom_usd": b.monthly_usd_limit.map(|l| l - s.projected_eom_usd),
+        }));
+    }
+    Ok(json!({
+        "month": spend.month,
+        "currency": spend.currency,
+        "month_to_date_usd": spend.month_to_date_usd,
+        "compute_usd": spend.compute_usd,
+        "charges_usd": spend.charges_usd,
+        "current_hourly_usd": spend.current_hourly_usd,
+        "projected_eom_usd": spend.projected_eom_usd,
+        "forecast": spend.forecast,
+        "monthly_usd_limit": settings.monthly_usd_limit,
+        "headroom_usd": settings.monthly_usd_limit.map(|l| l - spend.projected_eom_usd),
+        "running_pools": spend.running_pools,
+        "models": spend.models,
+        "budgets": budgets,
+        "ledger": {"path": state.ledger.path(), "persistent": state.ledger.path().is_some()},
+        "reservations": reservations::describe(&state.ledger),
+        "idle_savings_usd": state.idle.savings_usd(),
+        "idle_pools": state.idle.describe(),
+    }).to_string())
 }